@@ -0,0 +1,4 @@
+// sqlx::migrate! 在編譯時嵌入 migrations/，新增或修改遷移檔時需要重新編譯
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -0,0 +1,94 @@
+-- 初始資料表結構，與 backup_file.sql 相同
+-- 既有資料庫中已存在的資料表不會被修改，只補上程式依賴但舊資料庫可能缺少的欄位與索引
+
+CREATE TABLE IF NOT EXISTS EnrollmentStatus (
+    SN INT AUTO_INCREMENT PRIMARY KEY, -- 自動遞增的主鍵
+    Status VARCHAR(30) NOT NULL, -- 狀態
+    Notes VARCHAR(255) -- 備註欄
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS StudentAttributes (
+    SN INT AUTO_INCREMENT PRIMARY KEY, -- 自動遞增的主鍵
+    Attribute VARCHAR(50) NOT NULL, -- 學生屬性
+    Notes VARCHAR(255) -- 備註欄
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS StudentInfo (
+    StudentID VARCHAR(20) PRIMARY KEY, -- 學號，主鍵
+    Name VARCHAR(50) NOT NULL, -- 姓名
+    EnrollmentStatus_SN INT, -- 外來鍵，對應 EnrollmentStatus 的 SN
+    StudentAttribute_SN INT, -- 外來鍵，對應 StudentAttributes 的 SN
+    IsPassed BOOLEAN DEFAULT FALSE, -- 是否通過，預設否
+    PassingCriteria VARCHAR(255) DEFAULT NULL, -- 通過條件，預設為 NULL
+    Notes VARCHAR(255), -- 備註
+    FOREIGN KEY (EnrollmentStatus_SN) REFERENCES EnrollmentStatus(SN),
+    FOREIGN KEY (StudentAttribute_SN) REFERENCES StudentAttributes(SN)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS ExamSessions (
+    SN INT AUTO_INCREMENT PRIMARY KEY, -- 自動遞增的主鍵
+    ExamDate DATE NOT NULL, -- 考試日期
+    ExamType ENUM('官辦', '自辦') NOT NULL, -- 考試類別，限定值
+    Notes VARCHAR(255), -- 備註欄
+    UNIQUE KEY unique_exam (ExamDate, ExamType) -- 同一天同類別只有一個場次，/api/add_exam 依此判斷重複
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS ExamAttendance (
+    SN INT AUTO_INCREMENT PRIMARY KEY, -- 自動遞增的主鍵
+    ExamSession_SN INT NOT NULL, -- 外來鍵，對應 ExamSessions 的 SN
+    StudentID VARCHAR(20) NOT NULL, -- 外來鍵，對應 StudentInfo 的 StudentID
+    IsAbsent BOOLEAN DEFAULT FALSE, -- 是否缺考，預設否
+    IsExcused BOOLEAN DEFAULT FALSE, -- 是否請假，預設否
+    CorrectAnswersCount INT DEFAULT 0, -- 答對題數，預設為 0
+    Notes VARCHAR(255), -- 備註
+    UNIQUE KEY ExamSession_SN (ExamSession_SN, StudentID), -- 同一場次每位學生只有一筆成績
+    FOREIGN KEY (ExamSession_SN) REFERENCES ExamSessions(SN),
+    FOREIGN KEY (StudentID) REFERENCES StudentInfo(StudentID)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS ScholarshipRecord (
+    StudentID VARCHAR(20) PRIMARY KEY, -- 主鍵 + 外來鍵（保證一位學生最多一筆）
+    CorrectAnswersCount INT NOT NULL, -- 答對題數（根據這個發獎）
+    ReceivedDate DATE NOT NULL, -- 領獎日期
+    Notes VARCHAR(255), -- 備註欄（選填）
+    ScholarshipAmount INT NOT NULL DEFAULT 0 COMMENT '獎學金金額（單位：元）',
+    FOREIGN KEY (StudentID) REFERENCES StudentInfo(StudentID) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS users (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    username VARCHAR(255) NOT NULL UNIQUE,
+    password VARCHAR(255) NOT NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+-- 依 README 建立的舊資料庫沒有 ScholarshipAmount
+SET @stmt = IF(
+    (SELECT COUNT(*) FROM information_schema.COLUMNS
+     WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'ScholarshipRecord' AND COLUMN_NAME = 'ScholarshipAmount') = 0,
+    'ALTER TABLE ScholarshipRecord ADD COLUMN ScholarshipAmount INT NOT NULL DEFAULT 0 COMMENT ''獎學金金額（單位：元）''',
+    'DO 0'
+);
+PREPARE stmt FROM @stmt;
+EXECUTE stmt;
+DEALLOCATE PREPARE stmt;
+
+-- 依 README 建立的舊資料庫沒有場次的唯一索引，若已有重複的場次需先手動合併
+SET @stmt = IF(
+    (SELECT COUNT(*) FROM information_schema.STATISTICS
+     WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'ExamSessions' AND INDEX_NAME = 'unique_exam') = 0,
+    'ALTER TABLE ExamSessions ADD UNIQUE KEY unique_exam (ExamDate, ExamType)',
+    'DO 0'
+);
+PREPARE stmt FROM @stmt;
+EXECUTE stmt;
+DEALLOCATE PREPARE stmt;
+
+SET @stmt = IF(
+    (SELECT COUNT(*) FROM information_schema.STATISTICS
+     WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'ExamAttendance' AND NON_UNIQUE = 0 AND COLUMN_NAME = 'StudentID') = 0,
+    'ALTER TABLE ExamAttendance ADD UNIQUE KEY unique_attendance (ExamSession_SN, StudentID)',
+    'DO 0'
+);
+PREPARE stmt FROM @stmt;
+EXECUTE stmt;
+DEALLOCATE PREPARE stmt;
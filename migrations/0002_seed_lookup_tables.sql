@@ -0,0 +1,12 @@
+-- 程式中以固定的 SN 對應在學狀況與學生屬性（1 在學、2 休學、3 退學；1 本系、2 外系、3 外校）
+-- 已存在的資料列不覆寫，保留原本的備註
+
+INSERT IGNORE INTO EnrollmentStatus (SN, Status) VALUES
+    (1, '在學'),
+    (2, '休學'),
+    (3, '退學');
+
+INSERT IGNORE INTO StudentAttributes (SN, Attribute) VALUES
+    (1, '本系'),
+    (2, '外系'),
+    (3, '外校');
@@ -0,0 +1,32 @@
+-- 可設定的通過規則，以及依入學年度套用規則所需的欄位
+
+CREATE TABLE IF NOT EXISTS PassCriteria (
+    SN INT AUTO_INCREMENT PRIMARY KEY, -- 自動遞增的主鍵
+    Name VARCHAR(50) NOT NULL, -- 規則名稱
+    RuleDefinition TEXT NOT NULL, -- 規則內容(JSON)
+    EntranceYearFrom INT DEFAULT NULL, -- 適用入學年度起，NULL 代表不限
+    EntranceYearTo INT DEFAULT NULL, -- 適用入學年度迄，NULL 代表不限
+    IsActive BOOLEAN DEFAULT FALSE, -- 是否為目前使用的規則
+    CreatedAt DATETIME DEFAULT CURRENT_TIMESTAMP, -- 建立時間
+    Notes VARCHAR(255) -- 備註欄
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+SET @stmt = IF(
+    (SELECT COUNT(*) FROM information_schema.COLUMNS
+     WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'PassCriteria' AND COLUMN_NAME = 'EntranceYearFrom') = 0,
+    'ALTER TABLE PassCriteria ADD COLUMN EntranceYearFrom INT DEFAULT NULL, ADD COLUMN EntranceYearTo INT DEFAULT NULL',
+    'DO 0'
+);
+PREPARE stmt FROM @stmt;
+EXECUTE stmt;
+DEALLOCATE PREPARE stmt;
+
+SET @stmt = IF(
+    (SELECT COUNT(*) FROM information_schema.COLUMNS
+     WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'StudentInfo' AND COLUMN_NAME = 'EntranceYear') = 0,
+    'ALTER TABLE StudentInfo ADD COLUMN EntranceYear INT DEFAULT NULL',
+    'DO 0'
+);
+PREPARE stmt FROM @stmt;
+EXECUTE stmt;
+DEALLOCATE PREPARE stmt;
@@ -0,0 +1,17 @@
+-- 所有新增、修改、刪除資料的 API 在同一個交易內寫入的稽核紀錄
+
+CREATE TABLE IF NOT EXISTS AuditLog (
+    SN BIGINT AUTO_INCREMENT PRIMARY KEY, -- 自動遞增的主鍵
+    Username VARCHAR(255) NOT NULL, -- 執行操作的使用者
+    Endpoint VARCHAR(100) NOT NULL, -- 呼叫的 API
+    EntityType VARCHAR(50) NOT NULL, -- 異動的資料類型
+    EntityKey VARCHAR(50) NOT NULL, -- 異動資料的主鍵，應考紀錄為「場次SN:學號」
+    StudentID VARCHAR(20), -- 相關的學號，方便依學生查詢
+    BeforeValue TEXT, -- 異動前內容(JSON)，新增時為 NULL
+    AfterValue TEXT, -- 異動後內容(JSON)，刪除時為 NULL
+    CreatedAt DATETIME DEFAULT CURRENT_TIMESTAMP, -- 異動時間
+    INDEX idx_audit_username (Username),
+    INDEX idx_audit_entity (EntityType, EntityKey),
+    INDEX idx_audit_student (StudentID),
+    INDEX idx_audit_created (CreatedAt)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
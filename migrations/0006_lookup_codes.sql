@@ -0,0 +1,50 @@
+-- 註冊狀況與學生屬性改由管理員維護
+-- Code 為前端使用的英文代碼（例如 currentlyEnrolled），名稱與代碼都不可重複
+
+SET @stmt = IF(
+    (SELECT COUNT(*) FROM information_schema.COLUMNS
+     WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'EnrollmentStatus' AND COLUMN_NAME = 'Code') = 0,
+    'ALTER TABLE EnrollmentStatus ADD COLUMN Code VARCHAR(50) NULL UNIQUE AFTER Status',
+    'DO 0'
+);
+PREPARE stmt FROM @stmt;
+EXECUTE stmt;
+DEALLOCATE PREPARE stmt;
+
+SET @stmt = IF(
+    (SELECT COUNT(*) FROM information_schema.COLUMNS
+     WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'StudentAttributes' AND COLUMN_NAME = 'Code') = 0,
+    'ALTER TABLE StudentAttributes ADD COLUMN Code VARCHAR(50) NULL UNIQUE AFTER Attribute',
+    'DO 0'
+);
+PREPARE stmt FROM @stmt;
+EXECUTE stmt;
+DEALLOCATE PREPARE stmt;
+
+SET @stmt = IF(
+    (SELECT COUNT(*) FROM information_schema.STATISTICS
+     WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'EnrollmentStatus' AND INDEX_NAME = 'unique_status') = 0,
+    'ALTER TABLE EnrollmentStatus ADD UNIQUE KEY unique_status (Status)',
+    'DO 0'
+);
+PREPARE stmt FROM @stmt;
+EXECUTE stmt;
+DEALLOCATE PREPARE stmt;
+
+SET @stmt = IF(
+    (SELECT COUNT(*) FROM information_schema.STATISTICS
+     WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'StudentAttributes' AND INDEX_NAME = 'unique_attribute') = 0,
+    'ALTER TABLE StudentAttributes ADD UNIQUE KEY unique_attribute (Attribute)',
+    'DO 0'
+);
+PREPARE stmt FROM @stmt;
+EXECUTE stmt;
+DEALLOCATE PREPARE stmt;
+
+-- 原本寫死在程式中的代碼
+UPDATE EnrollmentStatus SET Code = 'currentlyEnrolled' WHERE Status = '在學' AND Code IS NULL;
+UPDATE EnrollmentStatus SET Code = 'onALeaveOfAbsence' WHERE Status = '休學' AND Code IS NULL;
+UPDATE EnrollmentStatus SET Code = 'droppedOut' WHERE Status = '退學' AND Code IS NULL;
+UPDATE StudentAttributes SET Code = 'departmental' WHERE Attribute = '本系' AND Code IS NULL;
+UPDATE StudentAttributes SET Code = 'interdepartmental' WHERE Attribute = '外系' AND Code IS NULL;
+UPDATE StudentAttributes SET Code = 'externalStudents' WHERE Attribute = '外校' AND Code IS NULL;
@@ -0,0 +1,37 @@
+-- 記錄學生在哪一個場次、哪一天、依哪些條件通過，不再只有 IsPassed 與文字的 PassingCriteria
+-- 既有的資料在伺服器啟動時由背景工作補上，也可以執行 cpe-admin recompute-status
+
+SET @stmt = IF(
+    (SELECT COUNT(*) FROM information_schema.COLUMNS
+     WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'StudentInfo' AND COLUMN_NAME = 'PassedSession_SN') = 0,
+    'ALTER TABLE StudentInfo
+        ADD COLUMN PassedSession_SN INT DEFAULT NULL AFTER PassingCriteria,
+        ADD COLUMN PassedDate DATE DEFAULT NULL AFTER PassedSession_SN,
+        ADD COLUMN PassCriteria_SN INT DEFAULT NULL AFTER PassedDate,
+        ADD COLUMN PassingConditions TEXT DEFAULT NULL AFTER PassCriteria_SN',
+    'DO 0'
+);
+PREPARE stmt FROM @stmt;
+EXECUTE stmt;
+DEALLOCATE PREPARE stmt;
+
+-- 刪除場次時先清空，同一個交易內重新計算後再寫入新的通過場次
+SET @stmt = IF(
+    (SELECT COUNT(*) FROM information_schema.TABLE_CONSTRAINTS
+     WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'StudentInfo' AND CONSTRAINT_NAME = 'fk_passed_session') = 0,
+    'ALTER TABLE StudentInfo ADD CONSTRAINT fk_passed_session FOREIGN KEY (PassedSession_SN) REFERENCES ExamSessions(SN) ON DELETE SET NULL',
+    'DO 0'
+);
+PREPARE stmt FROM @stmt;
+EXECUTE stmt;
+DEALLOCATE PREPARE stmt;
+
+SET @stmt = IF(
+    (SELECT COUNT(*) FROM information_schema.STATISTICS
+     WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'StudentInfo' AND INDEX_NAME = 'idx_passed_date') = 0,
+    'ALTER TABLE StudentInfo ADD INDEX idx_passed_date (PassedDate)',
+    'DO 0'
+);
+PREPARE stmt FROM @stmt;
+EXECUTE stmt;
+DEALLOCATE PREPARE stmt;
@@ -0,0 +1,292 @@
+use actix_session::Session;
+use sqlx::{Error, MySqlConnection};
+use crate::api::lookup::LookupKind;
+
+/// 稽核紀錄的對象，每個變體對應一張資料表與它的主鍵
+#[derive(Debug, Clone)]
+pub enum AuditTarget<'a> {
+    Student(&'a str),
+    ExamSession(i32),
+    ExamAttendance { exam_session_sn: i32, student_id: &'a str },
+    Scholarship(&'a str),
+    PassCriteria(i32),
+    User(&'a str),
+    Lookup(LookupKind, i32),
+}
+
+impl AuditTarget<'_> {
+    pub fn entity_type(&self) -> &'static str {
+        match self {
+            AuditTarget::Student(_) => "student",
+            AuditTarget::ExamSession(_) => "exam_session",
+            AuditTarget::ExamAttendance { .. } => "exam_attendance",
+            AuditTarget::Scholarship(_) => "scholarship",
+            AuditTarget::PassCriteria(_) => "pass_criteria",
+            AuditTarget::User(_) => "user",
+            AuditTarget::Lookup(LookupKind::EnrollmentStatus, _) => "enrollment_status",
+            AuditTarget::Lookup(LookupKind::StudentAttribute, _) => "student_attribute",
+        }
+    }
+
+    /// 寫入 AuditLog.EntityKey 的值，應考紀錄為「場次SN:學號」
+    pub fn entity_key(&self) -> String {
+        match self {
+            AuditTarget::Student(id) | AuditTarget::Scholarship(id) | AuditTarget::User(id) => id.to_string(),
+            AuditTarget::ExamSession(sn) | AuditTarget::PassCriteria(sn) | AuditTarget::Lookup(_, sn) => {
+                sn.to_string()
+            }
+            AuditTarget::ExamAttendance { exam_session_sn, student_id } => {
+                format!("{}:{}", exam_session_sn, student_id)
+            }
+        }
+    }
+
+    /// 相關的學號，方便依學生查詢所有異動
+    fn student_id(&self) -> Option<&str> {
+        match self {
+            AuditTarget::Student(id) | AuditTarget::Scholarship(id) => Some(id),
+            AuditTarget::ExamAttendance { student_id, .. } => Some(student_id),
+            _ => None,
+        }
+    }
+
+    /// 以 JSON 字串取得目前資料庫中的內容，資料不存在時回傳 None
+    pub async fn snapshot(&self, conn: &mut MySqlConnection) -> Result<Option<String>, Error> {
+        self.fetch_snapshot(conn, "").await
+    }
+
+    /// 與 snapshot 相同，但會鎖定該筆資料直到交易結束，用於比對版本後再修改
+    pub async fn snapshot_for_update(&self, conn: &mut MySqlConnection) -> Result<Option<String>, Error> {
+        self.fetch_snapshot(conn, " FOR UPDATE").await
+    }
+
+    async fn fetch_snapshot(&self, conn: &mut MySqlConnection, suffix: &str) -> Result<Option<String>, Error> {
+        let query = match self {
+            AuditTarget::Student(_) => r#"
+                SELECT CAST(JSON_OBJECT(
+                    'StudentID', StudentID, 'Name', Name,
+                    'EnrollmentStatus_SN', EnrollmentStatus_SN, 'StudentAttribute_SN', StudentAttribute_SN,
+                    'IsPassed', IsPassed, 'PassingCriteria', PassingCriteria,
+                    'PassedSession_SN', PassedSession_SN, 'PassedDate', PassedDate,
+                    'PassCriteria_SN', PassCriteria_SN, 'PassingConditions', PassingConditions,
+                    'EntranceYear', EntranceYear, 'Notes', Notes
+                ) AS CHAR)
+                FROM StudentInfo WHERE StudentID = ?
+            "#,
+            AuditTarget::ExamSession(_) => r#"
+                SELECT CAST(JSON_OBJECT(
+                    'SN', SN, 'ExamDate', ExamDate, 'ExamType', ExamType, 'Notes', Notes
+                ) AS CHAR)
+                FROM ExamSessions WHERE SN = ?
+            "#,
+            AuditTarget::ExamAttendance { .. } => r#"
+                SELECT CAST(JSON_OBJECT(
+                    'ExamSession_SN', ExamSession_SN, 'StudentID', StudentID,
+                    'IsAbsent', IsAbsent, 'IsExcused', IsExcused,
+                    'CorrectAnswersCount', CorrectAnswersCount, 'Notes', Notes
+                ) AS CHAR)
+                FROM ExamAttendance WHERE ExamSession_SN = ? AND StudentID = ?
+            "#,
+            AuditTarget::Scholarship(_) => r#"
+                SELECT CAST(JSON_OBJECT(
+                    'StudentID', StudentID, 'CorrectAnswersCount', CorrectAnswersCount,
+                    'ReceivedDate', ReceivedDate, 'ScholarshipAmount', ScholarshipAmount, 'Notes', Notes
+                ) AS CHAR)
+                FROM ScholarshipRecord WHERE StudentID = ?
+            "#,
+            AuditTarget::PassCriteria(_) => r#"
+                SELECT CAST(JSON_OBJECT(
+                    'SN', SN, 'Name', Name, 'RuleDefinition', RuleDefinition,
+                    'EntranceYearFrom', EntranceYearFrom, 'EntranceYearTo', EntranceYearTo,
+                    'IsActive', IsActive, 'Notes', Notes
+                ) AS CHAR)
+                FROM PassCriteria WHERE SN = ?
+            "#,
+            // 不記錄密碼雜湊
+            AuditTarget::User(_) => r#"
+                SELECT CAST(JSON_OBJECT(
+                    'id', id, 'username', username, 'role', role, 'is_disabled', is_disabled
+                ) AS CHAR)
+                FROM users WHERE username = ?
+            "#,
+            AuditTarget::Lookup(LookupKind::EnrollmentStatus, _) => r#"
+                SELECT CAST(JSON_OBJECT('SN', SN, 'Status', Status, 'Code', Code, 'Notes', Notes) AS CHAR)
+                FROM EnrollmentStatus WHERE SN = ?
+            "#,
+            AuditTarget::Lookup(LookupKind::StudentAttribute, _) => r#"
+                SELECT CAST(JSON_OBJECT('SN', SN, 'Attribute', Attribute, 'Code', Code, 'Notes', Notes) AS CHAR)
+                FROM StudentAttributes WHERE SN = ?
+            "#,
+        };
+        let query = format!("{}{}", query.trim_end(), suffix);
+        let query = sqlx::query_scalar(&query);
+        let query = match self {
+            AuditTarget::Student(id) | AuditTarget::Scholarship(id) | AuditTarget::User(id) => query.bind(*id),
+            AuditTarget::ExamSession(sn) | AuditTarget::PassCriteria(sn) | AuditTarget::Lookup(_, sn) => {
+                query.bind(*sn)
+            }
+            AuditTarget::ExamAttendance { exam_session_sn, student_id } => {
+                query.bind(*exam_session_sn).bind(*student_id)
+            }
+        };
+        query.fetch_optional(conn).await
+    }
+}
+
+/// 寫入 AuditLog 的操作者與端點，沒有 Session 的背景工作與 cpe-admin 直接指定使用者名稱
+#[derive(Debug, Clone)]
+pub struct AuditActor {
+    pub username: String,
+    pub endpoint: String,
+}
+
+impl AuditActor {
+    pub fn new(username: impl Into<String>, endpoint: impl Into<String>) -> Self {
+        AuditActor { username: username.into(), endpoint: endpoint.into() }
+    }
+
+    /// 以 session 中的 username 作為操作者
+    pub fn from_session(session: &Session, endpoint: &str) -> Self {
+        let username: Option<String> = session.get("username").unwrap_or(None);
+        AuditActor::new(username.unwrap_or_default(), endpoint)
+    }
+}
+
+/// 寫入一筆稽核紀錄，異動後的內容會在此時讀取，因此需在異動完成後、提交交易前呼叫
+/// before 與異動後內容相同時不寫入
+pub async fn write_audit_log(
+    conn: &mut MySqlConnection,
+    session: &Session,
+    endpoint: &str,
+    target: &AuditTarget<'_>,
+    before: Option<String>,
+) -> Result<(), Error> {
+    write_audit_log_as(conn, &AuditActor::from_session(session, endpoint), target, before).await
+}
+
+/// 同 write_audit_log，以指定的操作者寫入
+pub async fn write_audit_log_as(
+    conn: &mut MySqlConnection,
+    actor: &AuditActor,
+    target: &AuditTarget<'_>,
+    before: Option<String>,
+) -> Result<(), Error> {
+    let after = target.snapshot(&mut *conn).await?;
+    if before == after {
+        return Ok(());
+    }
+    insert_audit_log_as(conn, actor, target, before, after).await
+}
+
+/// 直接寫入指定的異動前後內容，用於無法從資料表讀出差異的操作（例如重設密碼）
+pub async fn insert_audit_log(
+    conn: &mut MySqlConnection,
+    session: &Session,
+    endpoint: &str,
+    target: &AuditTarget<'_>,
+    before: Option<String>,
+    after: Option<String>,
+) -> Result<(), Error> {
+    insert_audit_log_as(conn, &AuditActor::from_session(session, endpoint), target, before, after).await
+}
+
+/// 同 insert_audit_log，以指定的操作者寫入
+pub async fn insert_audit_log_as(
+    conn: &mut MySqlConnection,
+    actor: &AuditActor,
+    target: &AuditTarget<'_>,
+    before: Option<String>,
+    after: Option<String>,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO AuditLog (Username, Endpoint, EntityType, EntityKey, StudentID, BeforeValue, AfterValue)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&actor.username)
+    .bind(&actor.endpoint)
+    .bind(target.entity_type())
+    .bind(target.entity_key())
+    .bind(target.student_id())
+    .bind(before)
+    .bind(after)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// 取得多個對象異動前的快照，搭配 write_audit_logs 使用
+pub async fn snapshot_all<'a>(
+    conn: &mut MySqlConnection,
+    targets: Vec<AuditTarget<'a>>,
+) -> Result<Vec<(AuditTarget<'a>, Option<String>)>, Error> {
+    let mut snapshots = Vec::with_capacity(targets.len());
+    for target in targets {
+        let before = target.snapshot(&mut *conn).await?;
+        snapshots.push((target, before));
+    }
+    Ok(snapshots)
+}
+
+/// 一次寫入多筆稽核紀錄
+pub async fn write_audit_logs(
+    conn: &mut MySqlConnection,
+    session: &Session,
+    endpoint: &str,
+    snapshots: Vec<(AuditTarget<'_>, Option<String>)>,
+) -> Result<(), Error> {
+    for (target, before) in snapshots {
+        write_audit_log(&mut *conn, session, endpoint, &target, before).await?;
+    }
+    Ok(())
+}
+
+/// 刪除學生時會一併刪除的資料：學生本身、獎學金紀錄與所有應考紀錄
+pub async fn student_targets<'a>(
+    conn: &mut MySqlConnection,
+    student_id: &'a str,
+) -> Result<Vec<AuditTarget<'a>>, Error> {
+    let exam_session_sns: Vec<i32> =
+        sqlx::query_scalar("SELECT ExamSession_SN FROM ExamAttendance WHERE StudentID = ?")
+            .bind(student_id)
+            .fetch_all(conn)
+            .await?;
+    let mut targets = vec![AuditTarget::Student(student_id), AuditTarget::Scholarship(student_id)];
+    targets.extend(
+        exam_session_sns
+            .into_iter()
+            .map(|exam_session_sn| AuditTarget::ExamAttendance { exam_session_sn, student_id }),
+    );
+    Ok(targets)
+}
+
+/// 取得某個入學年度區間內啟用中的通過規則，用於切換啟用規則前記錄
+pub async fn active_pass_criteria_of_cohort(
+    conn: &mut MySqlConnection,
+    entrance_year_from: Option<i32>,
+    entrance_year_to: Option<i32>,
+) -> Result<Vec<AuditTarget<'static>>, Error> {
+    let sns: Vec<i32> = sqlx::query_scalar(
+        r#"
+        SELECT SN FROM PassCriteria
+        WHERE IsActive = TRUE AND EntranceYearFrom <=> ? AND EntranceYearTo <=> ?
+        "#,
+    )
+    .bind(entrance_year_from)
+    .bind(entrance_year_to)
+    .fetch_all(conn)
+    .await?;
+    Ok(sns.into_iter().map(AuditTarget::PassCriteria).collect())
+}
+
+/// 取得某個場次所有應考紀錄的學號，用於刪除場次前逐筆記錄
+pub async fn attendance_students_of_session(
+    conn: &mut MySqlConnection,
+    exam_session_sn: i32,
+) -> Result<Vec<String>, Error> {
+    sqlx::query_scalar("SELECT StudentID FROM ExamAttendance WHERE ExamSession_SN = ?")
+        .bind(exam_session_sn)
+        .fetch_all(conn)
+        .await
+}
@@ -1,8 +1,11 @@
-use actix_web::{get, HttpRequest, HttpResponse, Responder};
+use actix_web::{get, HttpRequest, HttpResponse};
 use actix_session::Session;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
 
+#[utoipa::path(tag = "auth", responses((status = 200, description = "已登入", body = String)))]
 #[get("/api/check_session")]
-async fn check_session(req: HttpRequest, session: Session) -> impl Responder {
+async fn check_session(req: HttpRequest, session: Session) -> Result<HttpResponse, ApiError> {
     // 從請求頭中提取 CSRF Token
     let csrf_token_header = req
         .headers()
@@ -16,11 +19,11 @@ async fn check_session(req: HttpRequest, session: Session) -> impl Responder {
         if header_token == session_token {
             if let Some(is_logged_in) = session.get::<bool>("is_logged_in").unwrap_or(None) {
                 if is_logged_in {
-                    return HttpResponse::Ok().body("User is logged in.");
+                    return Ok(HttpResponse::Ok().body(t!(UserLoggedIn)));
                 }
             }
-            return HttpResponse::Unauthorized().body("User is not logged in.");
+            return Err(ApiError::new(ErrorCode::Unauthorized, t!(UserNotLoggedIn)));
         }
     }
-    HttpResponse::Forbidden().body("Invalid CSRF token.")
+    Err(ApiError::new(ErrorCode::Forbidden, t!(InvalidCsrfToken)))
 }
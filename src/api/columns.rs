@@ -0,0 +1,167 @@
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::upload::Sheet;
+use calamine::{Data, DataType};
+
+/// 匯入檔案中的一個欄位：以標題文字對應，不依賴欄位順序
+pub struct ColumnSpec {
+    pub name: &'static str,              // 標準名稱，也是錯誤訊息中顯示的名稱
+    pub aliases: &'static [&'static str], // 其他可接受的標題
+    pub required: bool,
+}
+
+impl ColumnSpec {
+    pub const fn required(name: &'static str, aliases: &'static [&'static str]) -> Self {
+        ColumnSpec { name, aliases, required: true }
+    }
+
+    pub const fn optional(name: &'static str, aliases: &'static [&'static str]) -> Self {
+        ColumnSpec { name, aliases, required: false }
+    }
+
+    fn matches(&self, header: &str) -> bool {
+        std::iter::once(self.name)
+            .chain(self.aliases.iter().copied())
+            .any(|candidate| normalize_header(candidate) == header)
+    }
+}
+
+/// 學號欄位，查詢與刪除類的 API 共用
+pub const STUDENT_ID: ColumnSpec = ColumnSpec::required("學號", &["學生學號", "student id", "student_id"]);
+
+/// 統一標題的寫法：去掉括號中的填寫說明、空白與底線，英文不分大小寫
+/// 例如「註冊狀況(只能填在學、休學、退學)」→「註冊狀況」
+pub fn normalize_header(header: &str) -> String {
+    header
+        .split(['(', '（'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '_')
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// 標題列中各欄位所在的位置
+pub struct ColumnMap {
+    columns: Vec<(&'static str, usize)>,
+}
+
+impl ColumnMap {
+    /// 依標題列找出每個欄位的位置，多餘的欄位會被忽略
+    /// 缺少必要欄位時一次列出所有缺少的欄位
+    pub fn from_header(header: &[Data], specs: &[ColumnSpec]) -> Result<Self, String> {
+        let headers: Vec<String> = header
+            .iter()
+            .map(|cell| cell.get_string().map(normalize_header).unwrap_or_default())
+            .collect();
+
+        let mut columns = Vec::with_capacity(specs.len());
+        let mut missing = Vec::new();
+        for spec in specs {
+            match headers.iter().position(|header| !header.is_empty() && spec.matches(header)) {
+                Some(index) => columns.push((spec.name, index)),
+                None if spec.required => missing.push(spec.name),
+                None => (),
+            }
+        }
+        if !missing.is_empty() {
+            return Err(t!(MissingColumns, missing.join(&t!(ListSeparator))));
+        }
+        Ok(ColumnMap { columns })
+    }
+
+    /// 欄位所在的位置（從 0 開始），選填欄位不存在時為 None
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().find(|(column, _)| *column == name).map(|(_, index)| *index)
+    }
+
+    /// 取得某一列中指定欄位的儲存格
+    pub fn get<'a>(&self, row: &'a [Data], name: &str) -> Option<&'a Data> {
+        self.index(name).and_then(|index| row.get(index))
+    }
+}
+
+/// 讀取所有工作表中「學號」欄的學號（轉成大寫），查詢與刪除類的 API 共用
+pub fn read_student_ids(sheets: &[Sheet]) -> Result<Vec<String>, ApiError> {
+    let mut student_ids = Vec::new();
+    for sheet in sheets {
+        let Some(header_row) = sheet.range.rows().next() else {
+            continue;
+        };
+        let columns = ColumnMap::from_header(header_row, &[STUDENT_ID])
+            .map_err(|err| ApiError::new(ErrorCode::MissingColumns, t!(SheetError, sheet.name, err)))?;
+        student_ids.extend(
+            sheet
+                .range
+                .rows()
+                .skip(1) // 跳過標題列
+                .filter_map(|row| columns.get(row, STUDENT_ID.name))
+                .filter_map(|cell| cell.get_string()) // 只取字串
+                .map(|s| s.trim().to_ascii_uppercase())
+                .filter(|s| !s.is_empty()),
+        );
+    }
+    Ok(student_ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPECS: [ColumnSpec; 3] = [
+        STUDENT_ID,
+        ColumnSpec::required("姓名", &["name"]),
+        ColumnSpec::optional("備註", &["note"]),
+    ];
+
+    fn header(titles: &[&str]) -> Vec<Data> {
+        titles.iter().map(|title| Data::String(title.to_string())).collect()
+    }
+
+    #[test]
+    fn normalize_header_drops_hints_spaces_and_case() {
+        let cases = [
+            ("學號", "學號"),
+            ("註冊狀況(只能填在學、休學、退學)", "註冊狀況"),
+            ("學生屬性（選填）", "學生屬性"),
+            (" 學 號 ", "學號"),
+            ("Student_ID", "studentid"),
+            ("STUDENT ID", "studentid"),
+            ("", ""),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(normalize_header(input), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn from_header_matches_aliases_in_any_order() {
+        let columns = ColumnMap::from_header(&header(&["其他", "Name", "Student ID"]), &SPECS).unwrap();
+        assert_eq!(columns.index("學號"), Some(2));
+        assert_eq!(columns.index("姓名"), Some(1));
+        assert_eq!(columns.index("備註"), None);
+
+        let columns = ColumnMap::from_header(&header(&["學生學號", "姓名(必填)", "note"]), &SPECS).unwrap();
+        assert_eq!(columns.index("學號"), Some(0));
+        assert_eq!(columns.index("姓名"), Some(1));
+        assert_eq!(columns.index("備註"), Some(2));
+    }
+
+    #[test]
+    fn from_header_ignores_blank_and_non_text_cells() {
+        let row = vec![Data::Empty, Data::Float(1.0), Data::String("學號".to_string()), Data::String("姓名".to_string())];
+        let columns = ColumnMap::from_header(&row, &SPECS).unwrap();
+        assert_eq!(columns.index("學號"), Some(2));
+        assert_eq!(columns.get(&row, "姓名"), Some(&Data::String("姓名".to_string())));
+    }
+
+    #[test]
+    fn from_header_reports_every_missing_column() {
+        let err = ColumnMap::from_header(&header(&["備註"]), &SPECS).err().unwrap();
+        assert!(err.contains("學號、姓名"), "{}", err);
+
+        let err = ColumnMap::from_header(&header(&["姓名"]), &SPECS).err().unwrap();
+        assert!(err.contains("學號") && !err.contains("姓名"), "{}", err);
+    }
+}
@@ -1,10 +1,15 @@
 use actix_web::{post, web, HttpResponse, HttpRequest};
 use actix_session::Session;
 use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::audit::{write_audit_log, AuditTarget};
 use serde::Deserialize;
 use sqlx::MySqlPool;
 use chrono::NaiveDate;
-#[derive(Deserialize, Debug)]
+use utoipa::ToSchema;
+#[derive(Deserialize, Debug, ToSchema)]
 enum ExamType {
     #[serde(rename = "official")]
     Official,
@@ -19,48 +24,61 @@ impl ExamType {
         }
     }
 }
-#[derive(Deserialize, Debug)]
-struct AddExam {
+#[derive(Deserialize, Debug, ToSchema)]
+pub(crate) struct AddExam {
     date: NaiveDate,
     #[serde(rename = "type")]
     exam_type: ExamType,
     notes: String,    
 }
+#[utoipa::path(tag = "exam_sessions", responses((status = 200, description = "新增成功")))]
 #[post("/api/add_exam")]
 async fn add_exam(
     data: web::Json<AddExam>,
     req: HttpRequest,
     session: Session,
     db_pool: web::Data<MySqlPool>,
-) -> HttpResponse {
-    if !is_authorization(req, session) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Staff)?;
+    insert_exam(db_pool.get_ref(), &session, "/api/add_exam", &data).await?;
+    Ok(HttpResponse::Ok().body(""))
+}
+
+/// 新增考試場次並寫入稽核紀錄，回傳新場次的 SN
+pub(crate) async fn insert_exam(
+    db_pool: &MySqlPool,
+    session: &Session,
+    endpoint: &str,
+    data: &AddExam,
+) -> Result<i32, ApiError> {
     let date = data.date;
     let exam_type = data.exam_type.to_string();
     let notes = &data.notes;
     let byte_count = notes.as_bytes().len();
     if byte_count > 255 {
-        return HttpResponse::BadRequest().body("Notes 長度過長");
+        return Err(ApiError::invalid_input(t!(NotesTooLong)).with_field("notes"));
     }
     let query = r#"
     INSERT INTO ExamSessions (ExamDate, ExamType, Notes) VALUES (?, ?, ?)
     "#;
-    match sqlx::query(query)
+    let mut tx = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
+    let exam_session_sn = match sqlx::query(query)
         .bind(date)
         .bind(exam_type)
         .bind(notes)
-        .execute(db_pool.get_ref())
+        .execute(&mut *tx)
         .await 
     {
-        Ok(_) => (),
+        Ok(result) => result.last_insert_id() as i32,
         Err(sqlx::Error::Database(err)) if err.code() == Some(std::borrow::Cow::Borrowed("23000")) => {
-            return HttpResponse::Conflict().body("已經新增過此場次的考試");
+            return Err(ApiError::new(ErrorCode::AlreadyExists, t!(ExamAlreadyExists)));
         }
-        Err(err) => {
-            return HttpResponse::InternalServerError().body(format!("Internal server error.: {}", err));
-        }
-        
-    }
-    HttpResponse::Ok().body("")
+        Err(err) => return Err(ApiError::database(t!(AddExamFailed), err)),
+    };
+    let target = AuditTarget::ExamSession(exam_session_sn);
+    write_audit_log(&mut tx, session, endpoint, &target, None)
+        .await
+        .map_err(|err| ApiError::database(t!(AuditLogFailed), err))?;
+    tx.commit().await.map_err(|err| ApiError::database(t!(CommitFailed), err))?;
+    Ok(exam_session_sn)
 }
\ No newline at end of file
@@ -0,0 +1,59 @@
+use actix_web::{post, web, HttpResponse, HttpRequest};
+use actix_session::Session;
+use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::audit::{write_audit_log, AuditTarget};
+use crate::api::lookup::{validate_entry, LookupKind};
+use serde::Deserialize;
+use sqlx::MySqlPool;
+use utoipa::ToSchema;
+
+#[derive(Deserialize, Debug, ToSchema)]
+struct AddLookup {
+    kind: LookupKind,
+    name: String,         // 例如 延畢、交換生
+    code: Option<String>, // 前端使用的英文代碼，可不填
+    notes: Option<String>,
+}
+
+#[utoipa::path(tag = "lookup", responses((status = 200, description = "新增成功", body = String)))]
+#[post("/api/add_lookup")]
+async fn add_lookup(
+    data: web::Json<AddLookup>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Admin)?;
+    let (name, code) = validate_entry(data.kind, &data.name, data.code.as_deref(), data.notes.as_deref())?;
+    let notes = data.notes.as_deref().filter(|notes| !notes.trim().is_empty());
+    let query = format!(
+        "INSERT INTO {} ({}, Code, Notes) VALUES (?, ?, ?)",
+        data.kind.table(),
+        data.kind.name_column()
+    );
+
+    let mut tx = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
+    let sn = match sqlx::query(&query)
+        .bind(name)
+        .bind(code)
+        .bind(notes)
+        .execute(&mut *tx)
+        .await
+    {
+        Ok(result) => result.last_insert_id() as i32,
+        Err(sqlx::Error::Database(err)) if err.code() == Some(std::borrow::Cow::Borrowed("23000")) => {
+            return Err(ApiError::new(ErrorCode::AlreadyExists, t!(LookupExists)));
+        }
+        Err(err) => return Err(ApiError::database(t!(AddLookupFailed), err)),
+    };
+    let target = AuditTarget::Lookup(data.kind, sn);
+    write_audit_log(&mut tx, &session, "/api/add_lookup", &target, None)
+        .await
+        .map_err(|err| ApiError::database(t!(AuditLogFailed), err))?;
+    tx.commit().await.map_err(|err| ApiError::database(t!(CommitFailed), err))?;
+
+    Ok(HttpResponse::Ok().body(t!(AddLookupSuccess)))
+}
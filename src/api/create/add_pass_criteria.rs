@@ -0,0 +1,105 @@
+use actix_web::{post, web, HttpResponse, HttpRequest};
+use actix_session::Session;
+use crate::api::lib::is_authorization;
+use crate::api::pass_status::{recompute_pass_status, RecomputeFilter};
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use crate::api::audit::{active_pass_criteria_of_cohort, snapshot_all, write_audit_log, write_audit_logs, AuditActor, AuditTarget};
+use crate::api::pass_rule::PassRule;
+use serde::Deserialize;
+use sqlx::MySqlPool;
+use utoipa::ToSchema;
+
+#[derive(Deserialize, Debug, ToSchema)]
+struct AddPassCriteria {
+    name: String,
+    rule: PassRule,
+    entrance_year_from: Option<i32>, // 適用的入學年度(民國年)起，None 代表不限
+    entrance_year_to: Option<i32>,   // 適用的入學年度(民國年)迄，None 代表不限
+    notes: Option<String>,
+    #[serde(default)]
+    activate: bool, // 新增後是否立即啟用
+}
+
+#[utoipa::path(tag = "pass_criteria", responses((status = 200, description = "新增成功，activate 為 true 時會重新計算所有學生的通過狀態", body = String)))]
+#[post("/api/add_pass_criteria")]
+async fn add_pass_criteria(
+    data: web::Json<AddPassCriteria>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Admin)?;
+    let name = data.name.trim();
+    if name.is_empty() || name.chars().count() > 50 {
+        return Err(ApiError::invalid_input(t!(RuleNameInvalid)).with_field("name"));
+    }
+    if data.notes.as_ref().is_some_and(|notes| notes.len() > 255) {
+        return Err(ApiError::invalid_input(t!(NotesMax255)).with_field("notes"));
+    }
+    if let (Some(from), Some(to)) = (data.entrance_year_from, data.entrance_year_to) {
+        if from > to {
+            return Err(ApiError::invalid_input(t!(EntranceYearRangeInvalid)).with_field("entrance_year_from"));
+        }
+    }
+    data.rule
+        .validate()
+        .map_err(|msg| ApiError::invalid_input(msg).with_field("rule"))?;
+    let definition = serde_json::to_string(&data.rule)
+        .map_err(|err| ApiError::internal(t!(RuleSerializeFailed, err)))?;
+
+    let mut tx = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
+    // 同一個入學年度區間同時只會有一條啟用中的規則
+    let mut snapshots = Vec::new();
+    if data.activate {
+        let targets = active_pass_criteria_of_cohort(&mut tx, data.entrance_year_from, data.entrance_year_to)
+            .await
+            .map_err(|err| ApiError::database(t!(QueryFailed), err))?;
+        snapshots = snapshot_all(&mut tx, targets)
+            .await
+            .map_err(|err| ApiError::database(t!(QueryFailed), err))?;
+        sqlx::query(
+            r#"
+            UPDATE PassCriteria SET IsActive = FALSE
+            WHERE IsActive = TRUE AND EntranceYearFrom <=> ? AND EntranceYearTo <=> ?
+            "#,
+        )
+            .bind(data.entrance_year_from)
+            .bind(data.entrance_year_to)
+            .execute(&mut *tx)
+            .await
+            .map_err(|err| ApiError::database(t!(DeactivateRulesFailed), err))?;
+    }
+    let query = r#"
+        INSERT INTO PassCriteria (Name, RuleDefinition, EntranceYearFrom, EntranceYearTo, IsActive, Notes)
+        VALUES (?, ?, ?, ?, ?, ?)
+    "#;
+    let sn = sqlx::query(query)
+        .bind(name)
+        .bind(&definition)
+        .bind(data.entrance_year_from)
+        .bind(data.entrance_year_to)
+        .bind(data.activate)
+        .bind(&data.notes)
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| ApiError::database(t!(AddRuleFailed), err))?
+        .last_insert_id() as i32;
+    let audit = async {
+        write_audit_logs(&mut tx, &session, "/api/add_pass_criteria", snapshots).await?;
+        write_audit_log(&mut tx, &session, "/api/add_pass_criteria", &AuditTarget::PassCriteria(sn), None).await
+    }
+    .await;
+    audit.map_err(|err| ApiError::database(t!(AuditLogFailed), err))?;
+    tx.commit().await.map_err(|err| ApiError::database(t!(CommitFailed), err))?;
+
+    if data.activate {
+        let count = recompute_pass_status(db_pool.get_ref(), &AuditActor::from_session(&session, "/api/add_pass_criteria"), &RecomputeFilter::default(), false)
+            .await
+            .map_err(|err| ApiError::database(t!(RuleActivatedRecomputeFailed), err))?
+            .checked;
+        return Ok(HttpResponse::Ok().body(t!(AddRuleActivated, count)));
+    }
+    Ok(HttpResponse::Ok().body(t!(AddRuleSuccess)))
+}
@@ -0,0 +1,71 @@
+use actix_session::Session;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use sqlx::mysql::MySqlPool;
+use crate::api::lib::{hash_password, is_authorization};
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::audit::{write_audit_log_as, AuditActor, AuditTarget};
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema)]
+struct CreateUserRequest {
+    username: String,
+    password: String,
+    role: Role,
+}
+
+// 管理員新增使用者
+#[utoipa::path(tag = "users", responses((status = 200, description = "新增成功", body = String)))]
+#[post("/api/create_user")]
+async fn create_user(
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+    user_data: web::Json<CreateUserRequest>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Admin)?;
+    insert_user(db_pool.get_ref(), &AuditActor::from_session(&session, "/api/create_user"), &user_data.username, &user_data.password, user_data.role).await?;
+    Ok(HttpResponse::Ok().body(t!(CreateUserSuccess)))
+}
+
+/// 檢查使用者名稱與密碼後新增使用者，cpe-admin 的 create-user 共用
+pub async fn insert_user(
+    db_pool: &MySqlPool,
+    actor: &AuditActor,
+    username: &str,
+    password: &str,
+    role: Role,
+) -> Result<(), ApiError> {
+    let username = username.trim();
+    if username.is_empty() || username.len() > 255 {
+        return Err(ApiError::invalid_input(t!(UsernameInvalid)).with_field("username"));
+    }
+    let hashed_password = hash_password(password)?;
+
+    let query = r#"
+        INSERT INTO users (username, password, role)
+        VALUES (?, ?, ?)
+    "#;
+    let target = AuditTarget::User(username);
+    let result = async {
+        let mut tx = db_pool.begin().await?;
+        sqlx::query(query)
+            .bind(username)
+            .bind(hashed_password)
+            .bind(role.as_str())
+            .execute(&mut *tx)
+            .await?;
+        write_audit_log_as(&mut tx, actor, &target, None).await?;
+        tx.commit().await
+    }
+    .await;
+    match result {
+        Ok(_) => Ok(()),
+        Err(sqlx::Error::Database(err)) if err.code() == Some(std::borrow::Cow::Borrowed("23000")) => {
+            Err(ApiError::new(ErrorCode::AlreadyExists, t!(UsernameExists)).with_field("username"))
+        }
+        Err(err) => Err(ApiError::database(t!(CreateUserFailed), err)),
+    }
+}
@@ -1,29 +1,33 @@
 use actix_web::{get, HttpResponse, HttpRequest};
 use actix_session::Session;
 use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
 
+#[utoipa::path(
+    tag = "files",
+    responses((status = 200, description = "學生資料匯入範本", content_type = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet", body = Vec<u8>)),
+)]
 #[get("/api/get_students_info_template")]
 async fn get_students_info_template(
     req: HttpRequest,
     session: Session,
-) -> HttpResponse {
-    if !is_authorization(req, session){
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session, Role::Staff)?;
     let students_info_template = "./uploads/students_info_template.xlsx";
     match std::fs::read(students_info_template) {
-        Ok(file_data) => 
+        Ok(file_data) => Ok(
             HttpResponse::Ok()
             .content_type("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet")
             .append_header((
                 "Content-Disposition",
                 "attachment; filename=students_info_template.xlsx",
             ))
-            .body(file_data),
+            .body(file_data)),
         Err(err) => {
             println!("Error reading generated file: {}", err);
-            HttpResponse::InternalServerError()
-                .body("Failed to generate or retrieve result Excel file")
+            Err(ApiError::internal(t!(TemplateUnavailable)))
         }
     }
 }
\ No newline at end of file
@@ -6,4 +6,7 @@ pub mod mutiple_add_exam_score;
 pub mod single_add_student;
 pub mod single_add_exam_score;
 pub mod get_scholarship_template;
-pub mod mutiple_add_scholarship;
\ No newline at end of file
+pub mod mutiple_add_scholarship;
+pub mod add_pass_criteria;
+pub mod create_user;
+pub mod add_lookup;
\ No newline at end of file
@@ -1,206 +1,250 @@
 use actix_web::{post, web, HttpRequest, HttpResponse};
 use actix_session::Session;
 use actix_multipart::Multipart;
-use sqlx::{MySqlPool, Row};
-use crate::api::lib::is_authorization;
-use crate::api::lib::update_student_status;
-use std::fs::File;
-use std::io::Write;
-use calamine::{Reader, DataType,Data as calamineData};
+use sqlx::{MySqlConnection, MySqlPool, Row};
+use crate::api::lib::{is_authorization, update_students_status};
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode, ErrorDetail};
+use crate::api::messages::t;
+use crate::api::import::{finish_import, read_import, ImportKind, ImportPreviewStore, ImportQuery, ImportReport, RowAction, RowErrors};
+use crate::api::audit::{write_audit_log_as, AuditActor, AuditTarget};
+use crate::api::columns::{normalize_header, ColumnMap, STUDENT_ID};
+use crate::api::upload::Sheet;
+use calamine::{DataType, Data as calamineData};
 use chrono::NaiveDate;
-use futures_util::StreamExt as _;
 use std::collections::HashSet;
-
+use crate::api::openapi::UploadForm;
+
+#[utoipa::path(
+    tag = "scores",
+    request_body(content = UploadForm, content_type = "multipart/form-data", description = "以 preview_id 確認預覽時不需要上傳檔案"),
+    responses(
+        (status = 200, description = "預覽或確認預覽時回傳每一列的處理結果，直接上傳成功時回傳訊息", content(
+            (ImportReport = "application/json"),
+            (String = "text/plain"),
+        )),
+        (status = 400, description = "直接上傳且有錯誤的列時，回傳標示錯誤的 Excel 檔，X-Import-Rejected 標頭為錯誤列數",
+            content_type = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet", body = Vec<u8>),
+    ),
+)]
 #[post("/api/mutiple_add_exam_score")]
 pub async fn mutiple_add_exam_score(
     mut payload: Multipart,
     req: HttpRequest,
     session: Session,
     db_pool: web::Data<MySqlPool>,
-) -> HttpResponse {
-    if !is_authorization(req, session) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
-
-    let filepath = "./uploads/exam_score.xlsx";
+    previews: web::Data<ImportPreviewStore>,
+    query: web::Query<ImportQuery>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Staff)?;
 
-    // 儲存上傳的檔案
-    while let Some(Ok(mut field)) = payload.next().await {
-        if let Some(filename) = field.content_disposition().and_then(|cd| cd.get_filename()) {
-            if !filename.ends_with(".xlsx") {
-                return HttpResponse::BadRequest().body("請上傳 .xlsx 檔案");
-            }
+    let import = read_import(&mut payload, &query, &previews, &session, ImportKind::ExamScore, None).await?;
+    let sheets = import.read_sheets()?;
 
-            let mut f = File::create(filepath).expect("create file failed");
-            while let Some(chunk) = field.next().await {
-                let data = chunk.expect("chunk error");
-                f.write_all(&data).expect("write chunk error");
-            }
-        }
-    }
+    // SQL Transaction
+    let mut tx = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
+    let report = import_exam_scores(&mut tx, &AuditActor::from_session(&session, "/api/mutiple_add_exam_score"), &sheets).await?;
 
-    let mut workbook = match calamine::open_workbook_auto(filepath) {
-        Ok(wb) => wb,
-        Err(e) => {
-            println!("開啟 Excel 錯誤: {}", e);
-            return HttpResponse::InternalServerError().body("開啟 Excel 檔案失敗");
-        }
-    };
-
-    let range = match workbook.worksheet_range("工作表1") {
-        Ok(r) => r,
-        Err(_) => return HttpResponse::BadRequest().body("請確認檔案中有名為 '工作表1' 的工作表"),
-    };
-
-    let mut exam_sn = Vec::new();
-    let headers = range.rows().next().unwrap();
-    for cell in headers.iter().skip(1).step_by(2) {
-        let Some(info) = cell.get_string() else {
-            return HttpResponse::BadRequest().body("欄位標題格式錯誤");
-        };
-        let parts: Vec<&str> = info.split(',').collect();
-        if parts.len() != 2 {
-            return HttpResponse::BadRequest().body("請使用 'YYYY-MM-DD,官辦/自辦' 作為欄位標題格式");
-        }
+    let (response, _) = finish_import(tx, &sheets, report, import, &previews, &session, t!(ImportExamScoreSuccess)).await?;
+    Ok(response)
+}
 
-        let date = match NaiveDate::parse_from_str(parts[0], "%Y-%m-%d") {
-            Ok(d) => d,
-            Err(_) => return HttpResponse::BadRequest().body("日期格式錯誤，請使用 YYYY-MM-DD"),
+/// 逐列新增各場次的成績並回報每一列的結果，不提交交易，cpe-admin 的 import scores 共用
+/// 有新增成績的學生在同一個交易內重新計算通過狀態，失敗時整批回滾
+pub async fn import_exam_scores(
+    tx: &mut MySqlConnection,
+    actor: &AuditActor,
+    sheets: &[Sheet],
+) -> Result<ImportReport, ApiError> {
+    let mut report = ImportReport::default();
+    let mut update_list = HashSet::new(); // 同一位學生出現在多個工作表時只重新計算一次
+    for sheet in sheets.iter() {
+        report.begin_sheet(&sheet.name);
+        let range = &sheet.range;
+        let Some(headers) = range.rows().next() else {
+            return Err(ApiError::new(ErrorCode::InvalidFile, t!(SheetHasNoData, sheet.name)));
         };
+        let columns = ColumnMap::from_header(headers, &[STUDENT_ID])
+            .map_err(|err| ApiError::new(ErrorCode::MissingColumns, t!(SheetError, sheet.name, err)))?;
+        let id_col = columns.index("學號").unwrap_or_default();
+
+        // 其餘欄位中，標題為「YYYY-MM-DD,官辦/自辦」的是考試場次
+        // 緊接在場次之後、標題為空白或「備註」的欄位是該場次的備註，其他欄位忽略
+        let mut exam_columns: Vec<(usize, i32, Option<usize>)> = Vec::new(); // (成績欄, 場次SN, 備註欄)
+        let mut header_errors = Vec::new();
+        for (i, cell) in headers.iter().enumerate() {
+            if i == id_col {
+                continue;
+            }
+            let info = cell.get_string().map(str::trim).unwrap_or_default();
+            if info.is_empty() || normalize_header(info).starts_with("備註") {
+                if let Some(last) = exam_columns.last_mut().filter(|last| last.0 + 1 == i) {
+                    last.2 = Some(i);
+                }
+                continue;
+            }
+            let parts: Vec<&str> = info.split(',').map(str::trim).collect();
+            if parts.len() != 2 {
+                // 只有日期沒有考試類型時提醒使用者，其他標題視為多餘的欄位
+                if NaiveDate::parse_from_str(parts[0], "%Y-%m-%d").is_ok() {
+                    header_errors.push(t!(ColumnSessionFormat, sheet.column_number(i), info));
+                }
+                continue;
+            }
 
-        let exam_type = parts[1];
-        if exam_type != "官辦" && exam_type != "自辦" {
-            return HttpResponse::BadRequest().body("考試類型需為 '官辦' 或 '自辦'");
-        }
+            let Ok(date) = NaiveDate::parse_from_str(parts[0], "%Y-%m-%d") else {
+                header_errors.push(t!(ColumnDateInvalid, sheet.column_number(i), info));
+                continue;
+            };
 
-        let row = match sqlx::query("SELECT SN FROM ExamSessions WHERE ExamDate = ? AND ExamType = ?")
-            .bind(date)
-            .bind(exam_type)
-            .fetch_one(db_pool.get_ref())
-            .await
-        {
-            Ok(r) => r,
-            Err(_) => {
-                return HttpResponse::BadRequest().body(format!("找不到場次: {},{}", parts[0], parts[1]));
+            let exam_type = parts[1];
+            if exam_type != "官辦" && exam_type != "自辦" {
+                header_errors.push(t!(ColumnExamTypeInvalid, sheet.column_number(i), info));
+                continue;
             }
-        };
-        exam_sn.push(row.get::<i32, _>("SN"));
-    }
 
-    let mut student_ids_in_excel = HashSet::new();
-    for row in range.rows().skip(1) {
-        if let Some(cell) = row.get(0) {
-            if let Some(id) = cell.get_string() {
-                student_ids_in_excel.insert(id.to_ascii_uppercase());
+            match sqlx::query("SELECT SN FROM ExamSessions WHERE ExamDate = ? AND ExamType = ?")
+                .bind(date)
+                .bind(exam_type)
+                .fetch_optional(&mut *tx)
+                .await
+            {
+                Ok(Some(row)) => exam_columns.push((i, row.get::<i32, _>("SN"), None)),
+                Ok(None) => header_errors.push(t!(ColumnSessionNotFound, sheet.column_number(i), parts[0], parts[1])),
+                Err(e) => return Err(ApiError::database(t!(QueryExamSessionFailed), e)),
             }
         }
-    }
-
-    let placeholders = student_ids_in_excel.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-    let query = format!("SELECT StudentID FROM StudentInfo WHERE StudentID IN ({})", placeholders);
-
-    let mut query_builder = sqlx::query(&query);
-    for id in &student_ids_in_excel {
-        query_builder = query_builder.bind(id);
-    }
+        if !header_errors.is_empty() {
+            let details = header_errors
+                .iter()
+                .map(|message| ErrorDetail { sheet: Some(sheet.name.clone()), message: message.clone(), ..Default::default() })
+                .collect();
+            return Err(ApiError::invalid_input(t!(SheetHeaderErrors, sheet.name, header_errors.join("\n")))
+                .with_details(details));
+        }
+        if exam_columns.is_empty() {
+            return Err(ApiError::new(ErrorCode::MissingColumns, t!(SheetNoSessionColumns, sheet.name)));
+        }
 
-    let result = match query_builder.fetch_all(db_pool.get_ref()).await {
-        Ok(rows) => rows.into_iter().map(|r| r.get::<String, _>("StudentID")).collect::<HashSet<_>>(),
-        Err(e) => {
-            println!("查詢學生清單失敗: {}", e);
-            return HttpResponse::InternalServerError().body("查詢學生資料錯誤");
+        let mut student_ids_in_excel = HashSet::new();
+        for row in range.rows().skip(1) {
+            if let Some(cell) = row.get(id_col) {
+                if let Some(id) = cell.get_string() {
+                    student_ids_in_excel.insert(id.to_ascii_uppercase());
+                }
+            }
         }
-    };
 
-    let missing_students: Vec<_> = student_ids_in_excel.difference(&result).cloned().collect();
-    if !missing_students.is_empty() {
-        return HttpResponse::BadRequest().json(missing_students);
-    }
+        // 沒有任何學號時不查詢，避免產生 IN () 的 SQL 語法錯誤，各列會在下方被拒絕
+        let existing_students = if student_ids_in_excel.is_empty() {
+            HashSet::new()
+        } else {
+            let placeholders = student_ids_in_excel.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+            let student_query = format!("SELECT StudentID FROM StudentInfo WHERE StudentID IN ({})", placeholders);
 
-    // SQL Transaction
-    let mut tx = match db_pool.begin().await {
-        Ok(tx) => tx,
-        Err(e) => {
-            println!("無法開始交易: {}", e);
-            return HttpResponse::InternalServerError().body("系統錯誤");
-        }
-    };
-    let mut update_list = Vec::new();
-    for row in range.rows().skip(1) {
-        let Some(student_id_raw) = row.get(0) else {
-            return HttpResponse::BadRequest().body("缺少學號");
-        };
+            let mut query_builder = sqlx::query(&student_query);
+            for id in &student_ids_in_excel {
+                query_builder = query_builder.bind(id);
+            }
 
-        let Some(student_id) = student_id_raw.get_string() else {
-            return HttpResponse::BadRequest().body("學號格式錯誤");
+            query_builder
+                .fetch_all(&mut *tx)
+                .await
+                .map_err(|err| ApiError::database(t!(QueryStudentFailed), err))?
+                .into_iter()
+                .map(|r| r.get::<String, _>("StudentID"))
+                .collect::<HashSet<_>>()
         };
-        let student_id = student_id.to_ascii_uppercase();
-
-        for i in (1..headers.len()).step_by(2) {
-            let cell = row.get(i);
-            let note = row.get(i + 1).and_then(|c| c.get_string()).unwrap_or("").to_string();
-
-            let (mut is_absent, mut is_excused, mut score) = (false, false, 0);
-
-            match cell {
-                Some(calamineData::String(s)) => {
-                    match s.trim() {
-                        "請假" => { is_absent = true; is_excused = true; }
-                        "缺考" => { is_absent = true; }
-                        other => {
-                            return HttpResponse::BadRequest()
-                                .body(format!("第 {} 欄格式錯誤: {}", i + 1, other));
+
+        for (row_index, row) in range.rows().enumerate().skip(1) {
+            let line_num = sheet.line_number(row_index);
+            let Some(student_id) = row.get(id_col).and_then(|c| c.get_string()) else {
+                report.reject_at(line_num, "", Some(id_col), t!(RowStudentIdMissing, line_num));
+                continue;
+            };
+            let student_id = student_id.to_ascii_uppercase();
+
+            // 先檢查整列的格式，有錯誤時整列都不寫入
+            let mut scores = Vec::new();
+            let mut errors = RowErrors::default();
+            if !existing_students.contains(&student_id) {
+                errors.add(Some(id_col), t!(RowStudentNotFound, line_num, student_id));
+            }
+            for &(i, exam_session_sn, note_col) in exam_columns.iter() {
+                let note = note_col.and_then(|col| row.get(col)).and_then(|c| c.get_string()).unwrap_or("").to_string();
+                let (mut is_absent, mut is_excused, mut score) = (false, false, 0);
+
+                match row.get(i) {
+                    Some(calamineData::String(s)) => {
+                        match s.trim() {
+                            "請假" => { is_absent = true; is_excused = true; }
+                            "缺考" => { is_absent = true; }
+                            other => {
+                                errors.add(Some(i), t!(RowCellInvalid, line_num, sheet.column_number(i), other));
+                                continue;
+                            }
                         }
                     }
-                }
-                Some(calamineData::Float(f)) => {
-                    if f.fract() == 0.0 {
-                        score = *f as i32;
-                    } else {
-                        return HttpResponse::BadRequest()
-                            .body(format!("第 {} 欄成績應為整數", i + 1));
+                    Some(calamineData::Float(f)) => {
+                        if f.fract() == 0.0 {
+                            score = *f as i32;
+                        } else {
+                            errors.add(Some(i), t!(RowScoreNotInteger, line_num, sheet.column_number(i)));
+                            continue;
+                        }
                     }
+                    _ => continue,
                 }
-                _ => continue,
+                scores.push((exam_session_sn, is_absent, is_excused, score, note));
+            }
+            if !errors.is_empty() {
+                report.reject_row(line_num, &student_id, errors);
+                continue;
             }
 
-            let insert = sqlx::query(
-                r#"
-                INSERT INTO ExamAttendance (ExamSession_SN, StudentID, IsAbsent, IsExcused, CorrectAnswersCount, Notes)
-                VALUES (?, ?, ?, ?, ?, ?)
-                "#,
-            )
-            .bind(exam_sn[i / 2])
-            .bind(&student_id)
-            .bind(is_absent)
-            .bind(is_excused)
-            .bind(score)
-            .bind(&note)
-            .execute(&mut *tx)
-            .await;
-
-            if let Err(e) = insert {
-                if let sqlx::Error::Database(db_err) = &e {
-                    if db_err.is_unique_violation() {
-                        continue;
+            let mut inserted = 0;
+            for (exam_session_sn, is_absent, is_excused, score, note) in scores.iter() {
+                let insert = sqlx::query(
+                    r#"
+                    INSERT INTO ExamAttendance (ExamSession_SN, StudentID, IsAbsent, IsExcused, CorrectAnswersCount, Notes)
+                    VALUES (?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(exam_session_sn)
+                .bind(&student_id)
+                .bind(is_absent)
+                .bind(is_excused)
+                .bind(score)
+                .bind(note)
+                .execute(&mut *tx)
+                .await;
+
+                if let Err(e) = insert {
+                    if let sqlx::Error::Database(db_err) = &e {
+                        if db_err.is_unique_violation() {
+                            continue;
+                        }
                     }
+                    return Err(ApiError::database(t!(WriteDataFailed), e));
+                }else {
+                    let target = AuditTarget::ExamAttendance { exam_session_sn: *exam_session_sn, student_id: &student_id };
+                    write_audit_log_as(&mut *tx, actor, &target, None)
+                        .await
+                        .map_err(|err| ApiError::database(t!(AuditLogFailed), err))?;
+                    inserted += 1;
                 }
-                return HttpResponse::InternalServerError().body(format!("寫入資料失敗: {}", e));
-            }else {
-                update_list.push(student_id.clone());
             }
-            
-        }
-    }
-
-    if let Err(e) = tx.commit().await {
-        println!("交易提交失敗: {}", e);
-        return HttpResponse::InternalServerError().body("交易失敗");
-    }
-    for student_id in update_list {
-        if let Err(e) = update_student_status(db_pool.clone(), student_id).await {
-            println!("更新學生狀態失敗: {}", e);
+            if inserted > 0 {
+                let message = (inserted < scores.len()).then(|| t!(ScoresSkipped, scores.len() - inserted));
+                report.push(line_num, &student_id, RowAction::Insert, message);
+                update_list.insert(student_id);
+            } else {
+                report.push(line_num, &student_id, RowAction::Skip, Some(t!(NoScoresToAdd)));
+            }
         }
     }
-    HttpResponse::Ok().body("成功新增學生考試資料")
-}
+    update_students_status(&mut *tx, actor, update_list)
+        .await
+        .map_err(|err| ApiError::database(t!(UpdatePassStatusFailed), err))?;
+    Ok(report)
+}
\ No newline at end of file
@@ -1,251 +1,215 @@
 use actix_web::{post, web, HttpRequest, HttpResponse};
 use actix_session::Session;
 use actix_multipart::Multipart;
-use sqlx::MySqlPool;
+use sqlx::{MySqlConnection, MySqlPool};
 use crate::api::lib::is_authorization;
-use std::fs::File;
-use std::io::Write;
-use calamine::{Reader, DataType,Data as calamineData};
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::import::{finish_import, read_import, ImportKind, ImportPreviewStore, ImportQuery, ImportReport, RowAction, RowErrors};
+use crate::api::audit::{write_audit_log_as, AuditActor, AuditTarget};
+use crate::api::columns::{ColumnMap, ColumnSpec, STUDENT_ID};
+use crate::api::upload::Sheet;
+use calamine::{DataType, Data as calamineData};
 use chrono::NaiveDate;
-use futures_util::StreamExt as _;
-
+use crate::api::openapi::UploadForm;
+
+const SCHOLARSHIP_COLUMNS: [ColumnSpec; 5] = [
+    STUDENT_ID,
+    ColumnSpec::required("答對題數", &["題數", "correct answers"]),
+    ColumnSpec::required("領取日期", &["領獎日期", "received date"]),
+    ColumnSpec::required("領取金額", &["金額", "獎學金金額", "amount"]),
+    ColumnSpec::optional("備註", &["notes", "note"]),
+];
+
+#[utoipa::path(
+    tag = "scholarships",
+    request_body(content = UploadForm, content_type = "multipart/form-data", description = "以 preview_id 確認預覽時不需要上傳檔案"),
+    responses(
+        (status = 200, description = "預覽或確認預覽時回傳每一列的處理結果，直接上傳成功時回傳訊息", content(
+            (ImportReport = "application/json"),
+            (String = "text/plain"),
+        )),
+        (status = 400, description = "直接上傳且有錯誤的列時，回傳標示錯誤的 Excel 檔，X-Import-Rejected 標頭為錯誤列數",
+            content_type = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet", body = Vec<u8>),
+    ),
+)]
 #[post("/api/mutiple_add_scholarship")]
 async fn mutiple_add_scholarship(
     mut payload: Multipart,
     req: HttpRequest,
     session: Session,
     db_pool: web::Data<MySqlPool>,
-) -> HttpResponse {
-    if !is_authorization(req, session) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
+    previews: web::Data<ImportPreviewStore>,
+    query: web::Query<ImportQuery>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Staff)?;
 
-    let temp_filepath = "./uploads/scholarship.xlsx";
-
-    // 儲存上傳的檔案
-    while let Some(Ok(field)) = payload.next().await {
-        let content_disposition = field.content_disposition();
-        if let Some(filename) = content_disposition.and_then(|cd| cd.get_filename()) {
-            let file_ext = std::path::Path::new(filename)
-                .extension()
-                .and_then(|s| s.to_str());
-            if file_ext != Some("xlsx") {
-                return HttpResponse::BadRequest().body("請上傳 xlsx 檔案");
-            }
-            let mut f = File::create(temp_filepath).expect("Failed to create file");
-            let mut field_stream = field;
-            while let Some(chunk) = field_stream.next().await {
-                let data = chunk.expect("Error reading chunk");
-                f.write_all(&data).expect("Error writing chunk");
-            }
-        }
-    }
-
-    if !std::path::Path::new(temp_filepath).exists() {
-        return HttpResponse::InternalServerError().body("檔案處理失敗");
-    }
-
-    let mut workbook = match calamine::open_workbook_auto(temp_filepath) {
-        Ok(wb) => wb,
-        Err(err) => {
-            println!("無法開啟 Excel：{}", err);
-            return HttpResponse::InternalServerError().body("無效的 Excel 檔案");
-        }
-    };
-
-    let range = match workbook.worksheet_range("工作表1") {
-        Ok(r) => r,
-        Err(_) => return HttpResponse::BadRequest().body("請將資料放在名稱為『工作表1』的頁籤中"),
-    };
+    let import = read_import(&mut payload, &query, &previews, &session, ImportKind::Scholarship, None).await?;
+    let sheets = import.read_sheets()?;
 
     // 開啟交易
-    let mut tx = match db_pool.begin().await {
-        Ok(tx) => tx,
-        Err(e) => {
-            println!("交易開始失敗: {}", e);
-            return HttpResponse::InternalServerError().body("交易開始失敗");
-        }
-    };
+    let mut tx = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
+    let report = import_scholarships(&mut tx, &AuditActor::from_session(&session, "/api/mutiple_add_scholarship"), &sheets).await?;
 
-    for (i, row) in range.rows().skip(1).enumerate() {
-        // println!("{:?} ", row);
-        let line_num = i + 2;
+    let (response, _) = finish_import(tx, &sheets, report, import, &previews, &session, t!(ImportScholarshipSuccess)).await?;
+    Ok(response)
+}
 
-        let student_id = match row.get(0).and_then(|c| c.get_string()) {
-            Some(sid) => sid.trim().to_ascii_uppercase(),
-            None => {
-                tx.rollback().await.ok();
-                return HttpResponse::BadRequest().body(format!("第 {} 列 學號為空或格式錯誤", line_num));
-            }
+/// 逐列新增領獎紀錄並回報每一列的結果，不提交交易，cpe-admin 的 import scholarships 共用
+pub async fn import_scholarships(
+    tx: &mut MySqlConnection,
+    actor: &AuditActor,
+    sheets: &[Sheet],
+) -> Result<ImportReport, ApiError> {
+    let mut report = ImportReport::default();
+    for sheet in sheets.iter() {
+        report.begin_sheet(&sheet.name);
+        let range = &sheet.range;
+        let Some(header_row) = range.rows().next() else {
+            return Err(ApiError::new(ErrorCode::InvalidFile, t!(SheetHasNoData, sheet.name)));
         };
-
-        let correct_count = match row.get(1) {
-            Some(cell) => {
-                match cell.get_float() {
-                    Some(f) => {
-                        // 如果小數部分為 0，代表是「整數」，可以轉成 i32
-                        if f.fract() == 0.0 {
-                            f as i32
-                        } else {
-                            tx.rollback().await.ok();
-                            return HttpResponse::BadRequest().body(format!(
-                                "第 {} 列 答對題數必須為整數，但發現小數：{}",
-                                line_num, f
-                            ));
-                        }
-                    }
-                    None => {
-                        // 表示不是 float/int 類型 (比如 string)
-                        tx.rollback().await.ok();
-                        return HttpResponse::BadRequest().body(format!(
-                            "第 {} 列 答對題數格式錯誤 (非數值或非整數)",
-                            line_num
-                        ));
-                    }
-                }
-            }
-            None => {
-                tx.rollback().await.ok();
-                return HttpResponse::BadRequest().body(format!(
-                    "第 {} 列 缺少答對題數欄位",
-                    line_num
-                ));
+        let columns = ColumnMap::from_header(header_row, &SCHOLARSHIP_COLUMNS)
+            .map_err(|err| ApiError::new(ErrorCode::MissingColumns, t!(SheetError, sheet.name, err)))?;
+        for (i, row) in range.rows().enumerate().skip(1) {
+            let line_num = sheet.line_number(i);
+
+            let mut errors = RowErrors::default();
+            let student_id = columns.get(row, "學號").and_then(|c| c.get_string()).map(|sid| sid.trim().to_ascii_uppercase());
+            if student_id.is_none() {
+                errors.add(columns.index("學號"), t!(RowStudentIdInvalid, line_num));
             }
-        };
 
-        let received_date = match row.get(2) {
-            Some(cell) => {
-                match cell {
-                    calamineData::DateTime(dt) => {
-                        let days = dt.as_f64().trunc() as i64;
-                        let base_date = NaiveDate::from_ymd_opt(1899, 12, 30).unwrap();
-                        match base_date.checked_add_days(chrono::Days::new(days as u64)) {
-                            Some(date) => date,
-                            None => {
-                                tx.rollback().await.ok();
-                                return HttpResponse::BadRequest()
-                                    .body(format!("第 {} 列 日期超出範圍", line_num));
-                            }
-                        }
-                    }
-                    calamineData::String(s) => {
-                        match NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d") {
-                            Ok(d) => d,
-                            Err(_) => {
-                                tx.rollback().await.ok();
-                                return HttpResponse::BadRequest()
-                                    .body(format!("第 {} 列 日期格式錯誤（非 YYYY-MM-DD）", line_num));
-                            }
-                        }
-                    }
-                    _ => {
-                        tx.rollback().await.ok();
-                        return HttpResponse::BadRequest()
-                            .body(format!("第 {} 列 日期格式無效", line_num));
+            let correct_count = match columns.get(row, "答對題數").map(|cell| cell.get_float()) {
+                // 如果小數部分為 0，代表是「整數」，可以轉成 i32
+                Some(Some(f)) if f.fract() == 0.0 => Some(f as i32),
+                Some(Some(f)) => {
+                    errors.add(columns.index("答對題數"), t!(RowCountDecimal, line_num, f));
+                    None
+                }
+                // 表示不是 float/int 類型 (比如 string)
+                Some(None) => {
+                    errors.add(columns.index("答對題數"), t!(RowCountInvalid, line_num));
+                    None
+                }
+                None => {
+                    errors.add(columns.index("答對題數"), t!(RowCountMissing, line_num));
+                    None
+                }
+            };
+
+            let received_date = match columns.get(row, "領取日期") {
+                Some(calamineData::DateTime(dt)) => {
+                    let days = dt.as_f64().trunc() as i64;
+                    let base_date = NaiveDate::from_ymd_opt(1899, 12, 30).unwrap();
+                    let date = base_date.checked_add_days(chrono::Days::new(days as u64));
+                    if date.is_none() {
+                        errors.add(columns.index("領取日期"), t!(RowDateOutOfRange, line_num));
                     }
+                    date
                 }
-            }
-            None => {
-                tx.rollback().await.ok();
-                return HttpResponse::BadRequest()
-                    .body(format!("第 {} 列 缺少領獎日期欄位", line_num));
-            }
-        };
-        let amount = match row.get(3) {
-            Some(cell) => match cell.get_float() {
-                Some(f) => {
-                    if f >= 0.0 {
-                        f
-                    } else {
-                        tx.rollback().await.ok();
-                        return HttpResponse::BadRequest()
-                            .body(format!("第 {} 列 金額不得為負：{}", line_num, f));
+                Some(calamineData::String(s)) => match NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d") {
+                    Ok(d) => Some(d),
+                    Err(_) => {
+                        errors.add(columns.index("領取日期"), t!(RowDateFormat, line_num));
+                        None
                     }
+                },
+                Some(_) => {
+                    errors.add(columns.index("領取日期"), t!(RowDateInvalid, line_num));
+                    None
                 }
                 None => {
-                    tx.rollback().await.ok();
-                    return HttpResponse::BadRequest()
-                        .body(format!("第 {} 列 領取金額格式錯誤（非數值）", line_num));
+                    errors.add(columns.index("領取日期"), t!(RowReceivedDateMissing, line_num));
+                    None
+                }
+            };
+            let amount = match columns.get(row, "領取金額").map(|cell| cell.get_float()) {
+                Some(Some(f)) if f >= 0.0 => Some(f),
+                Some(Some(f)) => {
+                    errors.add(columns.index("領取金額"), t!(RowAmountNegative, line_num, f));
+                    None
+                }
+                Some(None) => {
+                    errors.add(columns.index("領取金額"), t!(RowAmountInvalid, line_num));
+                    None
+                }
+                None => {
+                    errors.add(columns.index("領取金額"), t!(RowAmountMissing, line_num));
+                    None
+                }
+            };
+            let (Some(student_id), Some(correct_count), Some(received_date), Some(amount)) =
+                (student_id.clone(), correct_count, received_date, amount)
+            else {
+                report.reject_row(line_num, student_id.unwrap_or_default(), errors);
+                continue;
+            };
+
+            let notes = columns.get(row, "備註").and_then(|c| c.get_string()).unwrap_or("").to_string();
+            let exists = sqlx::query_scalar!(
+                r#"
+                SELECT 1
+                FROM ExamAttendance ea
+                JOIN StudentInfo si ON ea.StudentID = si.StudentID
+                LEFT JOIN ExamSessions es ON ea.ExamSession_SN = es.SN 
+                    AND es.ExamDate <= ?
+                    AND es.ExamType = '官辦'
+                WHERE ea.StudentID = ?
+                  AND ea.CorrectAnswersCount = ?
+                  AND ea.IsAbsent = FALSE
+                  AND ea.IsExcused = FALSE
+                GROUP BY si.StudentID, si.Name, ea.CorrectAnswersCount
+                HAVING MAX(es.ExamDate) IS NOT NULL
+                LIMIT 1
+                "#,
+                received_date,
+                student_id,
+                correct_count
+            )
+            .fetch_optional(&mut *tx) // 用交易物件執行
+            .await;
+
+            match exists {
+                Ok(Some(_)) => (),
+                Ok(None) => {
+                    report.reject_at(line_num, &student_id, columns.index("答對題數"), t!(RowNoMatchingExam, line_num, student_id, received_date, correct_count));
+                    continue;
+                }
+                Err(e) => {
+                    return Err(ApiError::database(t!(RowQueryFailed, line_num), e));
                 }
-            },
-            None => {
-                tx.rollback().await.ok();
-                return HttpResponse::BadRequest()
-                    .body(format!("第 {} 列 缺少領取金額欄位", line_num));
-            }
-        };
-
-        let notes = row.get(3).and_then(|c| c.get_string()).unwrap_or("").to_string();
-        let exists = sqlx::query_scalar!(
-            r#"
-            SELECT 1
-            FROM ExamAttendance ea
-            JOIN StudentInfo si ON ea.StudentID = si.StudentID
-            LEFT JOIN ExamSessions es ON ea.ExamSession_SN = es.SN 
-                AND es.ExamDate <= ?
-                AND es.ExamType = '官辦'
-            WHERE ea.StudentID = ?
-              AND ea.CorrectAnswersCount = ?
-              AND ea.IsAbsent = FALSE
-              AND ea.IsExcused = FALSE
-            GROUP BY si.StudentID, si.Name, ea.CorrectAnswersCount
-            HAVING MAX(es.ExamDate) IS NOT NULL
-            LIMIT 1
-            "#,
-            received_date,
-            student_id,
-            correct_count
-        )
-        .fetch_optional(&mut *tx) // 用交易物件執行
-        .await;
-
-        match exists {
-            Ok(Some(_)) => (),
-            Ok(None) => {
-                tx.rollback().await.ok();
-                return HttpResponse::BadRequest()
-                    .body(format!("第 {} 列的學生， 學號:{} 沒有在{}以前的考試中答對題數是{}", line_num, student_id, received_date, correct_count));
-            }
-            Err(e) => {
-                tx.rollback().await.ok();
-                return HttpResponse::InternalServerError()
-                    .body(format!("第 {} 列查詢錯誤: {}", line_num, e));
-            }
-        }
-        // 寫入 DB（若重複，回滾整批）
-        let query = r#"
-            INSERT INTO ScholarshipRecord (StudentID, CorrectAnswersCount, ReceivedDate, ScholarshipAmount, Notes)
-            VALUES (?, ?, ?, ?, ?)
-        "#;
-
-        match sqlx::query(query)
-            .bind(&student_id)
-            .bind(correct_count)
-            .bind(received_date)
-            .bind(amount)
-            .bind(&notes)
-            .execute(&mut *tx)
-            .await
-        {
-            Ok(_) => (),
-            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
-                tx.rollback().await.ok();
-                return HttpResponse::BadRequest()
-                    .body(format!("第 {} 列的學生， 學號:{} 已存在於資料表中", line_num, student_id));
             }
-            Err(e) => {
-                tx.rollback().await.ok();
-                return HttpResponse::InternalServerError()
-                    .body(format!("第 {} 列寫入錯誤: {}", line_num, e));
+            // 寫入 DB（若重複，該列列為拒絕）
+            let insert_query = r#"
+                INSERT INTO ScholarshipRecord (StudentID, CorrectAnswersCount, ReceivedDate, ScholarshipAmount, Notes)
+                VALUES (?, ?, ?, ?, ?)
+            "#;
+
+            match sqlx::query(insert_query)
+                .bind(&student_id)
+                .bind(correct_count)
+                .bind(received_date)
+                .bind(amount)
+                .bind(&notes)
+                .execute(&mut *tx)
+                .await
+            {
+                Ok(_) => {
+                    let target = AuditTarget::Scholarship(&student_id);
+                    if let Err(e) = write_audit_log_as(&mut *tx, actor, &target, None).await {
+                        return Err(ApiError::database(t!(RowAuditLogFailed, line_num), e));
+                    }
+                    report.push(line_num, &student_id, RowAction::Insert, None);
+                }
+                Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                    report.reject_at(line_num, &student_id, columns.index("學號"), t!(RowScholarshipExists, line_num, student_id));
+                }
+                Err(e) => {
+                    return Err(ApiError::database(t!(RowWriteFailed, line_num), e));
+                }
             }
         }
     }
-
-    // 提交交易
-    if let Err(e) = tx.commit().await {
-        println!("交易提交失敗: {}", e);
-        return HttpResponse::InternalServerError().body("資料儲存失敗，交易未完成");
-    }
-
-    HttpResponse::Ok().body("成功新增獎學金資料")
-}
+    Ok(report)
+}
\ No newline at end of file
@@ -1,73 +1,65 @@
 use actix_web::{post, web, HttpResponse, HttpRequest};
 use actix_session::Session;
 use actix_multipart::Multipart;
-use sqlx::MySqlPool;
+use sqlx::{MySqlConnection, MySqlPool};
 use crate::api::lib::is_authorization;
-use std::fs::File;
-use std::io::Write;
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::import::{finish_import, read_import, ImportKind, ImportPreviewStore, ImportQuery, ImportReport, RowAction, RowErrors};
+use crate::api::audit::{write_audit_log_as, AuditActor, AuditTarget};
+use crate::api::lookup::{LookupKind, LookupTable};
+use crate::api::columns::{ColumnMap, ColumnSpec, STUDENT_ID};
+use crate::api::upload::Sheet;
 use calamine::DataType;
-use calamine::Reader;
-use futures_util::StreamExt as _;
+use crate::api::openapi::UploadForm;
 
+const STUDENT_COLUMNS: [ColumnSpec; 5] = [
+    STUDENT_ID,
+    ColumnSpec::required("姓名", &["學生姓名", "name"]),
+    ColumnSpec::required("註冊狀況", &["在學狀況", "在學狀態", "enrollment status"]),
+    ColumnSpec::required("學生屬性", &["屬性", "attribute"]),
+    ColumnSpec::optional("備註", &["notes", "note"]),
+];
+
+#[utoipa::path(
+    tag = "students",
+    request_body(content = UploadForm, content_type = "multipart/form-data", description = "以 preview_id 確認預覽時不需要上傳檔案"),
+    responses(
+        (status = 200, description = "預覽或確認預覽時回傳每一列的處理結果，直接上傳成功時回傳訊息", content(
+            (ImportReport = "application/json"),
+            (String = "text/plain"),
+        )),
+        (status = 400, description = "直接上傳且有錯誤的列時，回傳標示錯誤的 Excel 檔，X-Import-Rejected 標頭為錯誤列數",
+            content_type = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet", body = Vec<u8>),
+    ),
+)]
 #[post("/api/mutiple_add_student_info")]
 pub async fn mutiple_add_student_info(
     mut payload: Multipart,
     req: HttpRequest,
     session: Session,
     db_pool: web::Data<MySqlPool>,
-) -> HttpResponse {
-    if !is_authorization(req, session) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
-    let temp_filepath = "./uploads/students_info.xlsx";
-    //儲存上傳的檔案
-    while let Some(Ok(field)) = payload.next().await {
-        let content_disposition = field.content_disposition();
-        if let Some(filename) = content_disposition.and_then(|cd| cd.get_filename()) {
-            let file_ext = std::path::Path::new(filename)
-                .extension()
-                .and_then(|s| s.to_str());
-            if file_ext != Some("xlsx") {
-                return HttpResponse::BadRequest().body("請上傳xlsx檔案");
-            }
-            let mut f = File::create(temp_filepath).expect("Failed to create file");
-            let mut field_stream = field;
-            while let Some(chunk) = field_stream.next().await {
-                let data = chunk.expect("Error reading chunk");
-                f.write_all(&data).expect("Error writing chunk");
-            }
-        }
-    }
-    if !std::path::Path::new(temp_filepath).exists() {
-        println!("File does not exist: {}", temp_filepath);
-        return HttpResponse::InternalServerError().body("Failed to process file");
-    }
-    //解析上傳的檔案，取得學號的list
-    let mut workbook = match calamine::open_workbook_auto(temp_filepath) {
-        Ok(wb) => wb,
-        Err(err) => {
-            println!("Failed to open Excel file: {}", err);
-            return HttpResponse::InternalServerError().body("無效的 Excel file");
-        }
-    };
-    let range = match workbook.worksheet_range("工作表1") {
-        Ok(range) => range,
-        Err(err) => {
-            println!("Error reading sheet: {}", err);
-            return HttpResponse::BadRequest().body("請將需要查詢的資料放入工作表1");
-        }
-    };
-    let header_row = range.rows().next().unwrap();
-    let expected_headers = vec!["學號", "姓名", "註冊狀況(只能填在學、休學、退學)", "學生屬性(只能填本系、外系、外校)", "備註"];
-    let actual_headers: Vec<String> = header_row.iter()
-    .map(|cell| cell.get_string().unwrap_or("").to_string())
-    .collect();
+    previews: web::Data<ImportPreviewStore>,
+    query: web::Query<ImportQuery>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Staff)?;
+    let import = read_import(&mut payload, &query, &previews, &session, ImportKind::StudentInfo, None).await?;
+    let sheets = import.read_sheets()?;
+    // 整批寫入同一個交易，任一列失敗時不會留下部分資料
+    let mut tx = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
+    let report = import_student_info(&mut tx, &AuditActor::from_session(&session, "/api/mutiple_add_student_info"), &sheets).await?;
+    let (response, _) = finish_import(tx, &sheets, report, import, &previews, &session, t!(ImportStudentInfoSuccess)).await?;
+    Ok(response)
+}
 
-    if actual_headers != expected_headers {
-        println!("標題不正確: {:?}", actual_headers);
-        return HttpResponse::BadRequest().body("標題與預期不符，請檢查工作表格式");
-    }
-    let query = r#"
+/// 逐列新增學生資料並回報每一列的結果，不提交交易，cpe-admin 的 import students 共用
+pub async fn import_student_info(
+    tx: &mut MySqlConnection,
+    actor: &AuditActor,
+    sheets: &[Sheet],
+) -> Result<ImportReport, ApiError> {
+    let insert_query = r#"
         INSERT INTO StudentInfo (
             StudentID,
             Name,
@@ -76,90 +68,85 @@ pub async fn mutiple_add_student_info(
             Notes
         ) VALUES (?, ?, ?, ?, ?)
     "#;
-    for row in range.rows().skip(1) {
-        let student_id = match row.get(0) {
-            Some(id) => {
-                if let Some(id) = id.get_string() {
-                    id.to_ascii_uppercase()
-                }else {
-                    return HttpResponse::BadRequest().body("學號欄位不能為空");
-                }
-            }
-            None => {
-                return HttpResponse::InternalServerError().body("server讀取excel錯誤");
-            }
+    let statuses = LookupTable::load(&mut *tx, LookupKind::EnrollmentStatus)
+        .await
+        .map_err(|err| ApiError::database(t!(LoadLookupFailed), err))?;
+    let attributes = LookupTable::load(&mut *tx, LookupKind::StudentAttribute)
+        .await
+        .map_err(|err| ApiError::database(t!(LoadLookupFailed), err))?;
+    let mut report = ImportReport::default();
+    for sheet in sheets.iter() {
+        report.begin_sheet(&sheet.name);
+        let range = &sheet.range;
+        let Some(header_row) = range.rows().next() else {
+            return Err(ApiError::new(ErrorCode::InvalidFile, t!(SheetHasNoData, sheet.name)));
         };
-        let name = match row.get(1) {
-            Some(name) => {
-                if let Some(name) = name.get_string() {
-                    name
-                }else {
-                    return HttpResponse::BadRequest().body("姓名欄位不能為空");
-                }
+        let columns = ColumnMap::from_header(header_row, &STUDENT_COLUMNS)
+            .map_err(|err| ApiError::new(ErrorCode::MissingColumns, t!(SheetError, sheet.name, err)))?;
+        for (i, row) in range.rows().enumerate().skip(1) {
+            let line_num = sheet.line_number(i);
+            let mut errors = RowErrors::default();
+            let student_id = columns.get(row, "學號").and_then(|id| id.get_string()).map(|id| id.to_ascii_uppercase());
+            if student_id.is_none() {
+                errors.add(columns.index("學號"), t!(RowStudentIdEmpty, line_num));
             }
-            None => {
-                return HttpResponse::InternalServerError().body("server讀取excel錯誤");
+            let name = columns.get(row, "姓名").and_then(|name| name.get_string());
+            if name.is_none() {
+                errors.add(columns.index("姓名"), t!(RowNameEmpty, line_num));
             }
-        };
-        //之後記得改成動態調整，因為資料庫可能會出現第4個狀態(先讀資料庫在做匹配)
-        let es_sn = match row.get(2) {
-            Some(status) => {
-                if let Some(status) = status.get_string() {
-                    match  status {
-                        "在學" => 1,
-                        "休學" => 2,
-                        "退學" => 3,
-                        _=> return HttpResponse::BadRequest().body("註冊狀況欄位只能填入在學、休學、退學"),
+            let es_sn = match columns.get(row, "註冊狀況").and_then(|status| status.get_string()) {
+                Some(status) => match statuses.find(status) {
+                    Some(entry) => Some(entry.sn),
+                    None => {
+                        errors.add(columns.index("註冊狀況"), t!(RowEnrollmentInvalid, line_num, statuses.names()));
+                        None
                     }
-                }else{
-                    return HttpResponse::BadRequest().body("註冊狀況欄位不能為空");
+                },
+                None => {
+                    errors.add(columns.index("註冊狀況"), t!(RowEnrollmentEmpty, line_num));
+                    None
                 }
-            },
-            None => {
-                return HttpResponse::InternalServerError().body("server讀取excel錯誤");
-            }
-        };
-        //之後記得改成動態調整，因為資料庫可能會出現第4個屬性(先讀資料庫在做匹配)
-        let sa_sn = match row.get(3) {
-            Some(attribute) => {
-                if let Some(attribute) = attribute.get_string() {
-                    match attribute {
-                        "本系" => 1,
-                        "外系" => 2,
-                        "外校" => 3,
-                        _=> return HttpResponse::BadRequest().body("學生屬性欄位只能填入在學、休學、退學"),
+            };
+            let sa_sn = match columns.get(row, "學生屬性").and_then(|attribute| attribute.get_string()) {
+                Some(attribute) => match attributes.find(attribute) {
+                    Some(entry) => Some(entry.sn),
+                    None => {
+                        errors.add(columns.index("學生屬性"), t!(RowAttributeInvalid, line_num, attributes.names()));
+                        None
                     }
-                }else {
-                    return HttpResponse::BadRequest().body("學生屬性欄位不能為空");
+                },
+                None => {
+                    errors.add(columns.index("學生屬性"), t!(RowAttributeEmpty, line_num));
+                    None
                 }
-            },
-            None => {
-                return HttpResponse::InternalServerError().body("server讀取excel錯誤");
-            }
-        };
-        let note = match row.get(4) {
-            Some(note) => note.get_string().unwrap_or(""),
-            None => {
-                return HttpResponse::InternalServerError().body("server讀取excel錯誤");
-            }
-        };
-        match sqlx::query(query)
-            .bind(&student_id)
-            .bind(name)
-            .bind(es_sn)
-            .bind(sa_sn)
-            .bind(note)
-            .execute(db_pool.get_ref())
-            .await 
-        {
-            Ok(_) => (),
-            Err(sqlx::Error::Database(err)) if err.code() == Some(std::borrow::Cow::Borrowed("23000")) => {
-                return HttpResponse::Conflict().body(format!("學號:{}，已經被新增過",&student_id));
-            }
-            Err(err) => {
-                return HttpResponse::InternalServerError().body(format!("Internal server error.: {}", err));
+            };
+            let (Some(student_id), Some(name), Some(es_sn), Some(sa_sn)) = (student_id.clone(), name, es_sn, sa_sn) else {
+                report.reject_row(line_num, student_id.unwrap_or_default(), errors);
+                continue;
+            };
+            let note = columns.get(row, "備註").and_then(|note| note.get_string()).unwrap_or("");
+            match sqlx::query(insert_query)
+                .bind(&student_id)
+                .bind(name)
+                .bind(es_sn)
+                .bind(sa_sn)
+                .bind(note)
+                .execute(&mut *tx)
+                .await 
+            {
+                Ok(_) => {
+                    let target = AuditTarget::Student(&student_id);
+                    write_audit_log_as(&mut *tx, actor, &target, None)
+                        .await
+                        .map_err(|err| ApiError::database(t!(AuditLogFailed), err))?;
+                    report.push(line_num, &student_id, RowAction::Insert, None);
+                }
+                Err(sqlx::Error::Database(err)) if err.code() == Some(std::borrow::Cow::Borrowed("23000")) => {
+                    report.reject_at(line_num, &student_id, columns.index("學號"), t!(StudentIdAlreadyAdded, student_id));
+                }
+                Err(err) => return Err(ApiError::database(t!(AddStudentInfoFailed), err)),
             }
         }
     }
-    HttpResponse::Ok().body("成功新增學生資料")
+    Ok(report)
 }
\ No newline at end of file
@@ -1,12 +1,17 @@
 use actix_web::{post, web, HttpResponse, HttpRequest};
 use actix_session::Session;
-use crate::api::lib::{is_authorization,update_student_status};
+use crate::api::lib::{is_authorization,update_students_status};
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::audit::{write_audit_log, AuditActor, AuditTarget};
 use serde::Deserialize;
 use sqlx::MySqlPool;
 use sqlx::Row;
 use chrono::NaiveDate;
+use utoipa::ToSchema;
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, ToSchema)]
 struct AddExamScore {
     session: String,
     #[serde(rename = "studentID")]
@@ -14,21 +19,20 @@ struct AddExamScore {
     num: String,
     notes: String,
 }
+#[utoipa::path(tag = "scores", responses((status = 200, description = "新增成功")))]
 #[post("/api/single_add_exam_score")]
 async fn single_add_exam_score(
     data: web::Json<AddExamScore>,
     req: HttpRequest,
     session: Session,
     db_pool: web::Data<MySqlPool>,
-) -> HttpResponse {
-    if !is_authorization(req, session) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Staff)?;
     let split = data.session.split(",").collect::<Vec<&str>>();
     let date = match NaiveDate::parse_from_str(split[0], "%Y-%m-%d") {
         Ok(date) => date,
         Err(_) => {
-            return HttpResponse::BadRequest().body("日期格式錯誤，請將第二欄(column)以後的標題格式改為YYYY-MM-DD,(官辦、自辦)");
+            return Err(ApiError::invalid_input(t!(SessionDateInvalid)).with_field("session"));
         }
     };
     let exam_type = match split.get(1) {
@@ -37,11 +41,11 @@ async fn single_add_exam_score(
             if exam_type_str == "官辦" || exam_type_str == "自辦"{
                 exam_type_str
             }else {
-                return HttpResponse::BadRequest().body("場次種類格式錯誤，請將偶數欄(column)的標題格式改為YYYY-MM-DD,(官辦、自辦)");
+                return Err(ApiError::invalid_input(t!(SessionTypeInvalid)).with_field("session"));
             }
         }
         None => {
-            return HttpResponse::BadRequest().body("場次種類格式錯誤，請將偶數欄(column)以後的標題格式改為YYYY-MM-DD,(官辦、自辦)");
+            return Err(ApiError::invalid_input(t!(SessionTypeInvalid)).with_field("session"));
         }
     };
     let query = r#"
@@ -50,30 +54,46 @@ async fn single_add_exam_score(
     let row = match sqlx::query(query).bind(&date).bind(&exam_type).fetch_one(db_pool.get_ref()).await {
         Ok(row) => row,
         Err(sqlx::Error::RowNotFound) => {
-            return HttpResponse::BadRequest().body(format!("日期:{}, 場次種類:{}，找不到該場次的資料。請先新增或檢查該場次的資料", date, exam_type));
-        }
-        Err(err) => {
-            return HttpResponse::InternalServerError().body(format!("Internal server error.: {}", err));
+            return Err(ApiError::new(
+                ErrorCode::ExamSessionNotFound,
+                t!(SessionNotFoundAddFirst, date, exam_type),
+            ));
         }
+        Err(err) => return Err(ApiError::database(t!(QuerySessionFailed), err)),
     };
-    let exam_session_sn: i32 = row.try_get("SN").unwrap();
-    let id = data.student_id.to_ascii_uppercase();
+    let exam_session_sn: i32 = row.try_get("SN")?;
+    insert_score(&db_pool, &session, "/api/single_add_exam_score", exam_session_sn, &data.student_id, &data.num, &data.notes).await?;
+    Ok(HttpResponse::Ok().body(""))
+}
+
+/// 新增一筆成績並重新計算該學生的通過狀態，回傳轉成大寫的學號
+/// num 可填入答對題數、「請假」或「缺考」，/api/v2/exam-sessions/{sn}/attendance 共用
+pub(crate) async fn insert_score(
+    db_pool: &web::Data<MySqlPool>,
+    session: &Session,
+    endpoint: &str,
+    exam_session_sn: i32,
+    student_id: &str,
+    num: &str,
+    notes: &str,
+) -> Result<String, ApiError> {
+    let id = student_id.to_ascii_uppercase();
     let mut absent = false;
     let mut excused = false;
     let mut score = 0;
-    let note = data.notes.to_string();
-    if data.num == "請假" {
+    let note = notes.to_string();
+    if num == "請假" {
         absent = true;
         excused = true;
         score = 0;
-    } else if data.num == "缺考" {
+    } else if num == "缺考" {
         absent = true;
         score = 0;
     }else {
-        score = match data.num.parse::<i32>() {
+        score = match num.parse::<i32>() {
             Ok(num) => num,
             Err(_) => {
-                return HttpResponse::BadRequest().body("題數格式錯誤，請填入題數(整數)或請假、缺考");
+                return Err(ApiError::invalid_input(t!(ScoreNumInvalid)).with_field("num"));
             }
         }
     }
@@ -82,6 +102,7 @@ async fn single_add_exam_score(
                 VALUES (?, ?, ?, ?, ?, ?);
             "#;
 
+    let mut tx = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
     match sqlx::query(query)
         .bind(exam_session_sn)
         .bind(&id)
@@ -89,28 +110,26 @@ async fn single_add_exam_score(
         .bind(excused)
         .bind(score)
         .bind(&note)
-        .execute(db_pool.get_ref())
+        .execute(&mut *tx)
         .await
     {
         Ok(_) => {
-            match update_student_status(db_pool, id).await {
-                Ok(()) => {
-                    println!("學生狀態更新成功");
-                }
-                Err(e) => {
-                    println!("學生狀態更新失敗: {}", e);
-                }
-            }
+            let target = AuditTarget::ExamAttendance { exam_session_sn, student_id: &id };
+            write_audit_log(&mut tx, session, endpoint, &target, None)
+                .await
+                .map_err(|err| ApiError::database(t!(AuditLogFailed), err))?;
+            update_students_status(&mut tx, &AuditActor::from_session(session, endpoint), [id.clone()])
+                .await
+                .map_err(|err| ApiError::database(t!(UpdatePassStatusFailed), err))?;
+            tx.commit().await.map_err(|err| ApiError::database(t!(CommitFailed), err))?;
         },
         Err(sqlx::Error::Database(err)) if err.is_unique_violation() => {
-            return HttpResponse::BadRequest().body("此場次的學號已經被新增過了，欲新增此成績請使用修改功能。");
+            return Err(ApiError::new(ErrorCode::AlreadyExists, t!(ScoreAlreadyAdded)));
         }
         Err(sqlx::Error::Database(err)) if err.code() == Some(std::borrow::Cow::Borrowed("23000")) => {
-            return HttpResponse::Conflict().body("學生資訊無此學號，請先新增這個學號再新增此成績。");
-        }
-        Err(err) => {
-            return HttpResponse::InternalServerError().body(format!("Internal server error.: {}", err));
+            return Err(ApiError::new(ErrorCode::StudentNotFound, t!(StudentNotFoundAddFirst)).with_field("studentID"));
         }
+        Err(err) => return Err(ApiError::database(t!(AddScoreFailed), err)),
     }
-    HttpResponse::Ok().body("")
+    Ok(id)
 }
\ No newline at end of file
@@ -1,68 +1,50 @@
 use actix_web::{post, web, HttpResponse, HttpRequest};
 use actix_session::Session;
 use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::audit::{write_audit_log, AuditTarget};
+use crate::api::lookup::{LookupKind, LookupTable, LookupValue};
 use serde::Deserialize;
 use sqlx::MySqlPool;
+use utoipa::ToSchema;
 
-#[derive(Deserialize, Debug)]
-enum EnrollmentStatus {
-    #[serde(rename = "currentlyEnrolled")]
-    CurrentlyEnrolled,//在學
-    #[serde(rename = "onALeaveOfAbsence")]
-    OnALeaveOfAbsence,//休學
-    #[serde(rename = "droppedOut")]
-    DroppedOut,//退學
-}
-impl EnrollmentStatus {
-    fn to_sn(&self) -> i32 {
-        match self {
-            EnrollmentStatus::CurrentlyEnrolled => 1,
-            EnrollmentStatus::OnALeaveOfAbsence => 2,
-            EnrollmentStatus::DroppedOut => 3,
-        }
-    }
-}
-#[derive(Deserialize, Debug)]
-enum StudentAttribute {
-    #[serde(rename = "departmental")]
-    Departmental,//本系
-    #[serde(rename = "interdepartmental")]
-    Interdepartmental,//外系
-    #[serde(rename = "externalStudents")]
-    ExternalStudents,//外校
-}
-impl StudentAttribute {
-    fn to_sn(&self) -> i32 {
-        match self {
-            StudentAttribute::Departmental => 1,
-            StudentAttribute::Interdepartmental => 2,
-            StudentAttribute::ExternalStudents => 3,
-        }
-    }
-}
-#[derive(Deserialize, Debug)]
-struct AddStudent {
+#[derive(Deserialize, Debug, ToSchema)]
+pub(crate) struct AddStudent {
     #[serde(rename = "studentID")]
     student_id: String,
     name: String,
     #[serde(rename = "enrollmentStatus")]
-    enrollment_status: EnrollmentStatus,
+    enrollment_status: LookupValue,//SN、名稱(在學)或代碼(currentlyEnrolled)
     #[serde(rename = "studentAttribute")]
-    student_attribute: StudentAttribute,
+    student_attribute: LookupValue,//SN、名稱(本系)或代碼(departmental)
     notes: String,    
+    #[serde(rename = "entranceYear", default)]
+    entrance_year: Option<i32>,//入學年度(民國年)，未填時由學號推算
 }
+#[utoipa::path(tag = "students", responses((status = 200, description = "新增成功")))]
 #[post("/api/single_add_student")]
 async fn single_add_student(
     data: web::Json<AddStudent>,
     req: HttpRequest,
     session: Session,
     db_pool: web::Data<MySqlPool>,
-) -> HttpResponse {
-    if !is_authorization(req, session) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Staff)?;
+    insert_student(db_pool.get_ref(), &session, "/api/single_add_student", &data).await?;
+    Ok(HttpResponse::Ok().body(""))
+}
+
+/// 新增一位學生並寫入稽核紀錄，回傳轉成大寫的學號
+pub(crate) async fn insert_student(
+    db_pool: &MySqlPool,
+    session: &Session,
+    endpoint: &str,
+    data: &AddStudent,
+) -> Result<String, ApiError> {
     if data.notes.len() > 255 {
-        return HttpResponse::BadRequest().body("備註最多255字");
+        return Err(ApiError::invalid_input(t!(NotesMax255)).with_field("notes"));
     }
     let query = r#"
         INSERT INTO StudentInfo (
@@ -70,27 +52,46 @@ async fn single_add_student(
             Name,
             EnrollmentStatus_SN,
             StudentAttribute_SN,
-            Notes
-        ) VALUES (?, ?, ?, ?, ?)
+            Notes,
+            EntranceYear
+        ) VALUES (?, ?, ?, ?, ?, ?)
     "#;
 
+    let student_id = data.student_id.to_ascii_uppercase();
+    let mut tx = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
+    let statuses = LookupTable::load(&mut tx, LookupKind::EnrollmentStatus)
+        .await
+        .map_err(|err| ApiError::database(t!(LoadLookupFailed), err))?;
+    let Some(es_sn) = statuses.resolve(&data.enrollment_status) else {
+        return Err(ApiError::invalid_input(t!(EnrollmentStatusInvalid, statuses.names())).with_field("enrollmentStatus"));
+    };
+    let attributes = LookupTable::load(&mut tx, LookupKind::StudentAttribute)
+        .await
+        .map_err(|err| ApiError::database(t!(LoadLookupFailed), err))?;
+    let Some(sa_sn) = attributes.resolve(&data.student_attribute) else {
+        return Err(ApiError::invalid_input(t!(StudentAttributeInvalid, attributes.names())).with_field("studentAttribute"));
+    };
     match sqlx::query(query)
-        .bind(&data.student_id.to_ascii_uppercase())
+        .bind(&student_id)
         .bind(&data.name)
-        .bind(data.enrollment_status.to_sn())
-        .bind(data.student_attribute.to_sn())
+        .bind(es_sn)
+        .bind(sa_sn)
         .bind(&data.notes)
-        .execute(db_pool.get_ref())
+        .bind(data.entrance_year)
+        .execute(&mut *tx)
         .await
     {
         Ok(_) => (),
         Err(sqlx::Error::Database(err)) if err.code() == Some(std::borrow::Cow::Borrowed("23000")) => {
-            return HttpResponse::Conflict().body("此學號已經被新增過。");
-        }
-        Err(err) => {
-            return HttpResponse::InternalServerError().body(err.to_string());
+            return Err(ApiError::new(ErrorCode::AlreadyExists, t!(StudentIdExists)).with_field("studentID"));
         }
+        Err(err) => return Err(ApiError::database(t!(AddStudentFailed), err)),
     }
+    let target = AuditTarget::Student(&student_id);
+    write_audit_log(&mut tx, session, endpoint, &target, None)
+        .await
+        .map_err(|err| ApiError::database(t!(AuditLogFailed), err))?;
+    tx.commit().await.map_err(|err| ApiError::database(t!(CommitFailed), err))?;
 
-    HttpResponse::Ok().body("")
+    Ok(student_id)
 }
\ No newline at end of file
@@ -1,32 +1,35 @@
 use actix_session::Session;
 use actix_web::{post, web, HttpRequest, HttpResponse};
-use sqlx::MySqlPool;
+use sqlx::{MySql, MySqlPool, Transaction};
 use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use crate::api::audit::{attendance_students_of_session, snapshot_all, write_audit_logs, AuditActor, AuditTarget};
+use crate::api::pass_rule::load_rule_set;
+use crate::api::pass_status::{recompute_students, RecomputeFilter};
 use serde::Deserialize;
 use chrono::NaiveDate;
-#[derive(Deserialize,Debug)]
+use utoipa::ToSchema;
+#[derive(Deserialize,Debug,ToSchema)]
 struct DeleteExamInfo {
     date:NaiveDate,
     exam_type: String,
 }
 
+#[utoipa::path(tag = "exam_sessions", responses((status = 200, description = "已刪除場次與場次內所有成績", body = String)))]
 #[post("/api/delete_exam_info")]
 async fn delete_exam_info(
     data: web::Json<DeleteExamInfo>,
     req: HttpRequest,
     session: Session,
     db_pool: web::Data<MySqlPool>,
-) -> HttpResponse {
-    if !is_authorization(req, session) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Admin)?;
     let exam_date = data.date;
     let exam_type = &data.exam_type;
 
-    let mut tx = match db_pool.begin().await {
-        Ok(tx) => tx,
-        Err(err) => return HttpResponse::InternalServerError().body(format!("無法開始交易: {}", err)),
-    };
+    let mut tx = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
 
     let exam_session = sqlx::query!(
         "SELECT SN FROM ExamSessions WHERE ExamDate = ? AND ExamType = ?",
@@ -38,8 +41,37 @@ async fn delete_exam_info(
 
     let exam_session_sn = match exam_session {
         Ok(Some(record)) => record.SN,
-        Ok(None) => return HttpResponse::NotFound().body(format!("找不到{},{}的考試記錄",exam_date,exam_type)),
-        Err(err) => return HttpResponse::InternalServerError().body(format!("查詢失敗: {}", err)),
+        Ok(None) => return Err(ApiError::not_found(t!(ExamRecordNotFound, exam_date, exam_type))),
+        Err(err) => return Err(ApiError::database(t!(QueryFailed), err)),
+    };
+    remove_exam_session(tx, &session, "/api/delete_exam_info", exam_session_sn).await?;
+
+    Ok(HttpResponse::Ok().body(t!(DeleteExamSuccess)))
+}
+
+/// 刪除場次與場次內所有應考紀錄並寫入稽核紀錄，在同一個交易內重新計算應考學生的通過狀態後提交
+/// 呼叫前需確認場次存在，/api/v2/exam-sessions/{sn} 共用
+pub(crate) async fn remove_exam_session(
+    mut tx: Transaction<'_, MySql>,
+    session: &Session,
+    endpoint: &str,
+    exam_session_sn: i32,
+) -> Result<(), ApiError> {
+
+    // 刪除前記錄場次與所有應考紀錄的內容
+    let student_ids = match attendance_students_of_session(&mut tx, exam_session_sn).await {
+        Ok(ids) => ids,
+        Err(err) => return Err(ApiError::database(t!(QueryFailed), err)),
+    };
+    let mut targets = vec![AuditTarget::ExamSession(exam_session_sn)];
+    targets.extend(
+        student_ids
+            .iter()
+            .map(|student_id| AuditTarget::ExamAttendance { exam_session_sn, student_id }),
+    );
+    let snapshots = match snapshot_all(&mut tx, targets).await {
+        Ok(snapshots) => snapshots,
+        Err(err) => return Err(ApiError::database(t!(QueryFailed), err)),
     };
 
     let delete_attendance_result = sqlx::query!(
@@ -51,7 +83,7 @@ async fn delete_exam_info(
 
     if let Err(err) = delete_attendance_result {
         let _ = tx.rollback().await;
-        return HttpResponse::InternalServerError().body(format!("刪除考試參與記錄失敗: {}", err));
+        return Err(ApiError::database(t!(DeleteAttendanceFailed), err));
     }
 
     let delete_exam_result = sqlx::query!(
@@ -63,12 +95,29 @@ async fn delete_exam_info(
 
     if let Err(err) = delete_exam_result {
         let _ = tx.rollback().await;
-        return HttpResponse::InternalServerError().body(format!("刪除考試記錄失敗: {}", err));
+        return Err(ApiError::database(t!(DeleteExamFailed), err));
+    }
+
+    if let Err(err) = write_audit_logs(&mut tx, session, endpoint, snapshots).await {
+        let _ = tx.rollback().await;
+        return Err(ApiError::database(t!(AuditLogFailed), err));
     }
 
-    if let Err(err) = tx.commit().await {
-        return HttpResponse::InternalServerError().body(format!("提交交易失敗: {}", err));
+    // 學號清單為空時代表所有學生，場次內沒有應考紀錄時不需要重新計算
+    // 通過場次為此場次的學生已由外鍵清空，重新計算後補上新的通過場次，失敗時連同刪除一起回滾
+    if !student_ids.is_empty() {
+        let actor = AuditActor::from_session(session, endpoint);
+        let result = async {
+            let rule_set = load_rule_set(&mut *tx).await?;
+            recompute_students(&mut tx, &actor, &rule_set, &RecomputeFilter::students(student_ids), false).await
+        }
+        .await;
+        if let Err(err) = result {
+            let _ = tx.rollback().await;
+            return Err(ApiError::database(t!(UpdatePassStatusFailed), err));
+        }
     }
 
-    HttpResponse::Ok().body("考試記錄刪除成功")
+    tx.commit().await.map_err(|err| ApiError::database(t!(CommitFailed), err))?;
+    Ok(())
 }
\ No newline at end of file
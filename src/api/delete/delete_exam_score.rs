@@ -1,90 +1,172 @@
 use actix_session::Session;
 use actix_web::{post, web, HttpRequest, HttpResponse};
-use sqlx::MySqlPool;
-use crate::api::lib::{is_authorization, update_student_status};
+use sqlx::{MySqlConnection, MySqlPool};
+use crate::api::lib::{is_authorization, update_students_status};
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::audit::{write_audit_log, AuditActor, AuditTarget};
+use crate::api::etag::{attendance_etag, check_version, required_if_match};
 use serde::Deserialize;
+use std::collections::HashSet;
+use utoipa::ToSchema;
 
-#[derive(Debug, Deserialize)]
-struct StudentData {
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = DeleteScoreItem)]
+pub(crate) struct StudentData {
     student_id: String,
-    correct_answers_count: i32, 
+    correct_answers_count: i32,
 }
 
-#[derive(Debug, Deserialize)]
-struct StudentSelection {
-    students: Vec<StudentData>,
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = DeleteScores)]
+pub(crate) struct StudentSelection {
+    pub(crate) students: Vec<StudentData>,
 }
 
+#[utoipa::path(tag = "scores", responses((status = 200, description = "刪除的筆數", body = String)))]
 #[post("/api/delete_exam_score")]
 async fn delete_exam_score(
     data: web::Json<StudentSelection>,
     req: HttpRequest,
     session: Session,
     db_pool: web::Data<MySqlPool>,
-) -> HttpResponse {
-    if !is_authorization(req, session.clone()) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Admin)?;
     let exam_session_sn = match session.get::<i32>("delete_exam_session_sn") {
         Ok(Some(sn)) => sn,
-        Ok(None) => return HttpResponse::BadRequest().body("請先選擇要查詢的場次再刪除成績"),
-        Err(_) => return HttpResponse::InternalServerError().body("伺服器錯誤，無法解析 session。"),
+        Ok(None) => return Err(ApiError::invalid_input(t!(SelectSessionBeforeDelete))),
+        Err(_) => return Err(ApiError::internal(t!(SessionParseFailed))),
     };
-
-    let ids: Vec<(String, i32)> = data.students.iter()
-        .map(|s| (s.student_id.clone(), s.correct_answers_count))
-        .collect();
-    if ids.is_empty() {
-        return HttpResponse::BadRequest().body("未選擇任何學生");
+    if data.students.is_empty() {
+        return Err(ApiError::invalid_input(t!(NoStudentSelected)));
     }
     let mut transaction = match db_pool.begin().await {
         Ok(tx) => tx,
-        Err(_) => return HttpResponse::InternalServerError().body("無法啟動資料庫交易"),
+        Err(err) => return Err(ApiError::database(t!(StartTransactionFailed), err)),
     };
-    let mut update_list = Vec::new();
+    let delete_number =
+        delete_scores(&mut transaction, &session, "/api/delete_exam_score", exam_session_sn, &data.students).await?;
+
+    // 提交交易
+    match transaction.commit().await {
+        Ok(_) => Ok(HttpResponse::Ok().body(t!(DeletedCount, delete_number))),
+        Err(err) => Err(ApiError::database(t!(DeleteRetry), err)),
+    }
+
+}
+
+/// 以場次 SN 指定要刪除成績的場次，不依賴查詢時存入 session 的資料
+/// If-Match 需帶入 GET /api/exam_scores/{sn} 回應的 ETag，場次內任一筆成績已被修改時回傳 412
+#[utoipa::path(
+    tag = "scores",
+    params(("If-Match" = String, Header, description = "讀取時回應的 ETag")),
+    responses((status = 200, description = "刪除的筆數，ETag 標頭為新的版本", body = String)),
+)]
+#[post("/api/delete_exam_score/{sn}")]
+async fn delete_exam_score_by_sn(
+    path: web::Path<i32>,
+    data: web::Json<StudentSelection>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req.clone(), session.clone(), Role::Admin)?;
+    let if_match = required_if_match(&req)?;
+    remove_scores(&db_pool, &session, "/api/delete_exam_score", path.into_inner(), &if_match, &data.students).await
+}
+
+/// 比對場次內成績的版本後刪除選取的成績，/api/v2/exam-sessions/{sn}/attendance 共用
+pub(crate) async fn remove_scores(
+    db_pool: &web::Data<MySqlPool>,
+    session: &Session,
+    endpoint: &str,
+    exam_session_sn: i32,
+    if_match: &str,
+    students: &[StudentData],
+) -> Result<HttpResponse, ApiError> {
+    if students.is_empty() {
+        return Err(ApiError::invalid_input(t!(NoStudentSelected)));
+    }
+    let mut transaction = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
+    let exists = sqlx::query_scalar::<_, i32>("SELECT SN FROM ExamSessions WHERE SN = ?")
+        .bind(exam_session_sn)
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(|err| ApiError::database(t!(QuerySessionFailed), err))?;
+    if exists.is_none() {
+        return Err(ApiError::new(ErrorCode::ExamSessionNotFound, t!(ExamSessionNotFound)));
+    }
+    let current = attendance_etag(&mut transaction, exam_session_sn, true)
+        .await
+        .map_err(|err| ApiError::database(t!(ReadOriginalFailed), err))?;
+    check_version(if_match, &current)?;
+
+    let delete_number = delete_scores(&mut transaction, session, endpoint, exam_session_sn, students).await?;
+    let etag = attendance_etag(&mut transaction, exam_session_sn, false)
+        .await
+        .map_err(|err| ApiError::database(t!(ReadOriginalFailed), err))?;
+    transaction.commit().await.map_err(|err| ApiError::database(t!(DeleteRetry), err))?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("ETag", etag))
+        .body(t!(DeletedCount, delete_number)))
+}
+
+/// 刪除場次內選取的成績，並在同一個交易內重新計算被刪除成績的學生的通過狀態
+/// 任一筆失敗時回傳錯誤，交易未提交即會回滾，回傳刪除筆數
+async fn delete_scores(
+    transaction: &mut MySqlConnection,
+    session: &Session,
+    endpoint: &str,
+    exam_session_sn: i32,
+    students: &[StudentData],
+) -> Result<i32, ApiError> {
+    let mut update_list = HashSet::new();
     let query = r#"
-        DELETE FROM ExamAttendance 
-        WHERE ExamSession_SN = (?) 
-        AND StudentID = (?) 
+        DELETE FROM ExamAttendance
+        WHERE ExamSession_SN = (?)
+        AND StudentID = (?)
         AND CorrectAnswersCount = (?)
     "#;
     let mut delete_number = 0;
 
-    for (id, count) in &ids {
+    for student in students {
+        let id = &student.student_id;
+        let target = AuditTarget::ExamAttendance { exam_session_sn, student_id: id };
+        let before = match target.snapshot(&mut *transaction).await {
+            Ok(before) => before,
+            Err(e) => {
+                eprintln!("讀取原始資料失敗: {:?}", e);
+                return Err(ApiError::new(ErrorCode::DatabaseError, t!(DeleteRolledBack)));
+            }
+        };
         match sqlx::query(query)
             .bind(exam_session_sn)
             .bind(id)
-            .bind(count)
+            .bind(student.correct_answers_count)
             .execute(&mut *transaction)
             .await
         {
             Ok(result) => {
                 if result.rows_affected() > 0 {
-                    update_list.push(id.clone());
+                    if let Err(e) = write_audit_log(&mut *transaction, session, endpoint, &target, before).await {
+                        eprintln!("寫入稽核紀錄失敗: {:?}", e);
+                        return Err(ApiError::new(ErrorCode::DatabaseError, t!(DeleteRolledBack)));
+                    }
+                    update_list.insert(id.to_ascii_uppercase());
                     delete_number += 1;
                 }
             }
             Err(e) => {
                 eprintln!("刪除失敗: {:?}", e);
-                let _ = transaction.rollback().await;
-                return HttpResponse::InternalServerError().body("刪除失敗，所有變更已回滾");
+                return Err(ApiError::new(ErrorCode::DatabaseError, t!(DeleteRolledBack)));
             }
         }
     }
-
-    // 提交交易
-    match transaction.commit().await {
-        Ok(_) => {
-            for student_id in update_list {
-                if let Err(e) = update_student_status(db_pool.clone(), student_id).await {
-                    println!("更新學生狀態失敗: {}", e);
-                }
-            }
-            HttpResponse::Ok().body(format!("成功刪除 {} 筆記錄", delete_number))
-        }
-        Err(_) => {
-            HttpResponse::InternalServerError().body("刪除失敗請重新提交刪除")
-        }
-    }
-    
+    let actor = AuditActor::from_session(session, endpoint);
+    update_students_status(&mut *transaction, &actor, update_list)
+        .await
+        .map_err(|err| ApiError::database(t!(UpdatePassStatusFailed), err))?;
+    Ok(delete_number)
 }
\ No newline at end of file
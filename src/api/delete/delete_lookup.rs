@@ -0,0 +1,67 @@
+use actix_session::Session;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use sqlx::MySqlPool;
+use serde::Deserialize;
+use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::audit::{write_audit_log, AuditTarget};
+use crate::api::lookup::LookupKind;
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema)]
+struct DeleteLookup {
+    kind: LookupKind,
+    sn: i32,
+}
+
+/// 刪除的結果，仍被學生使用的項目不會刪除
+enum Outcome {
+    Deleted,
+    NotFound,
+    InUse(i64),
+}
+
+#[utoipa::path(tag = "lookup", responses((status = 200, description = "刪除成功", body = String)))]
+#[post("/api/delete_lookup")]
+async fn delete_lookup(
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+    data: web::Json<DeleteLookup>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Admin)?;
+    let target = AuditTarget::Lookup(data.kind, data.sn);
+    let result: Result<Outcome, sqlx::Error> = async {
+        let mut tx = db_pool.begin().await?;
+        let before = target.snapshot(&mut tx).await?;
+        if before.is_none() {
+            return Ok(Outcome::NotFound);
+        }
+        let in_use: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM StudentInfo WHERE {} = ?",
+            data.kind.student_column()
+        ))
+        .bind(data.sn)
+        .fetch_one(&mut *tx)
+        .await?;
+        if in_use > 0 {
+            return Ok(Outcome::InUse(in_use));
+        }
+        sqlx::query(&format!("DELETE FROM {} WHERE SN = ?", data.kind.table()))
+            .bind(data.sn)
+            .execute(&mut *tx)
+            .await?;
+        write_audit_log(&mut tx, &session, "/api/delete_lookup", &target, before).await?;
+        tx.commit().await?;
+        Ok(Outcome::Deleted)
+    }
+    .await;
+    match result {
+        Ok(Outcome::Deleted) => Ok(HttpResponse::Ok().body(t!(DeleteLookupSuccess))),
+        Ok(Outcome::NotFound) => Err(ApiError::not_found(t!(LookupNotFound))),
+        Ok(Outcome::InUse(count)) => Err(ApiError::new(ErrorCode::Conflict, t!(LookupInUse, count))),
+        Err(err) => Err(ApiError::database(t!(DeleteLookupFailed), err)),
+    }
+}
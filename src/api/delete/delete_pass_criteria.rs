@@ -0,0 +1,52 @@
+use actix_session::Session;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use sqlx::MySqlPool;
+use serde::Deserialize;
+use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::audit::{write_audit_log, AuditTarget};
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema)]
+struct DeletePassCriteria {
+    sn: i32,
+}
+
+#[utoipa::path(tag = "pass_criteria", responses((status = 200, description = "刪除成功", body = String)))]
+#[post("/api/delete_pass_criteria")]
+async fn delete_pass_criteria(
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+    data: web::Json<DeletePassCriteria>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Admin)?;
+    let target = AuditTarget::PassCriteria(data.sn);
+    let result: Result<Option<bool>, sqlx::Error> = async {
+        let mut tx = db_pool.begin().await?;
+        let is_active = sqlx::query_scalar::<_, bool>("SELECT IsActive FROM PassCriteria WHERE SN = ?")
+            .bind(data.sn)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if is_active != Some(false) {
+            return Ok(is_active);
+        }
+        let before = target.snapshot(&mut tx).await?;
+        sqlx::query("DELETE FROM PassCriteria WHERE SN = ? AND IsActive = FALSE")
+            .bind(data.sn)
+            .execute(&mut *tx)
+            .await?;
+        write_audit_log(&mut tx, &session, "/api/delete_pass_criteria", &target, before).await?;
+        tx.commit().await?;
+        Ok(is_active)
+    }
+    .await;
+    match result {
+        Ok(Some(false)) => Ok(HttpResponse::Ok().body(t!(DeleteRuleSuccess))),
+        Ok(Some(true)) => Err(ApiError::new(ErrorCode::Conflict, t!(CannotDeleteActiveRule))),
+        Ok(None) => Err(ApiError::not_found(t!(RuleNotFound))),
+        Err(err) => Err(ApiError::database(t!(DeleteRuleFailed), err)),
+    }
+}
@@ -4,40 +4,46 @@ use sqlx::MySqlPool;
 use serde::Deserialize;
 use chrono::NaiveDate;
 use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use crate::api::audit::{write_audit_log, AuditTarget};
+use utoipa::ToSchema;
 
 // 定義接收的 JSON 數據結構
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
+#[schema(as = DeleteScholarshipItem)]
 struct StudentData {
     student_id: String,
     received_date: String, // 格式為 "YYYY-MM-DD"
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
+#[schema(as = DeleteScholarship)]
 struct DeleteRequest {
     students: Vec<StudentData>,
 }
 
+#[utoipa::path(tag = "scholarships", responses((status = 200, description = "刪除的筆數", body = String)))]
 #[post("/api/delete_scholarship")]
 async fn delete_scholarship(
     req: HttpRequest,
     session: Session,
     db_pool: web::Data<MySqlPool>,
     data: web::Json<DeleteRequest>,
-) -> HttpResponse {
+) -> Result<HttpResponse, ApiError> {
     // 驗證授權
-    if !is_authorization(req, session) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
+    is_authorization(req, session.clone(), Role::Admin)?;
 
     // 檢查是否有資料
     if data.students.is_empty() {
-        return HttpResponse::BadRequest().body("未提供任何資料");
+        return Err(ApiError::invalid_input(t!(NoDataProvided)));
     }
 
     // 開始交易
     let mut tx = match db_pool.begin().await {
         Ok(t) => t,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("啟動交易失敗: {}", e)),
+        Err(e) => return Err(ApiError::database(t!(StartTransactionFailed), e)),
     };
 
     let mut deleted_count = 0;
@@ -49,7 +55,16 @@ async fn delete_scholarship(
             Ok(date) => date,
             Err(_) => {
                 let _ = tx.rollback().await; // 明確回滾
-                return HttpResponse::BadRequest().body(format!("無效的日期格式: {}", student.received_date));
+                return Err(ApiError::invalid_input(t!(InvalidDateValue, student.received_date)));
+            }
+        };
+
+        let target = AuditTarget::Scholarship(&student.student_id);
+        let before = match target.snapshot(&mut tx).await {
+            Ok(before) => before,
+            Err(e) => {
+                let _ = tx.rollback().await;
+                return Err(ApiError::database(t!(QueryFailed), e));
             }
         };
 
@@ -65,21 +80,25 @@ async fn delete_scholarship(
         match result {
             Ok(res) => {
                 deleted_count += res.rows_affected() as usize;
+                if let Err(e) = write_audit_log(&mut tx, &session, "/api/delete_scholarship", &target, before).await {
+                    let _ = tx.rollback().await;
+                    return Err(ApiError::database(t!(AuditLogFailed), e));
+                }
             }
             Err(e) => {
                 println!("刪除記錄失敗: {}", e);
                 let _ = tx.rollback().await; // 明確回滾
-                return HttpResponse::InternalServerError().body(format!("刪除失敗: {}", e));
+                return Err(ApiError::database(t!(DeleteFailed), e));
             }
         }
     }
 
     // 提交交易
     match tx.commit().await {
-        Ok(_) => HttpResponse::Ok().body(format!("成功刪除 {} 筆獎學金紀錄", deleted_count)),
+        Ok(_) => Ok(HttpResponse::Ok().body(t!(ScholarshipDeletedCount, deleted_count))),
         Err(e) => {
             // 這裡不需要回滾，因為 tx 銷毀時會自動回滾
-            HttpResponse::InternalServerError().body(format!("提交交易失敗: {}", e))
+            Err(ApiError::database(t!(CommitFailed), e))
         }
     }
 }
\ No newline at end of file
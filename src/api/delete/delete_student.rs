@@ -3,35 +3,52 @@ use actix_web::{post, web, HttpRequest, HttpResponse};
 use sqlx::MySqlPool;
 use serde::Deserialize;
 use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::audit::{snapshot_all, student_targets, write_audit_logs, AuditTarget};
+use crate::api::etag::{check_version, etag_of};
+use utoipa::ToSchema;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct DeleteStudentRequest {
     student_id: String,
 }
 
+#[utoipa::path(tag = "students", responses((status = 200, description = "已刪除學生與其應考、獎學金紀錄", body = String)))]
 #[post("/api/delete_student")]
 async fn delete_student(
     req: HttpRequest,
     session: Session,
     db_pool: web::Data<MySqlPool>,
     data: web::Json<DeleteStudentRequest>,
-) -> HttpResponse {
+) -> Result<HttpResponse, ApiError> {
     // 驗證授權
-    if !is_authorization(req, session) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
+    is_authorization(req, session.clone(), Role::Admin)?;
 
 
     // 獲取學號
     let student_id = data.student_id.trim().to_ascii_uppercase();
     if student_id.is_empty() {
-        return HttpResponse::BadRequest().body("學號不得為空");
+        return Err(ApiError::invalid_input(t!(StudentIdRequired)));
     }
     println!("學號: {}", student_id);
+    remove_student(db_pool.get_ref(), &session, "/api/delete_student", &student_id, None).await
+}
+
+/// 刪除學生與其應考、獎學金紀錄，if_match 有值時先比對學生資料的版本
+/// /api/v2/students/{student_id} 共用
+pub(crate) async fn remove_student(
+    db_pool: &MySqlPool,
+    session: &Session,
+    endpoint: &str,
+    student_id: &str,
+    if_match: Option<&str>,
+) -> Result<HttpResponse, ApiError> {
     // 開始交易
     let mut tx = match db_pool.begin().await {
         Ok(t) => t,
-        Err(e) => return HttpResponse::InternalServerError().body(format!("啟動交易失敗: {}", e)),
+        Err(e) => return Err(ApiError::database(t!(StartTransactionFailed), e)),
     };
 
     // 檢查學號是否存在
@@ -46,8 +63,28 @@ async fn delete_student(
 
     if !exists {
         let _ = tx.rollback().await;
-        return HttpResponse::BadRequest().body(format!("學號 {} 不存在", student_id));
+        return Err(ApiError::new(ErrorCode::StudentNotFound, t!(StudentIdNotExist, student_id)));
     }
+    if let Some(if_match) = if_match {
+        let current = AuditTarget::Student(student_id)
+            .snapshot_for_update(&mut tx)
+            .await
+            .map_err(|e| ApiError::database(t!(StudentQueryOriginalFailed, student_id), e))?;
+        check_version(if_match, &etag_of(current.as_deref().unwrap_or_default()))?;
+    }
+
+    // 刪除前記錄學生、獎學金與應考紀錄的內容
+    let snapshots = match student_targets(&mut tx, student_id).await {
+        Ok(targets) => snapshot_all(&mut tx, targets).await,
+        Err(e) => Err(e),
+    };
+    let snapshots = match snapshots {
+        Ok(snapshots) => snapshots,
+        Err(e) => {
+            let _ = tx.rollback().await;
+            return Err(ApiError::database(t!(StudentQueryOriginalFailed, student_id), e));
+        }
+    };
 
     // 刪除 ExamAttendance 表中的記錄
     let result_attendance = sqlx::query!(
@@ -59,7 +96,7 @@ async fn delete_student(
 
     if let Err(e) = result_attendance {
         let _ = tx.rollback().await;
-        return HttpResponse::InternalServerError().body(format!("學號 {}：刪除考試記錄失敗: {}", student_id, e));
+        return Err(ApiError::database(t!(StudentDeleteExamFailed, student_id), e));
     }
 
     // 刪除 StudentInfo 表中的記錄
@@ -74,17 +111,21 @@ async fn delete_student(
         Ok(res) => {
             if res.rows_affected() == 0 {
                 let _ = tx.rollback().await;
-                return HttpResponse::InternalServerError().body(format!("學號 {}：刪除學生資訊失敗", student_id));
+                return Err(ApiError::new(ErrorCode::StudentNotFound, t!(StudentDeleteInfoFailed, student_id)));
+            }
+            if let Err(e) = write_audit_logs(&mut tx, session, endpoint, snapshots).await {
+                let _ = tx.rollback().await;
+                return Err(ApiError::database(t!(StudentAuditLogFailed, student_id), e));
             }
             // 提交交易
             match tx.commit().await {
-                Ok(_) => HttpResponse::Ok().body(format!("成功刪除學號 {}", student_id)),
-                Err(e) => HttpResponse::InternalServerError().body(format!("提交交易失敗: {}", e)),
+                Ok(_) => Ok(HttpResponse::Ok().body(t!(StudentDeleted, student_id))),
+                Err(e) => Err(ApiError::database(t!(CommitFailed), e)),
             }
         }
         Err(e) => {
             let _ = tx.rollback().await;
-            HttpResponse::InternalServerError().body(format!("學號 {}：刪除學生資訊失敗: {}", student_id, e))
+            Err(ApiError::database(t!(StudentDeleteInfoFailed, student_id), e))
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,53 @@
+use actix_session::Session;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use sqlx::MySqlPool;
+use crate::api::lib::{current_username, is_authorization};
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use crate::api::audit::{write_audit_log, AuditTarget};
+use crate::api::session_registry::SessionRegistry;
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema)]
+struct DeleteUser {
+    username: String,
+}
+
+#[utoipa::path(tag = "users", responses((status = 200, description = "刪除成功", body = String)))]
+#[post("/api/delete_user")]
+async fn delete_user(
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+    registry: web::Data<SessionRegistry>,
+    data: web::Json<DeleteUser>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Admin)?;
+    if current_username(&session).as_deref() == Some(data.username.as_str()) {
+        return Err(ApiError::invalid_input(t!(CannotDeleteSelf)));
+    }
+    let target = AuditTarget::User(&data.username);
+    let result = async {
+        let mut tx = db_pool.begin().await?;
+        let before = target.snapshot(&mut tx).await?;
+        let res = sqlx::query("DELETE FROM users WHERE username = ?")
+            .bind(&data.username)
+            .execute(&mut *tx)
+            .await?;
+        write_audit_log(&mut tx, &session, "/api/delete_user", &target, before).await?;
+        tx.commit().await?;
+        Ok::<_, sqlx::Error>(res)
+    }
+    .await;
+    match result {
+        Ok(res) if res.rows_affected() > 0 => {
+            // 讓此使用者已登入的 session 立即失效
+            registry.revoke_after_change(&data.username).await?;
+            Ok(HttpResponse::Ok().body(t!(DeleteUserSuccess)))
+        }
+        Ok(_) => Err(ApiError::not_found(t!(UserNotFound))),
+        Err(err) => Err(ApiError::database(t!(DeleteUserFailed), err)),
+    }
+}
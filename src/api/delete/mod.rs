@@ -3,4 +3,8 @@ pub mod delete_exam_info;
 pub mod delete_exam_score;
 pub mod delete_scholarship_records;
 pub mod delete_scholarship;
-pub mod delete_student;
\ No newline at end of file
+pub mod delete_student;
+pub mod delete_pass_criteria;
+pub mod delete_user;
+pub mod revoke_session;
+pub mod delete_lookup;
\ No newline at end of file
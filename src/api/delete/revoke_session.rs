@@ -0,0 +1,41 @@
+use actix_session::Session;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use crate::api::session_registry::SessionRegistry;
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema)]
+struct RevokeSession {
+    username: String,
+    session_id: Option<String>, // 不填則撤銷此使用者所有的 session
+}
+
+#[utoipa::path(tag = "users", responses((status = 200, description = "撤銷的 session 數", body = String)))]
+#[post("/api/revoke_session")]
+async fn revoke_session(
+    req: HttpRequest,
+    session: Session,
+    registry: web::Data<SessionRegistry>,
+    data: web::Json<RevokeSession>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session, Role::Admin)?;
+    let result = match &data.session_id {
+        Some(session_id) => registry
+            .revoke(&data.username, session_id)
+            .await
+            .map(|revoked| revoked as usize),
+        None => registry.revoke_all(&data.username).await,
+    };
+    match result {
+        Ok(0) => Err(ApiError::not_found(t!(LoginSessionNotFound))),
+        Ok(count) => Ok(HttpResponse::Ok().body(t!(SessionsRevoked, count))),
+        Err(err) => {
+            eprintln!("Redis error: {:?}", err);
+            Err(ApiError::internal(t!(RevokeSessionFailed)))
+        }
+    }
+}
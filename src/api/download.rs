@@ -0,0 +1,133 @@
+use actix_session::Session;
+use actix_web::{get, web, HttpResponse};
+use futures_util::stream;
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncReadExt;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::export::{ExportFormat, ExportTable};
+use crate::api::lib::current_username;
+use crate::api::upload::TempFile;
+
+/// 下載網址的有效時間（10 分鐘），逾時後檔案會被刪除
+const DOWNLOAD_TTL: Duration = Duration::from_secs(10 * 60);
+/// 每次讀取檔案的大小
+const CHUNK_SIZE: usize = 64 * 1024;
+
+struct DownloadEntry {
+    file: TempFile, // 移除時自動刪除檔案
+    filename: String,
+    format: ExportFormat,
+    username: String,
+    expires_at: Instant,
+}
+
+/// 產生的匯出檔案，以隨機 token 對應，只有產生檔案的使用者可以下載
+#[derive(Clone, Default)]
+pub struct DownloadStore {
+    entries: Arc<Mutex<HashMap<String, DownloadEntry>>>,
+}
+
+impl DownloadStore {
+    /// 將表格存成檔案並回傳下載網址，filename 不含副檔名
+    pub fn register(
+        &self,
+        session: &Session,
+        table: &ExportTable,
+        format: ExportFormat,
+        filename: &str,
+    ) -> Result<String, ApiError> {
+        let username = current_username(session)
+            .ok_or_else(|| ApiError::new(ErrorCode::Unauthorized, t!(LoginRequired)))?;
+        let file = table.save(format).map_err(|err| {
+            println!("產生匯出檔案失敗: {}", err);
+            ApiError::internal(t!(ExportFailed))
+        })?;
+        let random_bytes: [u8; 16] = rand::thread_rng().gen();
+        let token = hex::encode(random_bytes);
+        let entry = DownloadEntry {
+            file,
+            filename: format!("{}.{}", filename, format.extension()),
+            format,
+            username,
+            expires_at: Instant::now() + DOWNLOAD_TTL,
+        };
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.insert(token.clone(), entry);
+        Ok(format!("/api/download/{}", token))
+    }
+
+    /// 移除逾時的檔案
+    pub fn purge_expired(&self) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, entry| entry.expires_at > now);
+    }
+
+    /// 在背景定期清除逾時的檔案
+    pub fn spawn_cleanup(&self) {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(60));
+            loop {
+                interval.tick().await;
+                store.purge_expired();
+            }
+        });
+    }
+}
+
+#[utoipa::path(
+    tag = "files",
+    params(("token" = String, Path, description = "查詢 API 回應的 download_url 中的 token")),
+    responses((status = 200, description = "匯出的檔案", content_type = "application/octet-stream", body = Vec<u8>)),
+)]
+#[get("/api/download/{token}")]
+pub async fn download(
+    path: web::Path<String>,
+    session: Session,
+    store: web::Data<DownloadStore>,
+) -> Result<HttpResponse, ApiError> {
+    // 由瀏覽器直接開啟網址下載，無法帶 CSRF token，改以 token 與登入的使用者驗證
+    let Some(username) = current_username(&session) else {
+        return Err(ApiError::new(ErrorCode::Unauthorized, t!(LoginRequired)));
+    };
+    store.purge_expired();
+    let (file_path, filename, format) = {
+        let entries = store.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.get(path.as_str()) {
+            Some(entry) if entry.username == username => {
+                (entry.file.path_str().to_string(), entry.filename.clone(), entry.format)
+            }
+            _ => return Err(ApiError::not_found(t!(DownloadExpired))),
+        }
+    };
+
+    let file = match tokio::fs::File::open(&file_path).await {
+        Ok(file) => file,
+        Err(err) => {
+            println!("開啟下載檔案失敗: {}", err);
+            return Err(ApiError::not_found(t!(DownloadExpired)));
+        }
+    };
+    // 分段讀取檔案，不需要一次載入記憶體
+    let body = stream::unfold(Some(file), |file| async move {
+        let mut file = file?;
+        let mut buffer = vec![0u8; CHUNK_SIZE];
+        match file.read(&mut buffer).await {
+            Ok(0) => None,
+            Ok(n) => {
+                buffer.truncate(n);
+                Some((Ok(web::Bytes::from(buffer)), Some(file)))
+            }
+            Err(err) => Some((Err(err), None)),
+        }
+    });
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .append_header(("Content-Disposition", format!("attachment; filename={}", filename)))
+        .streaming(body))
+}
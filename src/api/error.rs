@@ -0,0 +1,183 @@
+use actix_session::{SessionGetError, SessionInsertError};
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use serde::Serialize;
+use crate::api::messages::t;
+use std::fmt;
+use utoipa::ToSchema;
+
+/// 錯誤代碼，前端依代碼判斷錯誤種類，不需要解析訊息文字
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    Unauthorized,       // 未登入、Session 過期或 CSRF Token 無效
+    Forbidden,          // 權限不足
+    InvalidCredentials, // 帳號或密碼錯誤
+    AccountDisabled,    // 帳號已停用
+    TooManyAttempts,    // 登入失敗次數過多
+    InvalidInput,       // 欄位格式或內容不正確
+    InvalidFile,        // 上傳的檔案無法讀取
+    FileTooLarge,       // 上傳的檔案超過大小上限
+    MissingColumns,     // 匯入檔案缺少必要欄位
+    SheetNotFound,      // 找不到指定的工作表
+    ImportRejected,     // 匯入檔案中有錯誤的資料列
+    StudentNotFound,
+    ExamSessionNotFound,
+    NotFound,           // 其他找不到的資料
+    AlreadyExists,      // 資料重複
+    Conflict,           // 與目前資料狀態衝突
+    VersionMismatch,    // If-Match 與目前資料的 ETag 不符，資料已被其他人修改
+    VersionRequired,    // 修改時未帶入 If-Match
+    DatabaseError,
+    InternalError,
+    ServiceUnavailable, // 依賴的服務（Redis）暫時無法使用
+}
+
+impl ErrorCode {
+    pub fn status(self) -> StatusCode {
+        match self {
+            ErrorCode::Unauthorized | ErrorCode::InvalidCredentials => StatusCode::UNAUTHORIZED,
+            ErrorCode::Forbidden | ErrorCode::AccountDisabled => StatusCode::FORBIDDEN,
+            ErrorCode::TooManyAttempts => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::FileTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorCode::InvalidInput
+            | ErrorCode::InvalidFile
+            | ErrorCode::MissingColumns
+            | ErrorCode::SheetNotFound
+            | ErrorCode::ImportRejected => StatusCode::BAD_REQUEST,
+            ErrorCode::StudentNotFound | ErrorCode::ExamSessionNotFound | ErrorCode::NotFound => {
+                StatusCode::NOT_FOUND
+            }
+            ErrorCode::AlreadyExists | ErrorCode::Conflict => StatusCode::CONFLICT,
+            ErrorCode::VersionMismatch => StatusCode::PRECONDITION_FAILED,
+            ErrorCode::VersionRequired => StatusCode::PRECONDITION_REQUIRED,
+            ErrorCode::DatabaseError | ErrorCode::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::ServiceUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
+/// 錯誤發生的位置，例如匯入檔案中的某一列或表單中的某個欄位
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct ErrorDetail {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sheet: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub row: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    pub message: String,
+}
+
+/// 所有 API 共用的錯誤型別，回應為 `{"code": ..., "message": ..., "details": [...]}`
+#[derive(Debug)]
+pub struct ApiError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub details: Vec<ErrorDetail>,
+    headers: Vec<(&'static str, String)>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody<'a> {
+    code: ErrorCode,
+    message: &'a str,
+    #[serde(skip_serializing_if = "<[ErrorDetail]>::is_empty")]
+    #[schema(value_type = Vec<ErrorDetail>)]
+    details: &'a [ErrorDetail],
+}
+
+impl ApiError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        ApiError {
+            code,
+            message: message.into(),
+            details: Vec::new(),
+            headers: Vec::new(),
+        }
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        ApiError::new(ErrorCode::InvalidInput, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        ApiError::new(ErrorCode::NotFound, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        ApiError::new(ErrorCode::InternalError, message)
+    }
+
+    /// 記錄資料庫錯誤的內容，回應中只顯示 message，避免洩漏 SQL 細節
+    pub fn database(message: impl Into<String>, err: sqlx::Error) -> Self {
+        let message = message.into();
+        eprintln!("{}: {:?}", message, err);
+        ApiError::new(ErrorCode::DatabaseError, message)
+    }
+
+    /// 標示出錯的欄位
+    pub fn with_field(mut self, field: &str) -> Self {
+        let message = self.message.clone();
+        self.details.push(ErrorDetail {
+            field: Some(field.to_string()),
+            message,
+            ..Default::default()
+        });
+        self
+    }
+
+    pub fn with_details(mut self, details: Vec<ErrorDetail>) -> Self {
+        self.details.extend(details);
+        self
+    }
+
+    pub fn with_header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.code, self.message)
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(err: sqlx::Error) -> Self {
+        ApiError::database(t!(DatabaseError), err)
+    }
+}
+
+impl From<SessionInsertError> for ApiError {
+    fn from(err: SessionInsertError) -> Self {
+        eprintln!("Session error: {:?}", err);
+        ApiError::internal(t!(SessionWriteFailed))
+    }
+}
+
+impl From<SessionGetError> for ApiError {
+    fn from(err: SessionGetError) -> Self {
+        eprintln!("Session error: {:?}", err);
+        ApiError::internal(t!(SessionReadFailed))
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.code.status()
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        for (name, value) in &self.headers {
+            response.insert_header((*name, value.as_str()));
+        }
+        response.json(ErrorBody {
+            code: self.code,
+            message: &self.message,
+            details: &self.details,
+        })
+    }
+}
@@ -0,0 +1,55 @@
+use actix_web::http::header::IF_MATCH;
+use actix_web::HttpRequest;
+use sha2::{Digest, Sha256};
+use sqlx::{Error, MySqlConnection};
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+
+/// 由資料內容（稽核快照的 JSON）計算 ETag，內容不變時 ETag 就不變，不需要額外的版本欄位
+pub fn etag_of(content: &str) -> String {
+    let digest = Sha256::digest(content.as_bytes());
+    format!("\"{}\"", hex::encode(&digest[..16]))
+}
+
+/// 讀取 If-Match 標頭，修改資料時必須帶入讀取時取得的 ETag
+pub fn required_if_match(req: &HttpRequest) -> Result<String, ApiError> {
+    optional_if_match(req).ok_or_else(|| ApiError::new(ErrorCode::VersionRequired, t!(IfMatchRequired)))
+}
+
+/// 讀取 If-Match 標頭，未帶入時回傳 None，用於刪除等不強制比對版本的操作
+pub fn optional_if_match(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get(IF_MATCH)
+        .and_then(|header| header.to_str().ok())
+        .map(|header| header.trim().to_string())
+        .filter(|header| !header.is_empty())
+}
+
+/// 比對 If-Match 與目前的 ETag，不符時回傳 412 並在 ETag 標頭附上目前的版本
+/// If-Match 可以是以逗號分隔的多個 ETag，`*` 代表任何版本
+pub fn check_version(if_match: &str, current: &str) -> Result<(), ApiError> {
+    let matched = if_match.split(',').map(str::trim).any(|tag| tag == "*" || tag == current);
+    if matched {
+        Ok(())
+    } else {
+        Err(ApiError::new(ErrorCode::VersionMismatch, t!(VersionMismatch)).with_header("ETag", current))
+    }
+}
+
+/// 一個場次所有應考紀錄的 ETag，任一筆成績被修改、新增或刪除都會改變
+/// lock 為 true 時鎖定這些資料直到交易結束
+pub async fn attendance_etag(conn: &mut MySqlConnection, exam_session_sn: i32, lock: bool) -> Result<String, Error> {
+    let query = format!(
+        r#"
+        SELECT CAST(JSON_OBJECT(
+            'StudentID', StudentID, 'IsAbsent', IsAbsent, 'IsExcused', IsExcused,
+            'CorrectAnswersCount', CorrectAnswersCount, 'Notes', Notes
+        ) AS CHAR)
+        FROM ExamAttendance WHERE ExamSession_SN = ?
+        ORDER BY StudentID{}
+        "#,
+        if lock { " FOR UPDATE" } else { "" }
+    );
+    let rows: Vec<String> = sqlx::query_scalar(&query).bind(exam_session_sn).fetch_all(conn).await?;
+    Ok(etag_of(&rows.join("\n")))
+}
@@ -0,0 +1,242 @@
+use actix_web::http::header::{self, HeaderMap};
+use actix_web::HttpResponse;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use xlsxwriter::Workbook;
+use crate::api::upload::TempFile;
+use utoipa::{IntoParams, ToSchema};
+
+/// 匯出檔案的格式
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Xlsx,
+    Csv,
+    Json, // NDJSON，一列一個 JSON 物件
+}
+
+/// 匯出 API 共用的 query string，例如 `?format=csv`
+#[derive(Deserialize, Debug, Default, IntoParams)]
+pub struct ExportQuery {
+    pub format: Option<ExportFormat>,
+}
+
+impl ExportFormat {
+    /// 優先使用 format 參數，其次依 Accept 標頭判斷，都沒有時回傳 None 由各 API 使用原本的回應
+    pub fn negotiate(query: &ExportQuery, headers: &HeaderMap) -> Option<ExportFormat> {
+        if query.format.is_some() {
+            return query.format;
+        }
+        let accept = headers.get(header::ACCEPT)?.to_str().ok()?;
+        accept.split(',').map(|item| item.split(';').next().unwrap_or_default().trim()).find_map(|mime| match mime {
+            "text/csv" => Some(ExportFormat::Csv),
+            "application/x-ndjson" => Some(ExportFormat::Json),
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet" => Some(ExportFormat::Xlsx),
+            _ => None,
+        })
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Xlsx => "xlsx",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "ndjson",
+        }
+    }
+}
+
+/// 匯出表格中的一格
+#[derive(Debug, Clone)]
+pub enum ExportValue {
+    Text(String),
+    Number(f64),
+    Empty,
+}
+
+impl From<String> for ExportValue {
+    fn from(value: String) -> Self {
+        ExportValue::Text(value)
+    }
+}
+
+impl From<&str> for ExportValue {
+    fn from(value: &str) -> Self {
+        ExportValue::Text(value.to_string())
+    }
+}
+
+impl From<i32> for ExportValue {
+    fn from(value: i32) -> Self {
+        ExportValue::Number(value as f64)
+    }
+}
+
+impl From<u16> for ExportValue {
+    fn from(value: u16) -> Self {
+        ExportValue::Number(value as f64)
+    }
+}
+
+impl From<u8> for ExportValue {
+    fn from(value: u8) -> Self {
+        ExportValue::Number(value as f64)
+    }
+}
+
+impl<T: Into<ExportValue>> From<Option<T>> for ExportValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(ExportValue::Empty, Into::into)
+    }
+}
+
+impl ExportValue {
+    fn to_text(&self) -> String {
+        match self {
+            ExportValue::Text(text) => text.clone(),
+            ExportValue::Number(number) if number.fract() == 0.0 => format!("{}", *number as i64),
+            ExportValue::Number(number) => number.to_string(),
+            ExportValue::Empty => String::new(),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        match self {
+            ExportValue::Text(text) => Value::String(text.clone()),
+            ExportValue::Number(number) if number.fract() == 0.0 => Value::from(*number as i64),
+            ExportValue::Number(number) => Value::from(*number),
+            ExportValue::Empty => Value::Null,
+        }
+    }
+}
+
+/// 匯出欄位：key 為 JSON 的欄位名稱，title 為 Excel 與 CSV 的標題
+pub struct ExportColumn {
+    pub key: &'static str,
+    pub title: &'static str,
+}
+
+/// 匯出的表格，同一份資料可以輸出成 xlsx、CSV 或 NDJSON
+pub struct ExportTable {
+    columns: &'static [ExportColumn],
+    preamble: Vec<String>, // 只寫入 xlsx 的第一列，例如考試日期
+    rows: Vec<Vec<ExportValue>>,
+}
+
+impl ExportTable {
+    pub fn new(columns: &'static [ExportColumn]) -> Self {
+        ExportTable { columns, preamble: Vec::new(), rows: Vec::new() }
+    }
+
+    pub fn with_preamble(mut self, preamble: Vec<String>) -> Self {
+        self.preamble = preamble;
+        self
+    }
+
+    pub fn push(&mut self, row: Vec<ExportValue>) {
+        self.rows.push(row);
+    }
+
+    pub fn to_xlsx(&self) -> Result<Vec<u8>, String> {
+        let output_file = self.save(ExportFormat::Xlsx)?;
+        std::fs::read(output_file.path_str()).map_err(|e| format!("讀取暫存檔失敗: {}", e))
+    }
+
+    /// 寫入暫存檔，供下載網址使用
+    pub fn save(&self, format: ExportFormat) -> Result<TempFile, String> {
+        let output_file = TempFile::new(format.extension()).map_err(|e| format!("建立暫存檔失敗: {}", e))?;
+        match format {
+            ExportFormat::Xlsx => self.write_xlsx(output_file.path_str())?,
+            _ => std::fs::write(output_file.path_str(), self.render(format)?).map_err(|e| e.to_string())?,
+        }
+        Ok(output_file)
+    }
+
+    fn write_xlsx(&self, path: &str) -> Result<(), String> {
+        let workbook = Workbook::new(path).map_err(|e| e.to_string())?;
+        let mut worksheet = workbook.add_worksheet(None).map_err(|e| e.to_string())?;
+
+        let mut row_index = 0;
+        if !self.preamble.is_empty() {
+            for (c, text) in self.preamble.iter().enumerate() {
+                worksheet.write_string(0, c as u16, text, None).map_err(|e| e.to_string())?;
+            }
+            row_index += 1;
+        }
+        for (c, column) in self.columns.iter().enumerate() {
+            worksheet.write_string(row_index, c as u16, column.title, None).map_err(|e| e.to_string())?;
+        }
+        for row in &self.rows {
+            row_index += 1;
+            for (c, value) in row.iter().enumerate() {
+                let c = c as u16;
+                match value {
+                    ExportValue::Text(text) => worksheet.write_string(row_index, c, text, None),
+                    ExportValue::Number(number) => worksheet.write_number(row_index, c, *number, None),
+                    ExportValue::Empty => Ok(()),
+                }
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        workbook.close().map_err(|e| e.to_string())
+    }
+
+    /// UTF-8 加上 BOM，讓 Excel 開啟時能正確顯示中文
+    pub fn to_csv(&self) -> Result<Vec<u8>, String> {
+        let mut writer = csv::Writer::from_writer(b"\xEF\xBB\xBF".to_vec());
+        writer.write_record(self.columns.iter().map(|column| column.title)).map_err(|e| e.to_string())?;
+        for row in &self.rows {
+            writer.write_record(row.iter().map(ExportValue::to_text)).map_err(|e| e.to_string())?;
+        }
+        writer.into_inner().map_err(|e| e.to_string())
+    }
+
+    pub fn to_ndjson(&self) -> Vec<u8> {
+        let mut output = Vec::new();
+        for row in &self.rows {
+            let object: Map<String, Value> = self
+                .columns
+                .iter()
+                .zip(row)
+                .map(|(column, value)| (column.key.to_string(), value.to_json()))
+                .collect();
+            output.extend(Value::Object(object).to_string().into_bytes());
+            output.push(b'\n');
+        }
+        output
+    }
+
+    pub fn render(&self, format: ExportFormat) -> Result<Vec<u8>, String> {
+        match format {
+            ExportFormat::Xlsx => self.to_xlsx(),
+            ExportFormat::Csv => self.to_csv(),
+            ExportFormat::Json => Ok(self.to_ndjson()),
+        }
+    }
+
+    /// 以檔案下載的方式回傳，filename 不含副檔名
+    pub fn respond(&self, format: ExportFormat, filename: &str) -> Result<HttpResponse, ApiError> {
+        match self.render(format) {
+            Ok(data) => Ok(HttpResponse::Ok()
+                .content_type(format.content_type())
+                .append_header((
+                    "Content-Disposition",
+                    format!("attachment; filename={}.{}", filename, format.extension()),
+                ))
+                .body(data)),
+            Err(err) => {
+                println!("產生匯出檔案失敗: {}", err);
+                Err(ApiError::internal(t!(ExportFailed)))
+            }
+        }
+    }
+}
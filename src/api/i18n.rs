@@ -0,0 +1,115 @@
+use actix_session::SessionExt;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderValue, ACCEPT_LANGUAGE, CONTENT_LANGUAGE};
+use actix_web::middleware::Next;
+use actix_web::Error;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// 使用者語言偏好在 session 中的 key，登入時從 users.language 載入
+pub const LANG_SESSION_KEY: &str = "lang";
+
+/// 回應訊息使用的語言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+pub enum Lang {
+    #[default]
+    #[serde(rename = "zh-TW")]
+    ZhTw,
+    #[serde(rename = "en")]
+    En,
+}
+
+impl Lang {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Lang::ZhTw => "zh-TW",
+            Lang::En => "en",
+        }
+    }
+
+    /// 只看主要語言，zh、zh-TW、zh-Hant 都視為繁體中文，en、en-US 都視為英文
+    pub fn from_tag(tag: &str) -> Option<Lang> {
+        let primary = tag.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+        match primary.as_str() {
+            "zh" => Some(Lang::ZhTw),
+            "en" => Some(Lang::En),
+            _ => None,
+        }
+    }
+
+    /// 依 Accept-Language 的 q 值選出支援的語言，例如 `en-US,en;q=0.9,zh-TW;q=0.8` 為英文
+    pub fn from_accept_language(header: &str) -> Option<Lang> {
+        let mut best: Option<(Lang, f32)> = None;
+        for item in header.split(',') {
+            let mut parts = item.split(';');
+            let Some(lang) = parts.next().and_then(Lang::from_tag) else {
+                continue;
+            };
+            let q = parts
+                .find_map(|part| part.trim().strip_prefix("q="))
+                .and_then(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            // 同樣的 q 值以先出現的為準
+            if q > 0.0 && !matches!(best, Some((_, best_q)) if best_q >= q) {
+                best = Some((lang, q));
+            }
+        }
+        best.map(|(lang, _)| lang)
+    }
+}
+
+tokio::task_local! {
+    static LANG: Lang;
+}
+
+/// 目前請求使用的語言，不在請求中（例如背景工作）時為繁體中文
+pub fn current_lang() -> Lang {
+    LANG.try_with(|lang| *lang).unwrap_or_default()
+}
+
+/// 決定這次請求的語言：使用者的語言設定 > Accept-Language > 繁體中文
+/// 必須在 SessionMiddleware 內層才能取得 session
+pub async fn localize(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let preference = req.get_session().get::<Lang>(LANG_SESSION_KEY).unwrap_or(None);
+    let lang = preference
+        .or_else(|| {
+            req.headers()
+                .get(ACCEPT_LANGUAGE)
+                .and_then(|header| header.to_str().ok())
+                .and_then(Lang::from_accept_language)
+        })
+        .unwrap_or_default();
+    let mut res = LANG.scope(lang, next.call(req)).await?;
+    res.headers_mut()
+        .insert(CONTENT_LANGUAGE, HeaderValue::from_static(lang.as_str()));
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_language_picks_highest_q() {
+        let cases = [
+            ("en-US,en;q=0.9,zh-TW;q=0.8", Some(Lang::En)),
+            ("zh-TW,zh;q=0.9,en;q=0.8", Some(Lang::ZhTw)),
+            ("en;q=0.5, zh-Hant;q=0.7", Some(Lang::ZhTw)),
+            ("ja,en;q=0.3", Some(Lang::En)), // 不支援的語言略過
+            ("fr-FR,de;q=0.9", None),
+            ("zh;q=0, en;q=0.1", Some(Lang::En)), // q=0 表示不接受
+            ("en;q=0", None),
+            ("zh-TW;q=0.8,en;q=0.8", Some(Lang::ZhTw)), // 同樣的 q 值以先出現的為準
+            ("en;q=abc,zh;q=0.9", Some(Lang::En)), // 無法解析的 q 值視為 1
+            (" EN-gb ; q=0.6 , zh_TW ; q=0.4", Some(Lang::En)),
+            ("", None),
+        ];
+        for (header, expected) in cases {
+            assert_eq!(Lang::from_accept_language(header), expected, "{:?}", header);
+        }
+    }
+}
@@ -0,0 +1,444 @@
+use actix_multipart::Multipart;
+use actix_session::Session;
+use actix_web::HttpResponse;
+use base64::Engine as _;
+use rand::Rng;
+use redis::aio::MultiplexedConnection;
+use redis::{AsyncCommands, RedisResult};
+use serde::{Deserialize, Serialize};
+use sqlx::{MySql, Transaction};
+use crate::api::error::{ApiError, ErrorCode, ErrorDetail};
+use crate::api::messages::t;
+use crate::api::upload::{read_upload, SPREADSHEET_EXTENSIONS, Sheet, SheetSelection, TempFile, UploadedFile};
+use calamine::Data;
+use std::collections::HashMap;
+use xlsxwriter::format::FormatColor;
+use xlsxwriter::{Format, Workbook};
+use utoipa::{IntoParams, ToSchema};
+
+/// 預覽結果保留的時間（30 分鐘），逾時需重新上傳
+const PREVIEW_TTL_SECONDS: u64 = 30 * 60;
+const PREVIEW_PREFIX: &str = "cpe:import_preview:";
+
+/// 支援預覽的匯入功能
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportKind {
+    StudentInfo,
+    ExamScore,
+    Scholarship,
+    ModifyExamScore,
+}
+
+/// 匯入 API 的 query string
+/// - `?preview=true`：只檢查並回報每一列的處理結果，不寫入資料庫
+/// - `?preview_id=...`：確認先前的預覽，使用預覽時上傳的檔案寫入資料庫
+/// - `?sheet=...`、`?all_sheets=true`：選擇要匯入的工作表，確認時沿用預覽時的選擇
+#[derive(Deserialize, Debug, Default, IntoParams)]
+pub struct ImportQuery {
+    #[serde(default)]
+    pub preview: bool,
+    pub preview_id: Option<String>,
+    pub sheet: Option<String>,
+    #[serde(default)]
+    pub all_sheets: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    Direct,  // 直接上傳並寫入
+    Preview, // 預覽，不寫入
+    Confirm, // 確認預覽後寫入
+}
+
+/// 每一列的處理結果
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RowAction {
+    Insert,
+    Update,
+    Skip,
+    Reject,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct RowResult {
+    pub sheet: String, // 工作表名稱
+    pub row: usize, // Excel 中的列號（從 1 開始）
+    pub key: String, // 學號等可以辨識此列的值
+    pub action: RowAction,
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<usize>, // 有問題的欄位（讀出範圍內的第幾欄，從 0 開始），用於在回傳的 Excel 中標示
+}
+
+#[derive(Serialize, Debug, Default, ToSchema)]
+pub struct ImportReport {
+    pub preview_id: Option<String>,
+    pub committed: bool,
+    pub inserted: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub rejected: usize,
+    pub rows: Vec<RowResult>,
+    #[serde(skip)]
+    current_sheet: String,
+}
+
+impl ImportReport {
+    /// 之後加入的列都屬於這個工作表
+    pub fn begin_sheet(&mut self, name: &str) {
+        self.current_sheet = name.to_string();
+    }
+
+    pub fn push(&mut self, row: usize, key: impl Into<String>, action: RowAction, message: Option<String>) {
+        match action {
+            RowAction::Insert => self.inserted += 1,
+            RowAction::Update => self.updated += 1,
+            RowAction::Skip => self.skipped += 1,
+            RowAction::Reject => self.rejected += 1,
+        }
+        self.rows.push(RowResult {
+            sheet: self.current_sheet.clone(),
+            row,
+            key: key.into(),
+            action,
+            message,
+            columns: Vec::new(),
+        });
+    }
+
+    /// 整列拒絕，無法對應到特定欄位時使用
+    pub fn reject(&mut self, row: usize, key: impl Into<String>, message: impl Into<String>) {
+        self.reject_at(row, key, None, message);
+    }
+
+    /// 拒絕並標示出錯的欄位
+    pub fn reject_at(&mut self, row: usize, key: impl Into<String>, column: Option<usize>, message: impl Into<String>) {
+        self.push(row, key, RowAction::Reject, Some(message.into()));
+        if let Some(last) = self.rows.last_mut() {
+            last.columns.extend(column);
+        }
+    }
+
+    /// 整列的欄位錯誤一起回報
+    pub fn reject_row(&mut self, row: usize, key: impl Into<String>, errors: RowErrors) {
+        self.push(row, key, RowAction::Reject, Some(errors.messages.join("；")));
+        if let Some(last) = self.rows.last_mut() {
+            last.columns = errors.columns;
+        }
+    }
+
+    /// 所有被拒絕列的錯誤，放在 ApiError 的 details 中回傳
+    fn reject_details(&self) -> Vec<ErrorDetail> {
+        self.rows
+            .iter()
+            .filter(|row| row.action == RowAction::Reject)
+            .map(|row| ErrorDetail {
+                sheet: Some(row.sheet.clone()),
+                row: Some(row.row),
+                field: None,
+                message: row.message.clone().unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    fn rejected_error(&self) -> ApiError {
+        ApiError::new(ErrorCode::ImportRejected, t!(ImportRejected, self.rejected))
+            .with_details(self.reject_details())
+    }
+}
+
+/// 收集同一列中所有欄位的錯誤，不在第一個錯誤就停止
+#[derive(Debug, Default)]
+pub struct RowErrors {
+    columns: Vec<usize>,
+    messages: Vec<String>,
+}
+
+impl RowErrors {
+    /// column 為出錯的欄位，選填欄位不存在時為 None
+    pub fn add(&mut self, column: Option<usize>, message: impl Into<String>) {
+        self.columns.extend(column);
+        self.messages.push(message.into());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+/// 將上傳的工作表複製一份，標示出錯的儲存格並在最後加上「錯誤說明」欄
+fn annotated_workbook(sheets: &[Sheet], report: &ImportReport) -> Result<Vec<u8>, String> {
+    let output_file = TempFile::new("xlsx").map_err(|e| format!("建立暫存檔失敗: {}", e))?;
+    let workbook = Workbook::new(output_file.path_str()).map_err(|e| e.to_string())?;
+
+    let mut error_cell = Format::new();
+    error_cell.set_bg_color(FormatColor::Custom(0xFFC7CE));
+    let mut error_date = Format::new();
+    error_date.set_bg_color(FormatColor::Custom(0xFFC7CE)).set_num_format("yyyy-mm-dd");
+    let mut date = Format::new();
+    date.set_num_format("yyyy-mm-dd");
+    let mut error_text = Format::new();
+    error_text.set_font_color(FormatColor::Red);
+
+    // 同一列可能有多筆錯誤，合併在一起顯示
+    let mut errors: HashMap<(&str, usize), (Vec<usize>, Vec<String>)> = HashMap::new();
+    for row in report.rows.iter().filter(|row| row.action == RowAction::Reject) {
+        let entry = errors.entry((row.sheet.as_str(), row.row - 1)).or_default();
+        entry.0.extend(&row.columns);
+        entry.1.extend(row.message.clone());
+    }
+
+    for sheet in sheets {
+        let mut worksheet = workbook.add_worksheet(Some(&sheet.name)).map_err(|e| e.to_string())?;
+        // 寫回原本的位置，開頭的空白列與空白欄保持空白
+        let (first_row, first_col) = sheet.origin();
+        let error_col = (first_col as usize + sheet.range.width()) as u16;
+        worksheet.write_string(first_row, error_col, &t!(ErrorColumnTitle), None).map_err(|e| e.to_string())?;
+        worksheet.set_column(error_col, error_col, 60.0, None).map_err(|e| e.to_string())?;
+        for (i, row) in sheet.range.rows().enumerate() {
+            let r = first_row + i as u32;
+            let marked = errors.get(&(sheet.name.as_str(), r as usize));
+            for (c, cell) in row.iter().enumerate() {
+                let highlighted = marked.is_some_and(|(columns, _)| columns.contains(&c));
+                let format = highlighted.then_some(&error_cell);
+                let c = (first_col as usize + c) as u16;
+                let result = match cell {
+                    Data::Int(i) => worksheet.write_number(r, c, *i as f64, format),
+                    Data::Float(f) => worksheet.write_number(r, c, *f, format),
+                    Data::String(s) | Data::DateTimeIso(s) | Data::DurationIso(s) => worksheet.write_string(r, c, s, format),
+                    Data::Bool(b) => worksheet.write_boolean(r, c, *b, format),
+                    Data::DateTime(dt) => {
+                        let format = if highlighted { &error_date } else { &date };
+                        worksheet.write_number(r, c, dt.as_f64(), Some(format))
+                    }
+                    Data::Error(e) => worksheet.write_string(r, c, &e.to_string(), format),
+                    Data::Empty if highlighted => worksheet.write_blank(r, c, format),
+                    Data::Empty => Ok(()),
+                };
+                result.map_err(|e| e.to_string())?;
+            }
+            if let Some((_, messages)) = marked {
+                worksheet
+                    .write_string(r, error_col, &messages.join("\n"), Some(&error_text))
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+    }
+    workbook.close().map_err(|e| e.to_string())?;
+    std::fs::read(output_file.path_str()).map_err(|e| format!("讀取暫存檔失敗: {}", e))
+}
+
+/// 存放在 Redis 的預覽內容，確認時重新讀取同一份檔案
+#[derive(Serialize, Deserialize)]
+struct PreviewEntry {
+    kind: ImportKind,
+    username: String,
+    filename: String,
+    data: String, // base64 編碼的檔案內容
+    context: Option<i32>, // 例如 modify_exam_score 的場次 SN
+    #[serde(default)]
+    sheets: SheetSelection,
+}
+
+#[derive(Clone)]
+pub struct ImportPreviewStore {
+    conn: MultiplexedConnection,
+}
+
+impl ImportPreviewStore {
+    pub async fn connect(redis_url: &str) -> RedisResult<Self> {
+        let client = redis::Client::open(redis_url)?;
+        let conn = client.get_multiplexed_async_connection().await?;
+        Ok(ImportPreviewStore { conn })
+    }
+
+    fn key(preview_id: &str) -> String {
+        format!("{}{}", PREVIEW_PREFIX, preview_id)
+    }
+
+    async fn save(&self, entry: &PreviewEntry) -> RedisResult<String> {
+        let mut conn = self.conn.clone();
+        let random_bytes: [u8; 16] = rand::thread_rng().gen();
+        let preview_id = hex::encode(random_bytes);
+        let value = serde_json::to_string(entry).unwrap_or_default();
+        let _: () = conn.set_ex(Self::key(&preview_id), value, PREVIEW_TTL_SECONDS).await?;
+        Ok(preview_id)
+    }
+
+    /// 以 GETDEL 取出預覽，同一份預覽只有一個確認請求能取得
+    async fn take(&self, preview_id: &str) -> RedisResult<Option<String>> {
+        let mut conn = self.conn.clone();
+        conn.get_del(Self::key(preview_id)).await
+    }
+
+    async fn restore(&self, preview_id: &str, value: &str) -> RedisResult<()> {
+        let mut conn = self.conn.clone();
+        let _: () = conn.set_ex(Self::key(preview_id), value, PREVIEW_TTL_SECONDS).await?;
+        Ok(())
+    }
+}
+
+/// 確認時從 Redis 取出的預覽
+/// 沒有成功提交就被丟棄時（交易失敗或提早回傳錯誤）放回 Redis，讓使用者可以再次確認
+struct TakenPreview {
+    store: ImportPreviewStore,
+    preview_id: String,
+    value: String,
+    consumed: bool, // 已提交或不需放回
+}
+
+impl Drop for TakenPreview {
+    fn drop(&mut self) {
+        if self.consumed {
+            return;
+        }
+        let store = self.store.clone();
+        let preview_id = std::mem::take(&mut self.preview_id);
+        let value = std::mem::take(&mut self.value);
+        tokio::spawn(async move {
+            if let Err(err) = store.restore(&preview_id, &value).await {
+                eprintln!("Redis error: {:?}", err);
+            }
+        });
+    }
+}
+
+/// 一次匯入請求要處理的檔案與模式
+pub struct ImportRequest {
+    pub upload: UploadedFile,
+    pub mode: ImportMode,
+    pub sheets: SheetSelection,
+    kind: ImportKind,
+    context: Option<i32>,
+    preview: Option<TakenPreview>,
+}
+
+impl ImportRequest {
+    /// 讀取此次要匯入的工作表
+    pub fn read_sheets(&self) -> Result<Vec<Sheet>, ApiError> {
+        self.upload.read_sheets(&self.sheets)
+    }
+}
+
+/// 依 query string 決定匯入模式，確認模式會取回預覽時上傳的檔案
+/// context 為此次匯入依賴的其他狀態，確認時必須與預覽時相同
+pub async fn read_import(
+    payload: &mut Multipart,
+    query: &ImportQuery,
+    store: &ImportPreviewStore,
+    session: &Session,
+    kind: ImportKind,
+    context: Option<i32>,
+) -> Result<ImportRequest, ApiError> {
+    let Some(preview_id) = &query.preview_id else {
+        let upload = read_upload(payload, &SPREADSHEET_EXTENSIONS).await?;
+        let mode = if query.preview { ImportMode::Preview } else { ImportMode::Direct };
+        let sheets = SheetSelection { sheet: query.sheet.clone(), all_sheets: query.all_sheets };
+        return Ok(ImportRequest { upload, mode, sheets, kind, context, preview: None });
+    };
+
+    // 取出後其他確認請求就讀不到，驗證失敗或交易沒有提交時由 TakenPreview 放回
+    let mut preview = match store.take(preview_id).await {
+        Ok(Some(value)) => TakenPreview { store: store.clone(), preview_id: preview_id.clone(), value, consumed: false },
+        Ok(None) => return Err(ApiError::not_found(t!(PreviewExpired))),
+        Err(err) => {
+            eprintln!("Redis error: {:?}", err);
+            return Err(ApiError::internal(t!(ReadPreviewFailed)));
+        }
+    };
+    let entry: PreviewEntry = match serde_json::from_str(&preview.value) {
+        Ok(entry) => entry,
+        Err(_) => {
+            preview.consumed = true; // 無法解析的預覽不放回
+            return Err(ApiError::not_found(t!(PreviewExpired)));
+        }
+    };
+    let username: Option<String> = session.get("username").unwrap_or(None);
+    if entry.kind != kind || Some(&entry.username) != username.as_ref() {
+        return Err(ApiError::not_found(t!(PreviewExpired)));
+    }
+    if entry.context != context {
+        return Err(ApiError::new(ErrorCode::Conflict, t!(PreviewStale)));
+    }
+    let data = match base64::engine::general_purpose::STANDARD.decode(&entry.data) {
+        Ok(data) => data,
+        Err(_) => return Err(ApiError::internal(t!(PreviewCorrupted))),
+    };
+    Ok(ImportRequest {
+        upload: UploadedFile { filename: entry.filename, data },
+        mode: ImportMode::Confirm,
+        sheets: entry.sheets,
+        kind,
+        context,
+        preview: Some(preview),
+    })
+}
+
+/// 依模式結束匯入：預覽一律回滾，有被拒絕的列時回滾，否則提交
+/// 直接上傳時若有被拒絕的列，回傳標示錯誤的 Excel 檔，確認預覽時回傳 ImportRejected 錯誤
+/// 回傳給前端的回應，以及資料是否已提交
+pub async fn finish_import(
+    tx: Transaction<'_, MySql>,
+    sheets: &[Sheet],
+    mut report: ImportReport,
+    request: ImportRequest,
+    store: &ImportPreviewStore,
+    session: &Session,
+    success_body: String,
+) -> Result<(HttpResponse, bool), ApiError> {
+    if request.mode == ImportMode::Preview {
+        let _ = tx.rollback().await;
+        let entry = PreviewEntry {
+            kind: request.kind,
+            username: session.get("username").unwrap_or(None).unwrap_or_default(),
+            filename: request.upload.filename,
+            data: base64::engine::general_purpose::STANDARD.encode(&request.upload.data),
+            context: request.context,
+            sheets: request.sheets,
+        };
+        // 全部被拒絕時不需要確認
+        if report.rejected < report.rows.len() {
+            match store.save(&entry).await {
+                Ok(preview_id) => report.preview_id = Some(preview_id),
+                Err(err) => {
+                    eprintln!("Redis error: {:?}", err);
+                    return Err(ApiError::internal(t!(SavePreviewFailed)));
+                }
+            }
+        }
+        return Ok((HttpResponse::Ok().json(report), false));
+    }
+
+    if report.rejected > 0 {
+        let _ = tx.rollback().await;
+        if request.mode == ImportMode::Confirm {
+            return Err(report.rejected_error());
+        }
+        return match annotated_workbook(sheets, &report) {
+            Ok(file_data) => Ok((
+                HttpResponse::BadRequest()
+                    .content_type("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet")
+                    .append_header(("Content-Disposition", "attachment; filename=import_errors.xlsx"))
+                    .append_header(("X-Import-Rejected", report.rejected.to_string()))
+                    .body(file_data),
+                false,
+            )),
+            Err(err) => {
+                println!("產生錯誤標示檔失敗: {}", err);
+                Err(report.rejected_error())
+            }
+        };
+    }
+
+    tx.commit().await.map_err(|err| ApiError::database(t!(CommitFailed), err))?;
+    match request.preview {
+        Some(mut preview) => {
+            preview.consumed = true;
+            report.committed = true;
+            Ok((HttpResponse::Ok().json(report), true))
+        }
+        None => Ok((HttpResponse::Ok().body(success_body), true)),
+    }
+}
@@ -1,19 +1,28 @@
 use actix_web::HttpRequest;
 use actix_session::Session;
-use actix_web::web;
-use sqlx::{MySqlPool, Error};
-use sqlx::Row;
+use sqlx::{MySqlConnection, Error};
+use bcrypt::{hash, DEFAULT_COST};
+use chrono::NaiveDate;
+use crate::api::audit::AuditActor;
+use crate::api::pass_rule::load_rule_set;
+use crate::api::pass_status::{recompute_students, RecomputeFilter};
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+/// 驗證 CSRF Token 與登入狀態，並確認使用者角色至少為 required
+/// 驗證失敗時回傳 Unauthorized 或 Forbidden 錯誤
 pub fn is_authorization(
     req: HttpRequest,
     session: Session,
-) -> bool {
+    required: Role,
+) -> Result<(), ApiError> {
     let csrf_token_header = req
         .headers()
         .get("X-CSRF-Token")
         .and_then(|header| header.to_str().ok());
     let csrf_token_session: Option<String> = session.get("csrf_token").unwrap_or(None);
     if csrf_token_header != csrf_token_session.as_deref() {
-        return false;
+        return Err(ApiError::new(ErrorCode::Unauthorized, t!(SessionInvalid)));
     }
 
     if session
@@ -22,67 +31,58 @@ pub fn is_authorization(
         .unwrap_or(false)
         == false
     {
-        return false;
+        return Err(ApiError::new(ErrorCode::Unauthorized, t!(SessionInvalid)));
     }
-    true
-}
-
-pub async fn update_student_status(
-    db_pool: web::Data<MySqlPool>,
-    student_id: String,
-) -> Result<(), Error> {
-    // 1. 查詢 ExamAttendance 表中該學生的 CorrectAnswersCount 資料
-    let exam_rows = sqlx::query(
-        r#"
-        SELECT CorrectAnswersCount 
-        FROM ExamAttendance 
-        WHERE StudentID = ?
-        "#
-    )
-    .bind(&student_id)
-    .fetch_all(db_pool.get_ref())
-    .await?;
 
-    // 2. 計算累計答對題數和最大一次答對題數
-    let mut total_correct_answers = 0;
-    let mut max_correct_answers = 0;
-    for row in exam_rows {
-        let correct_answers_count: i32 = row.get("CorrectAnswersCount");
-        total_correct_answers += correct_answers_count;
-        max_correct_answers = max_correct_answers.max(correct_answers_count);
+    let role = session.get::<Role>("role").unwrap_or(None);
+    match role {
+        Some(role) if role.satisfies(required) => Ok(()),
+        _ => Err(ApiError::new(ErrorCode::Forbidden, t!(PermissionDenied))),
     }
-    // 3. 判斷 is_passed 與 passing_criteria
-    let mut is_passed = false;
-    let mut conditions = Vec::new();
+}
 
-    if max_correct_answers >= 2 {
-        is_passed = true;
-        conditions.push("一次兩題");
-    }
-    if total_correct_answers >= 3 {
-        is_passed = true;
-        conditions.push("累計3題");
+/// 取得目前登入的使用者名稱
+pub fn current_username(session: &Session) -> Option<String> {
+    session.get::<String>("username").unwrap_or(None)
+}
+
+/// 以 bcrypt 雜湊密碼
+pub fn hash_password(plain_password: &str) -> Result<String, ApiError> {
+    if plain_password.chars().count() < 8 {
+        return Err(ApiError::invalid_input(t!(PasswordTooShort)).with_field("password"));
     }
+    hash(plain_password, DEFAULT_COST)
+        .map_err(|_| ApiError::internal(t!(PasswordHashFailed)))
+}
 
-    let passing_criteria = if is_passed {
-        Some(conditions.join("且"))
-    } else {
-        None
+/// 將民國學年度轉為 [start_month/1, 隔年 start_month/1 前一天] 的西元日期區間
+/// 年度過大無法換算時回傳 InvalidInput，避免直接 unwrap 造成 panic
+pub fn academic_year_range(year: u32, start_month: u32) -> Result<(NaiveDate, NaiveDate), ApiError> {
+    let start_of = |offset: i32| {
+        i32::try_from(year)
+            .ok()
+            .and_then(|y| y.checked_add(1911 + offset))
+            .and_then(|y| NaiveDate::from_ymd_opt(y, start_month, 1))
     };
+    match (start_of(0), start_of(1).and_then(|next| next.pred_opt())) {
+        (Some(start), Some(end)) => Ok((start, end)),
+        _ => Err(ApiError::invalid_input(t!(AcademicYearInvalid, year))),
+    }
+}
 
-    // 4. 更新 StudentInfo 表中的資料
-    sqlx::query!(
-        r#"
-        UPDATE StudentInfo
-        SET IsPassed = ?, PassingCriteria = ?
-        WHERE StudentID = ?
-        "#,
-        is_passed,
-        passing_criteria,
-        student_id
-    )
-    .execute(db_pool.get_ref())
-    .await?;
-
+/// 依目前啟用的通過規則重新計算多位學生的 IsPassed、PassingCriteria 與通過紀錄
+/// 在寫入成績的交易內收集學號後呼叫一次，失敗時由呼叫端回滾，避免成績與通過狀態不一致
+pub async fn update_students_status(
+    conn: &mut MySqlConnection,
+    actor: &AuditActor,
+    student_ids: impl IntoIterator<Item = String>,
+) -> Result<(), Error> {
+    let student_ids: Vec<String> = student_ids.into_iter().collect();
+    // 空白的學號清單在 RecomputeFilter 中代表所有學生
+    if student_ids.is_empty() {
+        return Ok(());
+    }
+    let rule_set = load_rule_set(&mut *conn).await?;
+    recompute_students(conn, actor, &rule_set, &RecomputeFilter::students(student_ids), false).await?;
     Ok(())
 }
\ No newline at end of file
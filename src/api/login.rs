@@ -1,4 +1,4 @@
-use actix_web::{post, web, HttpResponse, Responder};
+use actix_web::{post, web, HttpRequest, HttpResponse};
 use actix_session::Session;
 use serde::Deserialize;
 use sqlx::mysql::MySqlPool;
@@ -6,60 +6,132 @@ use sqlx::Error;
 use bcrypt::verify;
 use rand::Rng;
 use sha2::{Digest, Sha256};
-#[derive(Deserialize)]
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::i18n::{Lang, LANG_SESSION_KEY};
+use crate::api::login_throttle::{client_ip, LoginThrottle};
+use crate::api::session_registry::{SessionRecord, SessionRegistry};
+use utoipa::ToSchema;
+#[derive(Deserialize, ToSchema)]
 pub struct LoginRequest {
     username: String,
     password: String,
 }
 
+#[utoipa::path(
+    tag = "auth",
+    security(()),
+    responses((status = 200, description = "登入成功，回應頭帶有 X-CSRF-Token 與 X-User-Role", body = String)),
+)]
 #[post("/api/login")]
 async fn login(
     db_pool: web::Data<MySqlPool>,
+    registry: web::Data<SessionRegistry>,
+    throttle: web::Data<LoginThrottle>,
     login_data: web::Json<LoginRequest>,
+    req: HttpRequest,
     session: Session,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     let username = &login_data.username;
     let password = &login_data.password;
+    let ip = client_ip(&req);
+
+    // 被鎖定時不做密碼驗證
+    match throttle.check(username, &ip).await {
+        Ok(Some(retry_after)) => return Err(too_many_attempts(retry_after)),
+        Ok(None) => (),
+        Err(err) => eprintln!("Redis error: {:?}", err),
+    }
 
     match validate_user(db_pool.get_ref(), username, password).await {
-        Ok(valid) if valid => {
-            session.insert("username", username).unwrap();
-            session.insert("is_logged_in", true).unwrap();
+        Ok(LoginResult::Success(username, role, language)) => {
+            // 之後一律使用資料表中的帳號名稱，撤銷 session 與稽核紀錄才能以同樣的名稱找到
+            if let Err(err) = throttle.record_success(&username).await {
+                eprintln!("Redis error: {:?}", err);
+            }
+            session.renew();
+            session.insert("username", &username)?;
+            session.insert("is_logged_in", true)?;
+            session.insert("role", role)?;
+            if let Some(lang) = language {
+                session.insert(LANG_SESSION_KEY, lang)?;
+            }
+
+            // 登記 session，讓管理員可以查詢與撤銷
+            let session_id = generate_csrf_token();
+            let record = SessionRecord::new(session_id.clone(), username, &req);
+            if let Err(err) = registry.register(&record).await {
+                eprintln!("Redis error: {:?}", err);
+                session.purge();
+                return Err(ApiError::internal(t!(InternalServerError)));
+            }
+            session.insert("session_id", session_id)?;
 
             // 生成 CSRF Token 並存入會話
             let csrf_token = generate_csrf_token();
-            session.insert("csrf_token", &csrf_token).unwrap();
-            HttpResponse::Ok()
+            session.insert("csrf_token", &csrf_token)?;
+            Ok(HttpResponse::Ok()
                 .insert_header(("X-CSRF-Token", csrf_token)) // 將 Token 放入回應頭
-                .body("Login successful!")
-        }
-        Ok(_) => HttpResponse::Unauthorized().body("Invalid username or password."),
-        Err(err) => {
-            eprintln!("Database error: {:?}", err);
-            HttpResponse::InternalServerError().body("Internal server error.")
+                .insert_header(("X-User-Role", role.as_str()))
+                .body(t!(LoginSuccess)))
         }
+        Ok(LoginResult::Disabled) => Err(ApiError::new(ErrorCode::AccountDisabled, t!(AccountDisabled))),
+        Ok(LoginResult::Invalid) => match throttle.record_failure(username, &ip).await {
+            Ok(Some(retry_after)) => Err(too_many_attempts(retry_after)),
+            Ok(None) => Err(ApiError::new(ErrorCode::InvalidCredentials, t!(InvalidCredentials))),
+            Err(err) => {
+                eprintln!("Redis error: {:?}", err);
+                Err(ApiError::new(ErrorCode::InvalidCredentials, t!(InvalidCredentials)))
+            }
+        },
+        Err(err) => Err(ApiError::database(t!(InternalServerError), err)),
     }
 }
 
+// 登入失敗次數過多，告知前端多久後可以再試
+fn too_many_attempts(retry_after: i64) -> ApiError {
+    ApiError::new(
+        ErrorCode::TooManyAttempts,
+        t!(TooManyAttempts, retry_after),
+    )
+    .with_header("Retry-After", retry_after.to_string())
+}
+
+enum LoginResult {
+    Success(String, Role, Option<Lang>), // 資料表中的帳號名稱、角色與使用者的語言設定
+    Disabled,
+    Invalid,
+}
+
 // 驗證用戶是否存在並匹配密碼
 async fn validate_user(
     db_pool: &MySqlPool,
     username: &str,
     password: &str,
-) -> Result<bool, Error> {
+) -> Result<LoginResult, Error> {
     let query = r#"
-        SELECT password
+        SELECT username, password, role, is_disabled, language
         FROM users
         WHERE username = ?
     "#;
-    let stored_hash: Option<String> = sqlx::query_scalar(query)
+    // 比對不分大小寫，回傳資料表中實際的帳號名稱
+    let user: Option<(String, String, String, bool, Option<String>)> = sqlx::query_as(query)
         .bind(username)
         .fetch_optional(db_pool)
         .await?;
-    if let Some(stored_hash) = stored_hash {
-        Ok(verify(password, &stored_hash).unwrap_or(false))
-    } else {
-        Ok(false)
+    let Some((username, stored_hash, role, is_disabled, language)) = user else {
+        return Ok(LoginResult::Invalid);
+    };
+    if !verify(password, &stored_hash).unwrap_or(false) {
+        return Ok(LoginResult::Invalid);
+    }
+    if is_disabled {
+        return Ok(LoginResult::Disabled);
+    }
+    match Role::from_db(&role) {
+        Some(role) => Ok(LoginResult::Success(username, role, language.as_deref().and_then(Lang::from_tag))),
+        None => Ok(LoginResult::Invalid),
     }
 }
 
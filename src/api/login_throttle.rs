@@ -0,0 +1,197 @@
+use actix_web::HttpRequest;
+use redis::aio::MultiplexedConnection;
+use redis::{AsyncCommands, RedisResult};
+use serde::Serialize;
+use std::sync::OnceLock;
+use utoipa::ToSchema;
+use crate::api::session_registry::scan_keys;
+
+const FAILURE_PREFIX: &str = "cpe:login_failures:";
+const LOCK_PREFIX: &str = "cpe:login_lock:";
+const LEVEL_PREFIX: &str = "cpe:login_lock_level:";
+
+/// 計算失敗次數的時間區間（15 分鐘）
+const FAILURE_WINDOW_SECONDS: i64 = 15 * 60;
+/// 鎖定等級的保留時間，一天內重複被鎖定會逐步加長鎖定時間
+const LEVEL_WINDOW_SECONDS: i64 = 24 * 60 * 60;
+/// 第 n 次鎖定的秒數：1 分鐘、5 分鐘、15 分鐘、1 小時、1 天
+const LOCK_SECONDS: [i64; 5] = [60, 5 * 60, 15 * 60, 60 * 60, 24 * 60 * 60];
+
+/// 登入失敗的對象，帳號與 IP 分開計算
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ThrottleTarget {
+    User,
+    Ip,
+}
+
+impl ThrottleTarget {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ThrottleTarget::User => "user",
+            ThrottleTarget::Ip => "ip",
+        }
+    }
+
+    /// 在時間區間內允許的失敗次數
+    fn max_failures(&self) -> i64 {
+        match self {
+            ThrottleTarget::User => 5,
+            ThrottleTarget::Ip => 20,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct LoginLock {
+    pub target: ThrottleTarget,
+    pub value: String,
+    pub retry_after: i64, // 剩餘鎖定秒數
+}
+
+/// 登入失敗次數限制，計數存放在 Redis
+#[derive(Clone)]
+pub struct LoginThrottle {
+    conn: MultiplexedConnection,
+}
+
+impl LoginThrottle {
+    pub async fn connect(redis_url: &str) -> RedisResult<Self> {
+        let client = redis::Client::open(redis_url)?;
+        let conn = client.get_multiplexed_async_connection().await?;
+        Ok(LoginThrottle { conn })
+    }
+
+    /// 帳號的 key 使用正規化後的名稱，檢查、記錄、解除鎖定與列出鎖定都一致
+    fn key(prefix: &str, target: ThrottleTarget, value: &str) -> String {
+        let value = match target {
+            ThrottleTarget::User => normalize_username(value),
+            ThrottleTarget::Ip => value.to_string(),
+        };
+        format!("{}{}:{}", prefix, target.as_str(), value)
+    }
+
+    /// 檢查帳號與 IP 是否被鎖定，被鎖定時回傳需等待的秒數
+    pub async fn check(&self, username: &str, ip: &str) -> RedisResult<Option<i64>> {
+        let mut conn = self.conn.clone();
+        let user_ttl: i64 = conn.ttl(Self::key(LOCK_PREFIX, ThrottleTarget::User, username)).await?;
+        let ip_ttl: i64 = conn.ttl(Self::key(LOCK_PREFIX, ThrottleTarget::Ip, ip)).await?;
+        let retry_after = user_ttl.max(ip_ttl);
+        Ok((retry_after > 0).then_some(retry_after))
+    }
+
+    /// 記錄一次登入失敗，達到上限時鎖定並回傳需等待的秒數
+    pub async fn record_failure(&self, username: &str, ip: &str) -> RedisResult<Option<i64>> {
+        let user_lock = self.record(ThrottleTarget::User, username).await?;
+        let ip_lock = self.record(ThrottleTarget::Ip, ip).await?;
+        Ok(user_lock.max(ip_lock))
+    }
+
+    async fn record(&self, target: ThrottleTarget, value: &str) -> RedisResult<Option<i64>> {
+        let mut conn = self.conn.clone();
+        let failure_key = Self::key(FAILURE_PREFIX, target, value);
+        let failures: i64 = conn.incr(&failure_key, 1).await?;
+        if failures == 1 {
+            let _: () = conn.expire(&failure_key, FAILURE_WINDOW_SECONDS).await?;
+        }
+        if failures < target.max_failures() {
+            return Ok(None);
+        }
+
+        // 達到上限：依照一天內被鎖定的次數決定鎖定時間
+        let level_key = Self::key(LEVEL_PREFIX, target, value);
+        let level: i64 = conn.incr(&level_key, 1).await?;
+        let _: () = conn.expire(&level_key, LEVEL_WINDOW_SECONDS).await?;
+        let index = ((level - 1) as usize).min(LOCK_SECONDS.len() - 1);
+        let seconds = LOCK_SECONDS[index];
+        let _: () = conn.set_ex(Self::key(LOCK_PREFIX, target, value), level, seconds as u64).await?;
+        let _: () = conn.del(&failure_key).await?;
+        Ok(Some(seconds))
+    }
+
+    /// 登入成功後清除此帳號的失敗次數
+    pub async fn record_success(&self, username: &str) -> RedisResult<()> {
+        let mut conn = self.conn.clone();
+        let _: () = conn.del(Self::key(FAILURE_PREFIX, ThrottleTarget::User, username)).await?;
+        Ok(())
+    }
+
+    /// 解除鎖定，並清除失敗次數與鎖定等級，回傳原本是否有被鎖定
+    pub async fn unlock(&self, target: ThrottleTarget, value: &str) -> RedisResult<bool> {
+        let mut conn = self.conn.clone();
+        let removed: i64 = conn.del(Self::key(LOCK_PREFIX, target, value)).await?;
+        let _: () = conn
+            .del(&[Self::key(FAILURE_PREFIX, target, value), Self::key(LEVEL_PREFIX, target, value)])
+            .await?;
+        Ok(removed > 0)
+    }
+
+    /// 列出目前所有被鎖定的帳號與 IP
+    pub async fn list_locks(&self) -> RedisResult<Vec<LoginLock>> {
+        let mut conn = self.conn.clone();
+        let mut locks = Vec::new();
+        for target in [ThrottleTarget::User, ThrottleTarget::Ip] {
+            let prefix = Self::key(LOCK_PREFIX, target, "");
+            let keys = scan_keys(&self.conn, &format!("{}*", prefix)).await?;
+            for key in keys {
+                let retry_after: i64 = conn.ttl(&key).await?;
+                if retry_after > 0 {
+                    locks.push(LoginLock {
+                        target,
+                        value: key[prefix.len()..].to_string(),
+                        retry_after,
+                    });
+                }
+            }
+        }
+        Ok(locks)
+    }
+}
+
+/// 計算登入失敗次數用的帳號名稱
+/// users.username 以不分大小寫的定序比對，admin 與 ADMIN 是同一個帳號，必須共用同一組失敗次數
+pub fn normalize_username(username: &str) -> String {
+    username.trim().to_lowercase()
+}
+
+/// 用戶端 IP，登入次數限制與 session 登記表使用
+/// 設定 `TRUSTED_PROXY_HEADER`（例如 `X-Forwarded-For` 或 `X-Real-IP`）時，改用反向代理寫入的標頭，
+/// X-Forwarded-For 取最後一個位址，也就是反向代理實際看到的來源，前面的位址可由用戶端自行填入
+/// 未設定時使用連線的來源位址，此時只應讓伺服器直接對外，否則所有請求都會算在反向代理的 IP 上
+pub fn client_ip(req: &HttpRequest) -> String {
+    static HEADER: OnceLock<Option<String>> = OnceLock::new();
+    let header = HEADER.get_or_init(|| {
+        std::env::var("TRUSTED_PROXY_HEADER")
+            .ok()
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+    });
+    let forwarded = header.as_deref().and_then(|name| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .map(str::trim)
+            .filter(|ip| !ip.is_empty())
+    });
+    match forwarded {
+        Some(ip) => ip.to_string(),
+        None => req.peer_addr().map(|addr| addr.ip().to_string()).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn username_keys_ignore_case_and_spaces() {
+        for username in ["admin", "Admin", "ADMIN", " admin "] {
+            assert_eq!(LoginThrottle::key(LOCK_PREFIX, ThrottleTarget::User, username), "cpe:login_lock:user:admin");
+        }
+        assert_eq!(
+            LoginThrottle::key(FAILURE_PREFIX, ThrottleTarget::Ip, "2001:DB8::1"),
+            "cpe:login_failures:ip:2001:DB8::1"
+        );
+    }
+}
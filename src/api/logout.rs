@@ -0,0 +1,26 @@
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use actix_session::Session;
+use crate::api::lib::{current_username, is_authorization};
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use crate::api::session_registry::SessionRegistry;
+
+#[utoipa::path(tag = "auth", responses((status = 200, description = "已登出", body = String)))]
+#[post("/api/logout")]
+async fn logout(
+    registry: web::Data<SessionRegistry>,
+    req: HttpRequest,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::ReadOnly)?;
+    let session_id: Option<String> = session.get("session_id").unwrap_or(None);
+    if let (Some(username), Some(session_id)) = (current_username(&session), session_id) {
+        if let Err(err) = registry.revoke(&username, &session_id).await {
+            eprintln!("Redis error: {:?}", err);
+        }
+    }
+    // 清除 Redis 中的 session 資料並讓 cookie 失效
+    session.purge();
+    Ok(HttpResponse::Ok().body(t!(LogoutSuccess)))
+}
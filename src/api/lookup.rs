@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, MySqlConnection, Row};
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use utoipa::ToSchema;
+
+/// 可由管理員維護的對照表
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LookupKind {
+    EnrollmentStatus, // 註冊狀況，例如在學、休學、退學
+    StudentAttribute, // 學生屬性，例如本系、外系、外校
+}
+
+impl LookupKind {
+    pub fn table(self) -> &'static str {
+        match self {
+            LookupKind::EnrollmentStatus => "EnrollmentStatus",
+            LookupKind::StudentAttribute => "StudentAttributes",
+        }
+    }
+
+    /// 存放名稱的欄位
+    pub fn name_column(self) -> &'static str {
+        match self {
+            LookupKind::EnrollmentStatus => "Status",
+            LookupKind::StudentAttribute => "Attribute",
+        }
+    }
+
+    /// StudentInfo 中參照此表的欄位
+    pub fn student_column(self) -> &'static str {
+        match self {
+            LookupKind::EnrollmentStatus => "EnrollmentStatus_SN",
+            LookupKind::StudentAttribute => "StudentAttribute_SN",
+        }
+    }
+
+    /// 名稱欄位的長度上限
+    pub fn max_name_len(self) -> usize {
+        match self {
+            LookupKind::EnrollmentStatus => 30,
+            LookupKind::StudentAttribute => 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LookupEntry {
+    pub sn: i32,
+    pub name: String,
+    pub code: Option<String>, // 前端使用的英文代碼，例如 currentlyEnrolled
+    pub notes: Option<String>,
+}
+
+/// 檢查新增或修改的內容，回傳整理後的名稱與代碼，空白代碼視為未設定
+pub fn validate_entry<'a>(
+    kind: LookupKind,
+    name: &'a str,
+    code: Option<&'a str>,
+    notes: Option<&str>,
+) -> Result<(&'a str, Option<&'a str>), ApiError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > kind.max_name_len() {
+        return Err(ApiError::invalid_input(t!(LookupNameInvalid, kind.max_name_len())).with_field("name"));
+    }
+    let code = code.map(str::trim).filter(|code| !code.is_empty());
+    if code.is_some_and(|code| code.len() > 50 || !code.chars().all(|c| c.is_ascii_alphanumeric())) {
+        return Err(ApiError::invalid_input(t!(LookupCodeInvalid)).with_field("code"));
+    }
+    if notes.is_some_and(|notes| notes.len() > 255) {
+        return Err(ApiError::invalid_input(t!(NotesMax255)).with_field("notes"));
+    }
+    Ok((name, code))
+}
+
+/// 前端傳入的對照值，可以是 SN、名稱（在學）或代碼（currentlyEnrolled）
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum LookupValue {
+    Sn(i32),
+    Text(String),
+}
+
+/// 整張對照表的內容，資料量很小，匯入時一次讀出後逐列比對
+#[derive(Debug, Clone)]
+pub struct LookupTable {
+    pub kind: LookupKind,
+    pub entries: Vec<LookupEntry>,
+}
+
+impl LookupTable {
+    pub async fn load(conn: &mut MySqlConnection, kind: LookupKind) -> Result<LookupTable, Error> {
+        let query = format!(
+            "SELECT SN, {} AS Name, Code, Notes FROM {} ORDER BY SN",
+            kind.name_column(),
+            kind.table()
+        );
+        let rows = sqlx::query(&query).fetch_all(conn).await?;
+        let mut entries = Vec::with_capacity(rows.len());
+        for row in rows {
+            entries.push(LookupEntry {
+                sn: row.try_get("SN")?,
+                name: row.try_get("Name")?,
+                code: row.try_get("Code")?,
+                notes: row.try_get("Notes")?,
+            });
+        }
+        Ok(LookupTable { kind, entries })
+    }
+
+    /// 依名稱或代碼（不分大小寫）找出對應的項目
+    pub fn find(&self, value: &str) -> Option<&LookupEntry> {
+        let value = value.trim();
+        self.entries
+            .iter()
+            .find(|entry| entry.name == value)
+            .or_else(|| {
+                self.entries.iter().find(|entry| {
+                    entry.code.as_deref().is_some_and(|code| code.eq_ignore_ascii_case(value))
+                })
+            })
+    }
+
+    pub fn resolve(&self, value: &LookupValue) -> Option<i32> {
+        match value {
+            LookupValue::Sn(sn) => self.entries.iter().find(|entry| entry.sn == *sn).map(|entry| entry.sn),
+            LookupValue::Text(text) => self.find(text).map(|entry| entry.sn),
+        }
+    }
+
+    /// 所有可填入的名稱，用於錯誤訊息
+    pub fn names(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>()
+            .join(&t!(ListSeparator))
+    }
+}
@@ -0,0 +1,345 @@
+use crate::api::i18n::{current_lang, Lang};
+use std::fmt::{Display, Write};
+
+/// 定義訊息代碼以及對應的繁體中文與英文訊息，訊息中以 `{0}`、`{1}` 代表參數
+macro_rules! catalog {
+    ($($code:ident => $zh:literal, $en:literal;)*) => {
+        /// 回應訊息的代碼，錯誤與成功的訊息都由這裡翻譯
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Msg {
+            $($code,)*
+        }
+
+        impl Msg {
+            pub fn template(self, lang: Lang) -> &'static str {
+                match self {
+                    $(Msg::$code => match lang {
+                        Lang::ZhTw => $zh,
+                        Lang::En => $en,
+                    },)*
+                }
+            }
+        }
+    };
+}
+
+impl Msg {
+    /// 以目前請求的語言產生訊息，並代入參數
+    pub fn render(self, args: &[&dyn Display]) -> String {
+        self.render_in(current_lang(), args)
+    }
+
+    pub fn render_in(self, lang: Lang, args: &[&dyn Display]) -> String {
+        let template = self.template(lang);
+        let mut message = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            message.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let placeholder = rest
+                .find('}')
+                .and_then(|end| Some((rest[1..end].parse::<usize>().ok()?, end)));
+            match placeholder {
+                Some((index, end)) if index < args.len() => {
+                    let _ = write!(message, "{}", args[index]);
+                    rest = &rest[end + 1..];
+                }
+                _ => {
+                    message.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        message.push_str(rest);
+        message
+    }
+}
+
+/// `t!(StudentNotFound)`、`t!(RowInvalidDate, line_num)`：以目前請求的語言產生訊息
+macro_rules! t {
+    ($code:ident) => {
+        $crate::api::messages::Msg::$code.render(&[])
+    };
+    ($code:ident, $($arg:expr),+ $(,)?) => {
+        $crate::api::messages::Msg::$code.render(&[$(&$arg),+])
+    };
+}
+pub(crate) use t;
+
+catalog! {
+    // 共用
+    InternalServerError => "伺服器內部錯誤", "Internal server error.";
+    DatabaseError => "資料庫錯誤", "Database error.";
+    SessionWriteFailed => "Session 寫入失敗", "Failed to write session.";
+    SessionReadFailed => "Session 讀取失敗", "Failed to read session.";
+    ListSeparator => "、", ", ";
+    SheetError => "工作表「{0}」{1}", "Sheet \"{0}\": {1}";
+    StartTransactionFailed => "無法開始交易", "Failed to start transaction.";
+    CommitFailed => "提交交易失敗", "Failed to commit transaction.";
+    AuditLogFailed => "寫入稽核紀錄失敗", "Failed to write audit log.";
+
+    // 登入與權限
+    SessionInvalid => "Session 無效或過期，或是無效的 CSRF Token", "Session is invalid or expired, or the CSRF token is invalid.";
+    PermissionDenied => "權限不足，無法執行此操作", "You do not have permission to perform this operation.";
+    PasswordTooShort => "密碼長度至少需要8個字元", "Password must be at least 8 characters long.";
+    PasswordHashFailed => "密碼加密失敗", "Failed to hash password.";
+    LoginSuccess => "登入成功", "Login successful!";
+    LogoutSuccess => "登出成功", "Logout successful!";
+    AccountDisabled => "此帳號已停用", "This account has been disabled.";
+    InvalidCredentials => "帳號或密碼錯誤", "Invalid username or password.";
+    TooManyAttempts => "登入失敗次數過多，請於 {0} 秒後再試", "Too many failed login attempts. Please try again in {0} seconds.";
+    UserLoggedIn => "使用者已登入", "User is logged in.";
+    UserNotLoggedIn => "使用者未登入", "User is not logged in.";
+    InvalidCsrfToken => "無效的 CSRF Token", "Invalid CSRF token.";
+    LoginRequired => "請先登入", "Please log in first.";
+
+    // 上傳與匯入
+    InvalidExcelFile => "無效的 Excel 檔案", "Invalid Excel file.";
+    SheetNotFound => "找不到工作表「{0}」，檔案中的工作表有：{1}", "Sheet \"{0}\" not found. Sheets in this file: {1}";
+    ReadSheetFailed => "讀取工作表「{0}」失敗: {1}", "Failed to read sheet \"{0}\": {1}";
+    FileHasNoData => "檔案中沒有任何資料", "The file contains no data.";
+    UnknownCsvEncoding => "無法辨識 CSV 檔的編碼，請使用 UTF-8 或 Big5", "Unable to detect the CSV encoding. Please use UTF-8 or Big5.";
+    InvalidCsv => "CSV 格式錯誤: {0}", "Invalid CSV format: {0}";
+    ReadUploadFailed => "讀取上傳資料失敗: {0}", "Failed to read the upload: {0}";
+    FileTooLarge => "上傳檔案過大，上限為 {0} MB", "The uploaded file is too large. The limit is {0} MB.";
+    OneFileOnly => "一次只能上傳一個檔案", "Only one file can be uploaded at a time.";
+    FileRequired => "請上傳檔案", "Please upload a file.";
+    FileTypeRequired => "請上傳 {0} 檔案", "Please upload a {0} file.";
+    FileEmpty => "上傳的檔案是空的", "The uploaded file is empty.";
+    MissingColumns => "缺少必要欄位：{0}（請確認標題列，欄位順序不限）", "Missing required columns: {0} (check the header row; column order does not matter).";
+    ImportRejected => "檔案中有 {0} 列資料有誤，未寫入任何資料", "{0} rows in the file have errors. No data was written.";
+    ErrorColumnTitle => "錯誤說明", "Errors";
+    PreviewExpired => "預覽已過期或不存在，請重新上傳檔案", "The preview has expired or does not exist. Please upload the file again.";
+    ReadPreviewFailed => "讀取預覽失敗", "Failed to read the preview.";
+    PreviewStale => "預覽後查詢的資料已變更，請重新上傳檔案", "The data has changed since the preview. Please upload the file again.";
+    PreviewCorrupted => "預覽內容損毀，請重新上傳檔案", "The preview is corrupted. Please upload the file again.";
+    SavePreviewFailed => "儲存預覽失敗", "Failed to save the preview.";
+    ExportFailed => "產生匯出檔案失敗", "Failed to generate the export file.";
+    DownloadExpired => "下載網址不存在或已過期，請重新查詢", "The download link does not exist or has expired. Please run the query again.";
+
+    // 通過規則
+    SingleExamThresholdInvalid => "單次題數門檻必須大於 0", "The single-exam threshold must be greater than 0.";
+    CumulativeThresholdInvalid => "累計題數門檻必須大於 0", "The cumulative threshold must be greater than 0.";
+    CompositeRuleEmpty => "組合規則至少需要一條子規則", "A composite rule needs at least one sub-rule.";
+    SheetHasNoData => "工作表「{0}」中沒有資料", "Sheet \"{0}\" contains no data.";
+
+    // 新增資料
+    NotesTooLong => "Notes 長度過長", "Notes are too long.";
+    ExamAlreadyExists => "已經新增過此場次的考試", "This exam session has already been added.";
+    AddExamFailed => "新增考試場次失敗", "Failed to add the exam session.";
+    RuleNameInvalid => "規則名稱不得為空且最多50字", "Rule name must not be empty and must be at most 50 characters.";
+    NotesMax255 => "備註最多255字", "Notes must be at most 255 characters.";
+    EntranceYearRangeInvalid => "入學年度區間起始不得大於結束", "The start of the entrance year range must not be after the end.";
+    RuleSerializeFailed => "規則序列化失敗: {0}", "Failed to serialize the rule: {0}";
+    QueryFailed => "查詢失敗", "Query failed.";
+    DeactivateRulesFailed => "停用舊規則失敗", "Failed to deactivate the previous rules.";
+    AddRuleFailed => "新增通過規則失敗", "Failed to add the pass criteria.";
+    RuleActivatedRecomputeFailed => "規則已啟用，但重新計算通過狀態失敗", "The rule was activated, but recomputing pass status failed.";
+    RecomputeStatusFailed => "重新計算通過狀態失敗", "Failed to recompute pass status.";
+    AddRuleActivated => "新增並啟用規則成功，已重新計算 {0} 位學生的通過狀態", "Rule added and activated. Recomputed pass status for {0} students.";
+    AddRuleSuccess => "新增通過規則成功", "Pass criteria added successfully.";
+    UsernameInvalid => "使用者名稱不可為空且最多255字", "Username must not be empty and must be at most 255 characters.";
+    CreateUserSuccess => "使用者新增成功", "User created successfully.";
+    UsernameExists => "此使用者名稱已存在", "This username already exists.";
+    CreateUserFailed => "新增使用者失敗", "Failed to create the user.";
+    ColumnSessionFormat => "第 {0} 欄 請使用 'YYYY-MM-DD,官辦/自辦' 作為欄位標題格式：{1}", "Column {0}: use 'YYYY-MM-DD,官辦/自辦' as the header format: {1}";
+    ColumnDateInvalid => "第 {0} 欄 日期格式錯誤，請使用 'YYYY-MM-DD,官辦/自辦'：{1}", "Column {0}: invalid date, use 'YYYY-MM-DD,官辦/自辦': {1}";
+    ColumnExamTypeInvalid => "第 {0} 欄 考試類型需為 '官辦' 或 '自辦'：{1}", "Column {0}: exam type must be '官辦' or '自辦': {1}";
+    ColumnSessionNotFound => "第 {0} 欄 找不到場次: {1},{2}", "Column {0}: exam session not found: {1},{2}";
+    QueryExamSessionFailed => "查詢考試場次失敗", "Failed to query exam sessions.";
+    SheetHeaderErrors => "工作表「{0}」
+{1}", "Sheet \"{0}\"
+{1}";
+    SheetNoSessionColumns => "工作表「{0}」找不到任何考試場次欄位，請使用 'YYYY-MM-DD,官辦/自辦' 作為欄位標題格式", "Sheet \"{0}\" has no exam session columns. Use 'YYYY-MM-DD,官辦/自辦' as the header format.";
+    QueryStudentFailed => "查詢學生資料錯誤", "Failed to query student data.";
+    RowStudentIdMissing => "第 {0} 列 缺少學號或學號格式錯誤", "Row {0}: student ID is missing or invalid.";
+    RowStudentNotFound => "第 {0} 列 學號:{1} 不存在", "Row {0}: student ID {1} does not exist.";
+    RowCellInvalid => "第 {0} 列第 {1} 欄格式錯誤: {2}", "Row {0}, column {1}: invalid value: {2}";
+    RowScoreNotInteger => "第 {0} 列第 {1} 欄成績應為整數", "Row {0}, column {1}: the score must be an integer.";
+    WriteDataFailed => "寫入資料失敗", "Failed to write data.";
+    ScoresSkipped => "{0} 場已有成績，略過", "{0} sessions already have scores and were skipped.";
+    NoScoresToAdd => "沒有需要新增的成績", "No scores to add.";
+    ImportExamScoreSuccess => "成功新增學生考試資料", "Exam scores imported successfully.";
+    RowStudentIdInvalid => "第 {0} 列 學號為空或格式錯誤", "Row {0}: student ID is empty or invalid.";
+    RowCountDecimal => "第 {0} 列 答對題數必須為整數，但發現小數：{1}", "Row {0}: correct answers must be an integer, got {1}.";
+    RowCountInvalid => "第 {0} 列 答對題數格式錯誤 (非數值或非整數)", "Row {0}: correct answers is not a whole number.";
+    RowCountMissing => "第 {0} 列 缺少答對題數欄位", "Row {0}: correct answers is missing.";
+    RowDateOutOfRange => "第 {0} 列 日期超出範圍", "Row {0}: date is out of range.";
+    RowDateFormat => "第 {0} 列 日期格式錯誤（非 YYYY-MM-DD）", "Row {0}: date must be YYYY-MM-DD.";
+    RowDateInvalid => "第 {0} 列 日期格式無效", "Row {0}: invalid date.";
+    RowReceivedDateMissing => "第 {0} 列 缺少領獎日期欄位", "Row {0}: received date is missing.";
+    RowAmountNegative => "第 {0} 列 金額不得為負：{1}", "Row {0}: amount must not be negative: {1}";
+    RowAmountInvalid => "第 {0} 列 領取金額格式錯誤（非數值）", "Row {0}: amount is not a number.";
+    RowAmountMissing => "第 {0} 列 缺少領取金額欄位", "Row {0}: amount is missing.";
+    RowNoMatchingExam => "第 {0} 列的學生， 學號:{1} 沒有在{2}以前的考試中答對題數是{3}", "Row {0}: student {1} has no exam before {2} with {3} correct answers.";
+    RowQueryFailed => "第 {0} 列查詢錯誤", "Row {0}: query failed.";
+    RowAuditLogFailed => "第 {0} 列寫入稽核紀錄錯誤", "Row {0}: failed to write audit log.";
+    RowScholarshipExists => "第 {0} 列的學生， 學號:{1} 已存在於資料表中", "Row {0}: student {1} already exists in the table.";
+    RowWriteFailed => "第 {0} 列寫入錯誤", "Row {0}: write failed.";
+    ImportScholarshipSuccess => "成功新增獎學金資料", "Scholarship records imported successfully.";
+    RowStudentIdEmpty => "第 {0} 列 學號欄位不能為空", "Row {0}: student ID must not be empty.";
+    RowNameEmpty => "第 {0} 列 姓名欄位不能為空", "Row {0}: name must not be empty.";
+    RowEnrollmentInvalid => "第 {0} 列 註冊狀況欄位只能填入{1}", "Row {0}: enrollment status must be one of: {1}.";
+    RowEnrollmentEmpty => "第 {0} 列 註冊狀況欄位不能為空", "Row {0}: enrollment status must not be empty.";
+    RowAttributeInvalid => "第 {0} 列 學生屬性欄位只能填入{1}", "Row {0}: student attribute must be one of: {1}.";
+    RowAttributeEmpty => "第 {0} 列 學生屬性欄位不能為空", "Row {0}: student attribute must not be empty.";
+    StudentIdAlreadyAdded => "學號:{0}，已經被新增過", "Student ID {0} has already been added.";
+    AddStudentInfoFailed => "新增學生資料失敗", "Failed to add student data.";
+    ImportStudentInfoSuccess => "成功新增學生資料", "Student data imported successfully.";
+    TemplateUnavailable => "無法讀取範本檔案", "Failed to retrieve the template file.";
+    SessionDateInvalid => "日期格式錯誤，請將第二欄(column)以後的標題格式改為YYYY-MM-DD,(官辦、自辦)", "Invalid date. Use YYYY-MM-DD,(官辦 or 自辦) as the session.";
+    SessionTypeInvalid => "場次種類格式錯誤，請將偶數欄(column)的標題格式改為YYYY-MM-DD,(官辦、自辦)", "Invalid exam type. Use YYYY-MM-DD,(官辦 or 自辦) as the session.";
+    SessionNotFoundAddFirst => "日期:{0}, 場次種類:{1}，找不到該場次的資料。請先新增或檢查該場次的資料", "No exam session found for {0}, {1}. Please add or check the session first.";
+    QuerySessionFailed => "查詢場次失敗", "Failed to query the exam session.";
+    ScoreNumInvalid => "題數格式錯誤，請填入題數(整數)或請假、缺考", "Invalid score. Enter the number of correct answers (integer), 請假 or 缺考.";
+    ScoreAlreadyAdded => "此場次的學號已經被新增過了，欲新增此成績請使用修改功能。", "This student already has a score for this session. Use the modify function to change it.";
+    StudentNotFoundAddFirst => "學生資訊無此學號，請先新增這個學號再新增此成績。", "This student ID does not exist. Please add the student before adding the score.";
+    AddScoreFailed => "新增成績失敗", "Failed to add the score.";
+    StudentIdExists => "此學號已經被新增過。", "This student ID has already been added.";
+    AddStudentFailed => "新增學生失敗", "Failed to add the student.";
+
+    // 修改資料
+    RuleNotFound => "找不到此通過規則", "Pass criteria not found.";
+    ActivateRuleFailed => "啟用規則失敗", "Failed to activate the rule.";
+    ActivateRuleSuccess => "啟用成功，已重新計算 {0} 位學生的通過狀態", "Rule activated. Recomputed pass status for {0} students.";
+    CannotDisableSelf => "無法停用自己的帳號", "You cannot disable your own account.";
+    UserDisabled => "帳號已停用", "Account disabled.";
+    UserEnabled => "帳號已啟用", "Account enabled.";
+    UserNotFoundOrUnchanged => "找不到此使用者，或狀態未變更", "User not found, or the status is unchanged.";
+    DisableUserFailed => "修改帳號狀態失敗", "Failed to change the account status.";
+    ModifyExamSnMissing => "找不到要修改的考試 SN，請先點擊查詢後再修改", "No exam session selected. Please query before modifying.";
+    NothingToUpdate => "無更新內容", "Nothing to update.";
+    UpdateSuccess => "更新成功", "Updated successfully.";
+    UpdateFailed => "更新失敗", "Update failed.";
+    QueryBeforeUpload => "請先查詢後再上傳檔案進行修改。", "Please query before uploading a file to modify.";
+    InvalidExamDate => "無效的考試日期", "Invalid exam date.";
+    ExamDateMismatch => "請先查詢後再上傳檔案進行修改。查詢的日期與修改的考試日期必須一樣", "Please query before uploading a file to modify. The queried date must match the exam date in the file.";
+    SheetEmpty => "工作表中沒有資料", "The sheet contains no data.";
+    ReadOriginalFailed => "讀取原始資料失敗", "Failed to read the original data.";
+    RowNoAttendance => "第 {0} 列 學號:{1} 沒有此場次的考試紀錄", "Row {0}: student {1} has no record for this exam session.";
+    DataUnchanged => "資料沒有變更", "No changes.";
+    RowUpdateFailed => "第 {0} 列更新失敗", "Row {0}: update failed.";
+    UpdatedCount => "成功更新 {0} 筆資料", "Updated {0} records.";
+    ModifyStudentIdMissing => "找不到要修改的學生學號，請先點擊查詢後再修改", "No student selected. Please query before modifying.";
+    EnrollmentStatusInvalid => "註冊狀態請填入{0}", "Enrollment status must be one of: {0}.";
+    StudentAttributeInvalid => "學生屬性請填入{0}", "Student attribute must be one of: {0}.";
+    EntranceYearInvalid => "入學年度格式錯誤", "Invalid entrance year.";
+    AcademicYearInvalid => "學年度超出範圍: {0}", "Academic year is out of range: {0}";
+    UpdatePassStatusFailed => "更新通過狀態失敗", "Failed to update the pass status.";
+    CannotChangeOwnRole => "無法修改自己的角色", "You cannot change your own role.";
+    RoleChanged => "角色修改成功", "Role changed successfully.";
+    UserNotFoundOrRoleUnchanged => "找不到此使用者，或角色未變更", "User not found, or the role is unchanged.";
+    ChangeRoleFailed => "修改角色失敗", "Failed to change the role.";
+    PasswordReset => "密碼重設成功", "Password reset successfully.";
+    UserNotFound => "找不到此使用者", "User not found.";
+    ResetPasswordFailed => "重設密碼失敗", "Failed to reset the password.";
+    UnlockTargetRequired => "請填入要解除鎖定的帳號或 IP", "Please enter the username or IP to unlock.";
+    UnlockFailed => "解除鎖定失敗", "Failed to unlock.";
+    NotLockedCleared => "目前沒有被鎖定，已清除失敗次數", "Not currently locked. Failed attempts have been cleared.";
+    UnlockSuccess => "解除鎖定成功", "Unlocked successfully.";
+    SessionFormatInvalid => "場次格式無效，應為 '日期,場次類型'（例如 '2025-01-06,自辦'）", "Invalid session. Expected 'date,type' (for example '2025-01-06,自辦').";
+    DateFormatInvalid => "無效的日期格式，應為 'YYYY-MM-DD'", "Invalid date. Expected 'YYYY-MM-DD'.";
+    QueryBeforeModify => "請先查詢後再進行修改。", "Please query before modifying.";
+    InvalidExamSession => "無效的場次", "Invalid exam session.";
+    SessionDateMismatch => "場次日期不匹配", "The exam session date does not match.";
+    SessionSnMismatch => "場次資訊與場次序號不匹配", "The exam session does not match the queried session.";
+    NoDataProvided => "未提供任何資料", "No data provided.";
+    StudentReceivedDateInvalid => "學號 {0}：無效的領取日期格式: {1}", "Student {0}: invalid received date: {1}";
+    StudentReceivedDateRequired => "學號 {0}：領取日期不得為空（當是否領獎為「是」時）", "Student {0}: received date is required when the scholarship is claimed.";
+    StudentAmountRequired => "學號 {0}：獎學金金額不得為 0（當是否領獎為「是」時）", "Student {0}: amount must not be 0 when the scholarship is claimed.";
+    StudentCountTooLow => "學號 {0}：答對題數必須大於 3（當是否領獎為「是」時）", "Student {0}: correct answers must be at least 3 when the scholarship is claimed.";
+    StudentQueryExamFailed => "學號 {0}：查詢考試記錄失敗", "Student {0}: failed to query exam records.";
+    StudentNoMatchingExam => "學號 {0}：在 {1} 之前的考試記錄中，找不到答對題數等於 {2} 的記錄", "Student {0}: no exam before {1} with {2} correct answers.";
+    StudentReadOriginalFailed => "學號 {0}：讀取原始資料失敗", "Student {0}: failed to read the original data.";
+    StudentUpdateFailed => "學號 {0}：更新失敗", "Student {0}: update failed.";
+    StudentDeleteFailed => "學號 {0}：刪除失敗", "Student {0}: delete failed.";
+    StudentInsertFailed => "學號 {0}：新增失敗", "Student {0}: insert failed.";
+    StudentAuditLogFailed => "學號 {0}：寫入稽核紀錄失敗", "Student {0}: failed to write audit log.";
+    ScholarshipProcessed => "成功處理 {0} 筆獎學金紀錄", "Processed {0} scholarship records.";
+
+    // 刪除資料
+    ExamRecordNotFound => "找不到{0},{1}的考試記錄", "No exam record found for {0},{1}.";
+    DeleteAttendanceFailed => "刪除考試參與記錄失敗", "Failed to delete exam attendance records.";
+    DeleteExamFailed => "刪除考試記錄失敗", "Failed to delete the exam record.";
+    DeleteExamSuccess => "考試記錄刪除成功", "Exam record deleted successfully.";
+    SelectSessionBeforeDelete => "請先選擇要查詢的場次再刪除成績", "Please query an exam session before deleting scores.";
+    SessionParseFailed => "伺服器錯誤，無法解析 session。", "Server error: unable to parse the session.";
+    NoStudentSelected => "未選擇任何學生", "No students selected.";
+    DeleteRolledBack => "刪除失敗，所有變更已回滾", "Delete failed. All changes have been rolled back.";
+    DeletedCount => "成功刪除 {0} 筆記錄", "Deleted {0} records.";
+    DeleteRetry => "刪除失敗請重新提交刪除", "Delete failed. Please submit again.";
+    DeleteRuleSuccess => "通過規則刪除成功", "Pass criteria deleted successfully.";
+    CannotDeleteActiveRule => "無法刪除啟用中的通過規則，請先啟用其他規則", "The active pass criteria cannot be deleted. Activate another rule first.";
+    DeleteRuleFailed => "刪除通過規則失敗", "Failed to delete the pass criteria.";
+    InvalidDateValue => "無效的日期格式: {0}", "Invalid date: {0}";
+    DeleteFailed => "刪除失敗", "Delete failed.";
+    ScholarshipDeletedCount => "成功刪除 {0} 筆獎學金紀錄", "Deleted {0} scholarship records.";
+    NoValidStudentIdProvided => "未提供任何有效學號", "No valid student IDs provided.";
+    StudentIdRequired => "學號不得為空", "Student ID must not be empty.";
+    StudentIdNotExist => "學號 {0} 不存在", "Student ID {0} does not exist.";
+    StudentQueryOriginalFailed => "學號 {0}：查詢原始資料失敗", "Student {0}: failed to query the original data.";
+    StudentDeleteExamFailed => "學號 {0}：刪除考試記錄失敗", "Student {0}: failed to delete exam records.";
+    StudentDeleteInfoFailed => "學號 {0}：刪除學生資訊失敗", "Student {0}: failed to delete student data.";
+    StudentDeleted => "成功刪除學號 {0}", "Deleted student {0}.";
+    NoValidStudentIdFound => "沒有找到任何有效的學號", "No valid student IDs found.";
+    TransactionFailed => "SQL交易失敗", "SQL transaction failed.";
+    StudentInfoDeletedCount => "成功刪除 {0} 筆學生資訊", "Deleted {0} student records.";
+    DeleteStudentInfoFailed => "刪除學生資訊失敗", "Failed to delete student data.";
+    CannotDeleteSelf => "無法刪除自己的帳號", "You cannot delete your own account.";
+    DeleteUserSuccess => "使用者刪除成功", "User deleted successfully.";
+    DeleteUserFailed => "刪除使用者失敗", "Failed to delete the user.";
+    LoginSessionNotFound => "找不到此登入狀態", "Login session not found.";
+    SessionsRevoked => "已撤銷 {0} 個登入狀態", "Revoked {0} login sessions.";
+    RevokeSessionFailed => "撤銷登入狀態失敗", "Failed to revoke the login session.";
+    SessionCheckUnavailable => "暫時無法確認登入狀態，請稍後再試", "Unable to verify the login session right now. Please try again later.";
+    RevokeAfterChangeFailed => "已完成變更，但無法撤銷此使用者已登入的 session，該使用者仍維持登入，請稍後以 /api/revoke_session 撤銷", "The change was saved, but the user's login sessions could not be revoked and are still active. Revoke them later with /api/revoke_session.";
+
+    // 查詢資料
+    StudentNotFoundCreateFirst => "查無此學生:{0}，請先建立此學生的資料再進行查詢", "Student {0} not found. Please add the student before querying.";
+    QueryAuditLogFailed => "查詢稽核紀錄失敗", "Failed to query the audit log.";
+    ExamSessionNotFound => "查無此考試場次", "Exam session not found.";
+    QueryLocksFailed => "查詢鎖定狀態失敗", "Failed to query login locks.";
+    QueryRulesFailed => "查詢通過規則失敗", "Failed to query the pass criteria.";
+    StoredRuleInvalid => "資料庫中的通過規則格式錯誤", "The pass criteria stored in the database are invalid.";
+    QueryCurrentYearFailed => "查詢本學年度資料失敗", "Failed to query the current academic year.";
+    QueryYearsFailed => "查詢歷年資料失敗", "Failed to query data by year.";
+    ExportExcelFailed => "匯出 Excel 失敗", "Failed to export Excel.";
+    ExamSessionNotMatched => "未找到對應的考試場次", "No matching exam session found.";
+    QueryScoresFailed => "查詢考試成績資料失敗", "Failed to query exam scores.";
+    SessionSaveFailed => "無法存入 session", "Failed to save to the session.";
+    SessionSaveRetry => "無法存入 session，請再試一次", "Failed to save to the session. Please try again.";
+    QuerySessionsFailed => "查詢登入狀態失敗", "Failed to query login sessions.";
+    QueryUsersFailed => "查詢使用者失敗", "Failed to query users.";
+    QueryClaimedFailed => "查詢已領資料時發生錯誤", "Failed to query claimed scholarships.";
+    QueryUnclaimedFailed => "查詢未領資料時發生錯誤", "Failed to query unclaimed scholarships.";
+    StudentIdNotFound => "此學號不存在", "Student ID not found.";
+    QueryStudentIdFailed => "查詢學號時發生錯誤", "Failed to query the student ID.";
+    QueryExamRecordsFailed => "查詢考試紀錄時發生錯誤", "Failed to query exam records.";
+
+    // 語言設定
+    ModifyLanguageFailed => "修改語言設定失敗", "Failed to change the language setting.";
+    LanguageChanged => "語言設定已更新", "Language setting updated.";
+
+    // 註冊狀況與學生屬性
+    LoadLookupFailed => "讀取註冊狀況與學生屬性失敗", "Failed to load enrollment statuses and student attributes.";
+    QueryLookupFailed => "查詢對照表失敗", "Failed to query the lookup table.";
+    LookupNameInvalid => "名稱不得為空，且長度不得超過 {0} 個字", "The name must not be empty or longer than {0} characters.";
+    LookupCodeInvalid => "代碼只能包含英數字，且長度不得超過 50 個字", "The code may only contain letters and digits, up to 50 characters.";
+    LookupExists => "名稱或代碼已經存在", "The name or code already exists.";
+    LookupNotFound => "找不到此項目", "Lookup entry not found.";
+    LookupInUse => "仍有 {0} 位學生使用此項目，無法刪除", "{0} students still use this entry, so it cannot be deleted.";
+    AddLookupFailed => "新增項目失敗", "Failed to add the entry.";
+    AddLookupSuccess => "新增成功", "Added successfully.";
+    ModifyLookupFailed => "修改項目失敗", "Failed to modify the entry.";
+    DeleteLookupFailed => "刪除項目失敗", "Failed to delete the entry.";
+    DeleteLookupSuccess => "刪除成功", "Deleted successfully.";
+
+    // 版本檢查
+    IfMatchRequired => "請在 If-Match 標頭帶入讀取資料時取得的 ETag", "Please send the ETag returned when reading the data in the If-Match header.";
+    VersionMismatch => "資料已被其他人修改，請重新讀取後再修改", "The data has been changed by someone else. Please reload it and try again.";
+
+    // 領獎紀錄
+    ScholarshipNotFound => "此學生沒有領獎紀錄", "This student has no scholarship record.";
+    ScholarshipExists => "此學生已有領獎紀錄，請改用 PATCH 修改", "This student already has a scholarship record. Use PATCH to modify it.";
+    QueryScholarshipFailed => "查詢領獎紀錄失敗", "Failed to query the scholarship record.";
+}
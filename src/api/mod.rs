@@ -1,8 +1,26 @@
 pub mod login;
-pub mod create_user;
+pub mod logout;
+pub mod login_throttle;
 pub mod check_session;
 pub mod lib;
 pub mod query;
 pub mod create;
 pub mod modify;
-pub mod delete;
\ No newline at end of file
+pub mod delete;
+pub mod v2;
+pub mod pass_rule;
+pub mod pass_status;
+pub mod lookup;
+pub mod etag;
+pub mod openapi;
+pub mod role;
+pub mod session_registry;
+pub mod audit;
+pub mod upload;
+pub mod import;
+pub mod columns;
+pub mod export;
+pub mod download;
+pub mod error;
+pub mod i18n;
+pub mod messages;
\ No newline at end of file
@@ -0,0 +1,77 @@
+use crate::api::lib::is_authorization;
+use crate::api::pass_status::{recompute_pass_status, RecomputeFilter};
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use crate::api::audit::{active_pass_criteria_of_cohort, snapshot_all, write_audit_logs, AuditActor, AuditTarget};
+use actix_session::Session;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use sqlx::MySqlPool;
+use utoipa::ToSchema;
+
+#[derive(Deserialize, Debug, ToSchema)]
+struct ActivatePassCriteria {
+    sn: i32,
+}
+
+#[utoipa::path(tag = "pass_criteria", responses((status = 200, description = "已啟用，並重新計算所有學生的通過狀態", body = String)))]
+#[post("/api/activate_pass_criteria")]
+async fn activate_pass_criteria(
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+    data: web::Json<ActivatePassCriteria>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Admin)?;
+    let mut tx = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
+
+    let cohort = sqlx::query_as::<_, (Option<i32>, Option<i32>)>(
+        "SELECT EntranceYearFrom, EntranceYearTo FROM PassCriteria WHERE SN = ?",
+    )
+    .bind(data.sn)
+    .fetch_optional(&mut *tx)
+    .await;
+    let (year_from, year_to) = match cohort {
+        Ok(Some(cohort)) => cohort,
+        Ok(None) => return Err(ApiError::not_found(t!(RuleNotFound))),
+        Err(err) => return Err(ApiError::database(t!(QueryFailed), err)),
+    };
+
+    let snapshots = match active_pass_criteria_of_cohort(&mut tx, year_from, year_to).await {
+        Ok(mut targets) => {
+            targets.push(AuditTarget::PassCriteria(data.sn));
+            snapshot_all(&mut tx, targets).await
+        }
+        Err(err) => Err(err),
+    };
+    let snapshots = snapshots.map_err(|err| ApiError::database(t!(QueryFailed), err))?;
+
+    // 停用同一入學年度區間的其他規則後再啟用指定規則
+    let result = sqlx::query(
+        r#"
+        UPDATE PassCriteria SET IsActive = (SN = ?)
+        WHERE EntranceYearFrom <=> ? AND EntranceYearTo <=> ?
+        "#,
+    )
+    .bind(data.sn)
+    .bind(year_from)
+    .bind(year_to)
+    .execute(&mut *tx)
+    .await;
+    if let Err(err) = result {
+        let _ = tx.rollback().await;
+        return Err(ApiError::database(t!(ActivateRuleFailed), err));
+    }
+    if let Err(err) = write_audit_logs(&mut tx, &session, "/api/activate_pass_criteria", snapshots).await {
+        let _ = tx.rollback().await;
+        return Err(ApiError::database(t!(AuditLogFailed), err));
+    }
+    tx.commit().await.map_err(|err| ApiError::database(t!(CommitFailed), err))?;
+
+    let count = recompute_pass_status(db_pool.get_ref(), &AuditActor::from_session(&session, "/api/activate_pass_criteria"), &RecomputeFilter::default(), false)
+        .await
+        .map_err(|err| ApiError::database(t!(RuleActivatedRecomputeFailed), err))?
+        .checked;
+    Ok(HttpResponse::Ok().body(t!(ActivateRuleSuccess, count)))
+}
@@ -0,0 +1,59 @@
+use actix_session::Session;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use sqlx::MySqlPool;
+use crate::api::lib::{current_username, is_authorization};
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::audit::{write_audit_log, AuditTarget};
+use crate::api::session_registry::SessionRegistry;
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema)]
+struct DisableUser {
+    username: String,
+    is_disabled: bool, // true 停用，false 重新啟用
+}
+
+#[utoipa::path(tag = "users", responses((status = 200, description = "已停用或重新啟用", body = String)))]
+#[post("/api/disable_user")]
+async fn disable_user(
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+    registry: web::Data<SessionRegistry>,
+    data: web::Json<DisableUser>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Admin)?;
+    if current_username(&session).as_deref() == Some(data.username.as_str()) {
+        return Err(ApiError::new(ErrorCode::Conflict, t!(CannotDisableSelf)));
+    }
+    let target = AuditTarget::User(&data.username);
+    let result = async {
+        let mut tx = db_pool.begin().await?;
+        let before = target.snapshot(&mut tx).await?;
+        let res = sqlx::query("UPDATE users SET is_disabled = ? WHERE username = ?")
+            .bind(data.is_disabled)
+            .bind(&data.username)
+            .execute(&mut *tx)
+            .await?;
+        write_audit_log(&mut tx, &session, "/api/disable_user", &target, before).await?;
+        tx.commit().await?;
+        Ok::<_, sqlx::Error>(res)
+    }
+    .await;
+    match result {
+        Ok(res) if res.rows_affected() > 0 => {
+            if data.is_disabled {
+                // 停用後讓此使用者已登入的 session 立即失效
+                registry.revoke_after_change(&data.username).await?;
+                Ok(HttpResponse::Ok().body(t!(UserDisabled)))
+            } else {
+                Ok(HttpResponse::Ok().body(t!(UserEnabled)))
+            }
+        }
+        Ok(_) => Err(ApiError::not_found(t!(UserNotFoundOrUnchanged))),
+        Err(err) => Err(ApiError::database(t!(DisableUserFailed), err)),
+    }
+}
@@ -2,4 +2,12 @@ pub mod modify_student_info;
 pub mod modify_exam_info;
 pub mod modify_exam_score;
 pub mod update_exam_score;
-pub mod update_scholarship;
\ No newline at end of file
+pub mod update_scholarship;
+pub mod activate_pass_criteria;
+pub mod recompute_pass_status;
+pub mod modify_user_role;
+pub mod disable_user;
+pub mod reset_user_password;
+pub mod unlock_login;
+pub mod modify_language;
+pub mod modify_lookup;
\ No newline at end of file
@@ -1,50 +1,172 @@
 use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::audit::{write_audit_log, AuditTarget};
+use crate::api::etag::{check_version, etag_of, required_if_match};
 use actix_session::Session;
 use actix_web::{post, web, HttpRequest, HttpResponse};
+use chrono::NaiveDate;
 use serde::Deserialize;
-use sqlx::{MySqlPool, mysql::MySqlArguments};
-use sqlx::Arguments;
+use sqlx::{MySqlConnection, MySqlPool, mysql::MySqlArguments};
+use sqlx::{Arguments, Row};
+use utoipa::ToSchema;
 
-#[derive(Deserialize,Debug)]
-struct ModifyData {
+#[derive(Deserialize,Debug,ToSchema)]
+#[schema(as = ModifyExamInfo)]
+pub(crate) struct ModifyData {
     exam_date: Option<String>,
     exam_type: Option<String>,
     notes: Option<String>,
 }
 
 
+#[utoipa::path(tag = "exam_sessions", responses((status = 200, description = "修改成功或沒有需要更新的欄位", body = String)))]
 #[post("/api/modify_exam_info")]
 async fn modify_exam_info(
     req: HttpRequest,
     mut session: Session,
     db_pool: web::Data<MySqlPool>,
     data: web::Json<ModifyData>,
-) -> HttpResponse {
-    if !is_authorization(req, session.clone()) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
-    let exam_sn: Option<i32> = session.get("modify_exam_sn").unwrap();
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Staff)?;
+    let exam_sn: Option<i32> = session.get("modify_exam_sn")?;
     let exam_sn = match exam_sn {
         Some(sn) => sn,
-        None => return HttpResponse::BadRequest().body("找不到要修改的考試 SN，請先點擊查詢後再修改"),
+        None => return Err(ApiError::invalid_input(t!(ModifyExamSnMissing))),
     };
     let original_data: ModifyData = ModifyData {
-        exam_date: session.get("modify_exam_date").unwrap(),
-        exam_type: session.get("modify_exam_type").unwrap(),
+        exam_date: session.get("modify_exam_date")?,
+        exam_type: session.get("modify_exam_type")?,
         notes: session.get("modify_notes").unwrap_or(None),
     };
-    let new_data = data.into_inner();
+    let (set_clauses, query_args) = diff_changes(original_data, data.into_inner());
+
+    // 如果沒有任何欄位有變化，就直接回傳
+    if set_clauses.is_empty() {
+        clean_session(&mut session);
+        return Ok(HttpResponse::Ok().body(t!(NothingToUpdate)));
+    }
+
+    // 執行更新，並在同一個交易內寫入稽核紀錄
+    let target = AuditTarget::ExamSession(exam_sn);
+    let result = async {
+        let mut tx = db_pool.begin().await?;
+        let before = target.snapshot(&mut tx).await?;
+        apply_changes(&mut tx, exam_sn, set_clauses, query_args).await?;
+        write_audit_log(&mut tx, &session, "/api/modify_exam_info", &target, before).await?;
+        tx.commit().await
+    }
+    .await;
+
+    match result {
+        Ok(()) => {
+            clean_session(&mut session);
+            Ok(HttpResponse::Ok().body(t!(UpdateSuccess)))
+        }
+        Err(e) => Err(ApiError::database(t!(UpdateFailed), e)),
+    }
+}
+
+/// 以場次 SN 指定要修改的場次，不依賴查詢時存入 session 的資料
+/// If-Match 需帶入 GET /api/exam_info/{sn} 回應的 ETag，資料已被修改時回傳 412
+#[utoipa::path(
+    tag = "exam_sessions",
+    params(("If-Match" = String, Header, description = "讀取時回應的 ETag")),
+    responses((status = 200, description = "修改成功，ETag 標頭為新的版本", body = String)),
+)]
+#[post("/api/modify_exam_info/{sn}")]
+async fn modify_exam_info_by_sn(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+    data: web::Json<ModifyData>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req.clone(), session.clone(), Role::Staff)?;
+    let if_match = required_if_match(&req)?;
+    modify_exam(&db_pool, &session, "/api/modify_exam_info", path.into_inner(), &if_match, data.into_inner()).await
+}
+
+/// 比對版本後修改場次資料，/api/v2/exam-sessions/{sn} 共用
+pub(crate) async fn modify_exam(
+    db_pool: &MySqlPool,
+    session: &Session,
+    endpoint: &str,
+    exam_sn: i32,
+    if_match: &str,
+    new_data: ModifyData,
+) -> Result<HttpResponse, ApiError> {
+    let target = AuditTarget::ExamSession(exam_sn);
+
+    let mut tx = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
+    let before = target
+        .snapshot_for_update(&mut tx)
+        .await
+        .map_err(|err| ApiError::database(t!(ReadOriginalFailed), err))?;
+    let Some(current) = before.as_deref() else {
+        return Err(ApiError::new(ErrorCode::ExamSessionNotFound, t!(ExamSessionNotFound)));
+    };
+    check_version(if_match, &etag_of(current))?;
+    let original_data = load_original(&mut tx, exam_sn)
+        .await
+        .map_err(|err| ApiError::database(t!(ReadOriginalFailed), err))?;
+    let (set_clauses, query_args) = diff_changes(original_data, new_data);
+    if set_clauses.is_empty() {
+        return Ok(HttpResponse::Ok()
+            .insert_header(("ETag", etag_of(current)))
+            .body(t!(NothingToUpdate)));
+    }
+    match apply_changes(&mut tx, exam_sn, set_clauses, query_args).await {
+        Ok(()) => (),
+        Err(sqlx::Error::Database(err)) if err.code() == Some(std::borrow::Cow::Borrowed("23000")) => {
+            return Err(ApiError::new(ErrorCode::AlreadyExists, t!(ExamAlreadyExists)));
+        }
+        Err(err) => return Err(ApiError::database(t!(UpdateFailed), err)),
+    }
+    let result = async {
+        write_audit_log(&mut tx, session, endpoint, &target, before.clone()).await?;
+        let after = target.snapshot(&mut tx).await?;
+        tx.commit().await?;
+        Ok::<_, sqlx::Error>(after)
+    }
+    .await;
+    let after = result.map_err(|err| ApiError::database(t!(UpdateFailed), err))?;
+
+    let mut response = HttpResponse::Ok();
+    if let Some(after) = after {
+        response.insert_header(("ETag", etag_of(&after)));
+    }
+    Ok(response.body(t!(UpdateSuccess)))
+}
+
+/// 從資料庫讀出目前的資料，格式與查詢時存入 session 的相同
+async fn load_original(conn: &mut MySqlConnection, exam_sn: i32) -> Result<ModifyData, sqlx::Error> {
+    let row = sqlx::query("SELECT ExamDate, ExamType, Notes FROM ExamSessions WHERE SN = ?")
+        .bind(exam_sn)
+        .fetch_one(conn)
+        .await?;
+    let exam_date: NaiveDate = row.try_get("ExamDate")?;
+    Ok(ModifyData {
+        exam_date: Some(exam_date.to_string()),
+        exam_type: row.try_get("ExamType")?,
+        notes: row.try_get("Notes")?,
+    })
+}
+
+/// 比對原始資料與新資料，組合出需要更新的欄位與參數，未提供的欄位不更新
+fn diff_changes(original_data: ModifyData, new_data: ModifyData) -> (Vec<&'static str>, MySqlArguments) {
     let mut set_clauses = Vec::new();
     let mut query_args = MySqlArguments::default();
 
     // 處理 exam_date
-    if new_data.exam_date != original_data.exam_date {
+    if new_data.exam_date.is_some() && new_data.exam_date != original_data.exam_date {
         set_clauses.push("ExamDate = ?");
         let _ = query_args.add(new_data.exam_date);
     }
 
-    // 處理 exam_type：前端是字串，要轉換成數字
-    if new_data.exam_type != original_data.exam_type {
+    // 處理 exam_type
+    if new_data.exam_type.is_some() && new_data.exam_type != original_data.exam_type {
         set_clauses.push("ExamType = ?");
         let _ = query_args.add(new_data.exam_type);
     }
@@ -62,33 +184,22 @@ async fn modify_exam_info(
             let _ = query_args.add(new_notes_val);
         }
     }
+    (set_clauses, query_args)
+}
 
-    // 如果沒有任何欄位有變化，就直接回傳
-    if set_clauses.is_empty() {
-        clean_session(&mut session);
-        return HttpResponse::Ok().body("無更新內容");
-    }
-
+async fn apply_changes(
+    conn: &mut MySqlConnection,
+    exam_sn: i32,
+    set_clauses: Vec<&'static str>,
+    mut query_args: MySqlArguments,
+) -> Result<(), sqlx::Error> {
     // 組合 SQL 語句
     let set_clause = set_clauses.join(", ");
     let sql = format!("UPDATE ExamSessions SET {} WHERE SN = ?", set_clause);
     // 最後將 exam_sn 當作條件參數加入
     let _ = query_args.add(exam_sn);
-
-    // 執行更新
-    let result = sqlx::query_with(&sql, query_args)
-        .execute(db_pool.get_ref())
-        .await;
-
-    match result {
-        Ok(_res) => {
-            clean_session(&mut session);
-            HttpResponse::Ok().body("更新成功")
-        }
-        Err(_e) => {
-            HttpResponse::InternalServerError().body("更新失敗")
-        }
-    }
+    sqlx::query_with(&sql, query_args).execute(conn).await?;
+    Ok(())
 }
 
 fn clean_session(session: &mut Session) {
@@ -1,78 +1,111 @@
-use crate::api::lib::{is_authorization,update_student_status};
+use crate::api::lib::{is_authorization,update_students_status};
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::import::{finish_import, read_import, ImportKind, ImportPreviewStore, ImportQuery, ImportReport, RowAction};
+use crate::api::audit::{write_audit_log, AuditActor, AuditTarget};
+use crate::api::etag::{attendance_etag, check_version, required_if_match};
+use crate::api::columns::{ColumnMap, ColumnSpec, STUDENT_ID};
 use actix_session::Session;
 use actix_web::{post, web, HttpRequest, HttpResponse};
+use actix_web::http::header::{HeaderValue, ETAG};
 use sqlx::MySqlPool;
 use actix_multipart::Multipart;
-use futures_util::StreamExt as _;
-use std::fs::File;
-use std::io::Write;
-use calamine::{DataType, Reader};
+use calamine::DataType;
 use chrono::NaiveDate;
+use crate::api::openapi::UploadForm;
 
+const MODIFY_SCORE_COLUMNS: [ColumnSpec; 4] = [
+    STUDENT_ID,
+    ColumnSpec::required("請假/缺考", &["缺考/請假", "出缺席", "狀態"]),
+    ColumnSpec::required("答對題數", &["題數", "correct answers"]),
+    ColumnSpec::optional("備註", &["notes", "note"]),
+];
+
+#[utoipa::path(
+    tag = "scores",
+    request_body(content = UploadForm, content_type = "multipart/form-data", description = "以 preview_id 確認預覽時不需要上傳檔案"),
+    responses(
+        (status = 200, description = "預覽或確認預覽時回傳每一列的處理結果，直接上傳成功時回傳訊息", content(
+            (ImportReport = "application/json"),
+            (String = "text/plain"),
+        )),
+        (status = 400, description = "直接上傳且有錯誤的列時，回傳標示錯誤的 Excel 檔，X-Import-Rejected 標頭為錯誤列數",
+            content_type = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet", body = Vec<u8>),
+    ),
+)]
 #[post("/api/modify_exam_score")]
 pub async fn modify_exam_score(
     mut payload: Multipart,
     req: HttpRequest,
     session: Session,
     db_pool: web::Data<MySqlPool>,
-) -> HttpResponse {
-    if !is_authorization(req, session.clone()) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
+    previews: web::Data<ImportPreviewStore>,
+    query: web::Query<ImportQuery>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Staff)?;
     let exam_session_sn:i32 = match session.get("modify_exam_session_sn") {
         Ok(Some(sn)) => sn,
-        Ok(None) => return HttpResponse::BadRequest().body("請先查詢後再上傳檔案進行修改。"),
-        Err(_) => return HttpResponse::InternalServerError().body("server error"),
+        Ok(None) => return Err(ApiError::invalid_input(t!(QueryBeforeUpload))),
+        Err(err) => return Err(err.into()),
     };
-    let temp_filepath = "./uploads/modify_exam_score.xlsx";
-    //儲存上傳的檔案
-    while let Some(Ok(field)) = payload.next().await {
-        let content_disposition = field.content_disposition();
-        if let Some(filename) = content_disposition.and_then(|cd| cd.get_filename()) {
-            let file_ext = std::path::Path::new(filename)
-                .extension()
-                .and_then(|s| s.to_str());
-            if file_ext != Some("xlsx") {
-                return HttpResponse::BadRequest().body("請上傳xlsx檔案");
-            }
-            let mut f = File::create(temp_filepath).expect("Failed to create file");
-            let mut field_stream = field;
-            while let Some(chunk) = field_stream.next().await {
-                let data = chunk.expect("Error reading chunk");
-                f.write_all(&data).expect("Error writing chunk");
-            }
-        }
-    }
-
-    if !std::path::Path::new(temp_filepath).exists() {
-        println!("File does not exist: {}", temp_filepath);
-        return HttpResponse::InternalServerError().body("Failed to process file");
-    }
+    modify_scores(&mut payload, &session, &db_pool, &previews, &query, exam_session_sn, None).await
+}
 
-    let mut workbook = match calamine::open_workbook_auto(temp_filepath) {
-        Ok(wb) => wb,
-        Err(err) => {
-            println!("Failed to open Excel file: {}", err);
-            return HttpResponse::InternalServerError().body("無效的 Excel file");
-        }
-    };
+/// 以場次 SN 指定要修改的場次，不依賴查詢時存入 session 的資料
+/// If-Match 需帶入 GET /api/exam_scores/{sn} 回應的 ETag，場次內任一筆成績已被修改時回傳 412
+#[utoipa::path(
+    tag = "scores",
+    params(("If-Match" = String, Header, description = "讀取時回應的 ETag")),
+    request_body(content = UploadForm, content_type = "multipart/form-data", description = "以 preview_id 確認預覽時不需要上傳檔案"),
+    responses(
+        (status = 200, description = "預覽或確認預覽時回傳每一列的處理結果，直接上傳成功時回傳訊息", content(
+            (ImportReport = "application/json"),
+            (String = "text/plain"),
+        )),
+        (status = 400, description = "直接上傳且有錯誤的列時，回傳標示錯誤的 Excel 檔，X-Import-Rejected 標頭為錯誤列數",
+            content_type = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet", body = Vec<u8>),
+    ),
+)]
+#[post("/api/modify_exam_score/{sn}")]
+pub async fn modify_exam_score_by_sn(
+    path: web::Path<i32>,
+    mut payload: Multipart,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+    previews: web::Data<ImportPreviewStore>,
+    query: web::Query<ImportQuery>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req.clone(), session.clone(), Role::Staff)?;
+    let if_match = required_if_match(&req)?;
+    let exam_session_sn = path.into_inner();
+    modify_scores(&mut payload, &session, &db_pool, &previews, &query, exam_session_sn, Some(if_match)).await
+}
 
-    let range = match workbook.worksheet_range("Sheet1") {
-        Ok(range) => range,
-        Err(err) => {
-            println!("Error reading sheet: {}", err);
-            return HttpResponse::BadRequest().body("請將需要查詢的資料放入Sheet1");
-        }
-    };
+/// 讀取上傳的成績檔並更新場次內的成績，if_match 有值時先比對場次內成績的版本
+async fn modify_scores(
+    payload: &mut Multipart,
+    session: &Session,
+    db_pool: &web::Data<MySqlPool>,
+    previews: &web::Data<ImportPreviewStore>,
+    query: &web::Query<ImportQuery>,
+    exam_session_sn: i32,
+    if_match: Option<String>,
+) -> Result<HttpResponse, ApiError> {
+    let import = read_import(payload, query, previews, session, ImportKind::ModifyExamScore, Some(exam_session_sn)).await?;
+    // 一個檔案只對應一個場次，只讀取一個工作表
+    let sheet = import.upload.read_sheet(import.sheets.sheet.clone())?;
+    let range = &sheet.range;
     let exam_date: NaiveDate = match range.get((0, 0)).and_then(|cell| cell.get_string()) {
         Some(s) if s.starts_with("考試日期: ") => {
             let date_str = s.trim_start_matches("考試日期: ").trim();
             match NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
                 Ok(date) => date,
-                Err(_) => return HttpResponse::BadRequest().body("無效的考試日期"),
+                Err(_) => return Err(ApiError::invalid_input(t!(InvalidExamDate))),
             }
         }
-        _ => return HttpResponse::BadRequest().body("無效的考試日期"),
+        _ => return Err(ApiError::invalid_input(t!(InvalidExamDate))),
     };
     // 查詢 ExamSessions，確保 exam_date 與 session_sn 相符
     let session_match = sqlx::query!(
@@ -85,17 +118,31 @@ pub async fn modify_exam_score(
 
     match session_match {
         Ok(Some(_)) => {} // 符合，繼續執行
-        _ => return HttpResponse::BadRequest().body("請先查詢後再上傳檔案進行修改。查詢的日期與修改的考試日期必須一樣"),
+        Ok(None) => return Err(ApiError::new(ErrorCode::Conflict, t!(ExamDateMismatch))),
+        Err(err) => return Err(ApiError::database(t!(QueryExamSessionFailed), err)),
+    };
+    // 第一列為考試資訊，第二列為標題
+    let Some(header_row) = range.rows().nth(1) else {
+        return Err(ApiError::invalid_input(t!(SheetEmpty)));
     };
+    let columns = ColumnMap::from_header(header_row, &MODIFY_SCORE_COLUMNS)
+        .map_err(|err| ApiError::new(ErrorCode::MissingColumns, t!(SheetError, sheet.name, err)))?;
     let mut updated_count = 0;
+    let mut update_list = Vec::new();
+    let mut tx = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
+    if let Some(if_match) = if_match.as_deref() {
+        let current = attendance_etag(&mut tx, exam_session_sn, true)
+            .await
+            .map_err(|err| ApiError::database(t!(ReadOriginalFailed), err))?;
+        check_version(if_match, &current)?;
+    }
 
     // 讀取 Excel 資料並更新 `ExamAttendance`
-    for row in range.rows().skip(2) {
-        if row.len() < 4 {
-            continue;
-        }
-    
-        let student_id = row.get(0)
+    let mut report = ImportReport::default();
+    report.begin_sheet(&sheet.name);
+    for (i, row) in range.rows().enumerate().skip(2) {
+        let line_num = sheet.line_number(i);
+        let student_id = columns.get(row, "學號")
             .and_then(|cell| cell.get_string())
             .map(|s| s.trim().to_string());
         
@@ -104,7 +151,7 @@ pub async fn modify_exam_score(
         }
         let student_id = student_id.unwrap();
     
-        let attendance_status = row.get(1)
+        let attendance_status = columns.get(row, "請假/缺考")
             .and_then(|cell| cell.get_string())
             .map(|s| s.trim().to_string());
     
@@ -114,15 +161,25 @@ pub async fn modify_exam_score(
             _ => (false, false),
         };
     
-        let correct_answers_count = row.get(2)
+        let correct_answers_count = columns.get(row, "答對題數")
             .and_then(|cell| cell.get_float())
             .map(|n| n as i32)
             .unwrap_or(0);
     
-        let notes = row.get(3)
+        let notes = columns.get(row, "備註")
             .and_then(|cell| cell.get_string())
             .map(|s| s.trim().to_string())
             .filter(|s| !s.is_empty());
+
+        let target = AuditTarget::ExamAttendance { exam_session_sn, student_id: &student_id };
+        let before = match target.snapshot(&mut tx).await {
+            Ok(before) => before,
+            Err(e) => return Err(ApiError::database(t!(ReadOriginalFailed), e)),
+        };
+        if before.is_none() {
+            report.reject_at(line_num, &student_id, columns.index("學號"), t!(RowNoAttendance, line_num, student_id));
+            continue;
+        }
     
         // 更新資料庫，僅在有變更時更新
         let result = sqlx::query!(
@@ -149,18 +206,42 @@ pub async fn modify_exam_score(
             correct_answers_count,
             notes
         )
-        .execute(db_pool.as_ref())
+        .execute(&mut *tx)
         .await;
     
-        if let Ok(res) = result {
-            if res.rows_affected() > 0 {
-                if let Err(e) = update_student_status(db_pool.clone(), student_id).await {
-                    println!("更新學生狀態失敗: {}", e);
-                }
+        match result {
+            Ok(res) if res.rows_affected() > 0 => {
+                write_audit_log(&mut tx, session, "/api/modify_exam_score", &target, before)
+                    .await
+                    .map_err(|err| ApiError::database(t!(AuditLogFailed), err))?;
+                report.push(line_num, &student_id, RowAction::Update, None);
+                update_list.push(student_id);
                 updated_count += 1;
             }
+            Ok(_) => report.push(line_num, &student_id, RowAction::Skip, Some(t!(DataUnchanged))),
+            Err(e) => {
+                eprintln!("第 {} 列更新失敗: {:?}", line_num, e);
+                report.reject(line_num, &student_id, t!(RowUpdateFailed, line_num));
+            }
         }
     }
 
-    HttpResponse::Ok().body(format!("成功更新 {} 筆資料", updated_count))    
+    // 在同一個交易內重新計算通過狀態，失敗時連同成績一起回滾
+    let actor = AuditActor::from_session(session, "/api/modify_exam_score");
+    update_students_status(&mut tx, &actor, update_list)
+        .await
+        .map_err(|err| ApiError::database(t!(UpdatePassStatusFailed), err))?;
+
+    let success_body = t!(UpdatedCount, updated_count);
+    let etag = attendance_etag(&mut tx, exam_session_sn, false)
+        .await
+        .map_err(|err| ApiError::database(t!(ReadOriginalFailed), err))?;
+    let (mut response, committed) = finish_import(tx, std::slice::from_ref(&sheet), report, import, previews, session, success_body).await?;
+    if !committed {
+        return Ok(response);
+    }
+    if let Ok(etag) = HeaderValue::from_str(&etag) {
+        response.headers_mut().insert(ETAG, etag);
+    }
+    Ok(response)
 }
\ No newline at end of file
@@ -0,0 +1,47 @@
+use actix_session::Session;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use sqlx::MySqlPool;
+use crate::api::lib::{current_username, is_authorization};
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::i18n::{Lang, LANG_SESSION_KEY};
+use crate::api::messages::{t, Msg};
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema)]
+struct ModifyLanguage {
+    language: Option<Lang>, // "zh-TW" 或 "en"，null 表示依瀏覽器的 Accept-Language
+}
+
+/// 修改自己的語言設定，之後的回應訊息都使用此語言
+#[utoipa::path(tag = "auth", responses((status = 200, description = "已更新語言設定", body = String)))]
+#[post("/api/modify_language")]
+async fn modify_language(
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+    data: web::Json<ModifyLanguage>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::ReadOnly)?;
+    let Some(username) = current_username(&session) else {
+        return Err(ApiError::internal(t!(SessionReadFailed)));
+    };
+    sqlx::query("UPDATE users SET language = ? WHERE username = ?")
+        .bind(data.language.map(Lang::as_str))
+        .bind(&username)
+        .execute(db_pool.get_ref())
+        .await
+        .map_err(|err| ApiError::database(t!(ModifyLanguageFailed), err))?;
+    match data.language {
+        Some(lang) => {
+            session.insert(LANG_SESSION_KEY, lang)?;
+            // 這次的回應就使用新的語言
+            Ok(HttpResponse::Ok().body(Msg::LanguageChanged.render_in(lang, &[])))
+        }
+        None => {
+            session.remove(LANG_SESSION_KEY);
+            Ok(HttpResponse::Ok().body(t!(LanguageChanged)))
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::audit::{write_audit_log, AuditTarget};
+use crate::api::lookup::{validate_entry, LookupKind};
+use actix_session::Session;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use sqlx::MySqlPool;
+use utoipa::ToSchema;
+
+/// 以新的內容取代整筆項目，學生參照的是 SN，改名不影響既有學生資料
+#[derive(Deserialize, Debug, ToSchema)]
+struct ModifyLookup {
+    kind: LookupKind,
+    sn: i32,
+    name: String,
+    code: Option<String>,
+    notes: Option<String>,
+}
+
+#[utoipa::path(tag = "lookup", responses((status = 200, description = "修改成功", body = String)))]
+#[post("/api/modify_lookup")]
+async fn modify_lookup(
+    data: web::Json<ModifyLookup>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Admin)?;
+    let (name, code) = validate_entry(data.kind, &data.name, data.code.as_deref(), data.notes.as_deref())?;
+    let notes = data.notes.as_deref().filter(|notes| !notes.trim().is_empty());
+    let query = format!(
+        "UPDATE {} SET {} = ?, Code = ?, Notes = ? WHERE SN = ?",
+        data.kind.table(),
+        data.kind.name_column()
+    );
+
+    let target = AuditTarget::Lookup(data.kind, data.sn);
+    let mut tx = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
+    let before = target
+        .snapshot(&mut tx)
+        .await
+        .map_err(|err| ApiError::database(t!(ModifyLookupFailed), err))?;
+    if before.is_none() {
+        return Err(ApiError::not_found(t!(LookupNotFound)));
+    }
+    match sqlx::query(&query)
+        .bind(name)
+        .bind(code)
+        .bind(notes)
+        .bind(data.sn)
+        .execute(&mut *tx)
+        .await
+    {
+        Ok(_) => (),
+        Err(sqlx::Error::Database(err)) if err.code() == Some(std::borrow::Cow::Borrowed("23000")) => {
+            return Err(ApiError::new(ErrorCode::AlreadyExists, t!(LookupExists)));
+        }
+        Err(err) => return Err(ApiError::database(t!(ModifyLookupFailed), err)),
+    }
+    write_audit_log(&mut tx, &session, "/api/modify_lookup", &target, before)
+        .await
+        .map_err(|err| ApiError::database(t!(AuditLogFailed), err))?;
+    tx.commit().await.map_err(|err| ApiError::database(t!(CommitFailed), err))?;
+
+    Ok(HttpResponse::Ok().body(t!(UpdateSuccess)))
+}
@@ -1,60 +1,203 @@
-use crate::api::lib::is_authorization;
+use crate::api::lib::{is_authorization, update_students_status};
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::audit::{write_audit_log, AuditActor, AuditTarget};
+use crate::api::etag::{check_version, etag_of, required_if_match};
+use crate::api::lookup::{LookupKind, LookupTable};
 use actix_session::Session;
 use actix_web::{post, web, HttpRequest, HttpResponse};
 use serde::Deserialize;
-use sqlx::{MySqlPool, mysql::MySqlArguments};
-use sqlx::Arguments;
+use sqlx::{MySqlConnection, MySqlPool, mysql::MySqlArguments};
+use sqlx::{Arguments, Row};
+use utoipa::ToSchema;
 
-#[derive(Deserialize,Debug)]
-struct ModifyData {
+#[derive(Deserialize,Debug,ToSchema)]
+#[schema(as = ModifyStudentInfo)]
+pub(crate) struct ModifyData {
     name: Option<String>,
     enrollment_status: Option<String>,
     student_attribute: Option<String>,
     notes: Option<String>,
+    entrance_year: Option<i32>, // 入學年度(民國年)，填 0 代表清除並改由學號推算
 }
+
+/// 與原始資料比對後需要更新的欄位
+struct Changes {
+    set_clauses: Vec<&'static str>,
+    query_args: MySqlArguments,
+    entrance_year_changed: bool,
+}
+
+#[utoipa::path(tag = "students", responses((status = 200, description = "修改成功或沒有需要更新的欄位", body = String)))]
 #[post("/api/modify_student_info")]
 async fn modify_student_info(
     from_data: web::Json<ModifyData>,
     req: HttpRequest,
     mut session: Session,
     db_pool: web::Data<MySqlPool>,
-) -> HttpResponse {
-    if !is_authorization(req, session.clone()) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
-    let student_id: Option<String> = session.get("modify_student_id").unwrap();
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Staff)?;
+    let student_id: Option<String> = session.get("modify_student_id")?;
     let student_id = match student_id {
         Some(id) => id,
-        None => return HttpResponse::BadRequest().body("找不到要修改的學生學號，請先點擊查詢後再修改"),
+        None => return Err(ApiError::invalid_input(t!(ModifyStudentIdMissing))),
     };
     let original_data: ModifyData = ModifyData{
-        name: session.get("modify_name").unwrap(),
-        enrollment_status: session.get("modify_enrollment_status").unwrap(),
-        student_attribute: session.get("modify_student_attribute").unwrap(),
+        name: session.get("modify_name")?,
+        enrollment_status: session.get("modify_enrollment_status")?,
+        student_attribute: session.get("modify_student_attribute")?,
         notes: session.get("modify_notes").unwrap_or(None),
+        entrance_year: session.get("modify_entrance_year").unwrap_or(None),
     };
     let new_data = from_data.into_inner();
 
-    // 輔助函式：將 enrollment_status 轉換成數字
-    fn convert_enrollment_status(s: &str) -> Result<i32, HttpResponse> {
-        match s {
-            "在學" => Ok(1),
-            "休學" => Ok(2),
-            "退學" => Ok(3),
-            _ => Err(HttpResponse::BadRequest().body("註冊狀態請填入在學、休學、退學")),
-        }
+    let mut conn = db_pool.acquire().await?;
+    let changes = diff_changes(&mut conn, original_data, new_data).await?;
+    drop(conn);
+
+    // 如果沒有任何欄位有變化，就直接回傳
+    if changes.set_clauses.is_empty() {
+        clean_session(&mut session);
+        return Ok(HttpResponse::Ok().body(t!(NothingToUpdate)));
     }
+    let entrance_year_changed = changes.entrance_year_changed;
 
-    // 輔助函式：將 student_attribute 轉換成數字
-    fn convert_student_attribute(s: &str) -> Result<i32, HttpResponse> {
-        match s {
-            "本系" => Ok(1),
-            "外系" => Ok(2),
-            "外校" => Ok(3),
-            _ => Err(HttpResponse::BadRequest().body("學生屬性請填入本系、外系、外校")),
+    // 執行更新，並在同一個交易內寫入稽核紀錄
+    let target = AuditTarget::Student(&student_id);
+    let result = async {
+        let mut tx = db_pool.begin().await?;
+        let before = target.snapshot(&mut tx).await?;
+        apply_changes(&mut tx, &student_id, changes).await?;
+        write_audit_log(&mut tx, &session, "/api/modify_student_info", &target, before).await?;
+        // 入學年度改變時，適用的通過規則可能不同，需在同一個交易內重新計算
+        if entrance_year_changed {
+            let actor = AuditActor::from_session(&session, "/api/modify_student_info");
+            update_students_status(&mut tx, &actor, [student_id.clone()]).await?;
         }
+        tx.commit().await
+    }
+    .await;
+
+    match result {
+        Ok(()) => {
+            clean_session(&mut session);
+            Ok(HttpResponse::Ok().body(t!(UpdateSuccess)))
+        },
+        Err(e) => Err(ApiError::database(t!(UpdateFailed), e)),
+    }
+}
+
+/// 以學號指定要修改的學生，不依賴查詢時存入 session 的資料
+/// If-Match 需帶入 GET /api/student_info/{student_id} 回應的 ETag，資料已被修改時回傳 412
+#[utoipa::path(
+    tag = "students",
+    params(("If-Match" = String, Header, description = "讀取時回應的 ETag")),
+    responses((status = 200, description = "修改成功，ETag 標頭為新的版本", body = String)),
+)]
+#[post("/api/modify_student_info/{student_id}")]
+async fn modify_student_info_by_id(
+    path: web::Path<String>,
+    from_data: web::Json<ModifyData>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req.clone(), session.clone(), Role::Staff)?;
+    let if_match = required_if_match(&req)?;
+    let student_id = path.into_inner().to_ascii_uppercase();
+    modify_student(&db_pool, &session, "/api/modify_student_info", &student_id, &if_match, from_data.into_inner()).await
+}
+
+/// 比對版本後修改學生資料，/api/v2/students/{student_id} 共用
+pub(crate) async fn modify_student(
+    db_pool: &web::Data<MySqlPool>,
+    session: &Session,
+    endpoint: &str,
+    student_id: &str,
+    if_match: &str,
+    new_data: ModifyData,
+) -> Result<HttpResponse, ApiError> {
+    let target = AuditTarget::Student(student_id);
+
+    let mut tx = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
+    let before = target
+        .snapshot_for_update(&mut tx)
+        .await
+        .map_err(|err| ApiError::database(t!(ReadOriginalFailed), err))?;
+    let Some(current) = before.as_deref() else {
+        return Err(ApiError::new(ErrorCode::StudentNotFound, t!(StudentIdNotFound)));
+    };
+    check_version(if_match, &etag_of(current))?;
+    let original_data = load_original(&mut tx, student_id)
+        .await
+        .map_err(|err| ApiError::database(t!(ReadOriginalFailed), err))?;
+    let changes = diff_changes(&mut tx, original_data, new_data).await?;
+    if changes.set_clauses.is_empty() {
+        return Ok(HttpResponse::Ok()
+            .insert_header(("ETag", etag_of(current)))
+            .body(t!(NothingToUpdate)));
+    }
+    let entrance_year_changed = changes.entrance_year_changed;
+    let result = async {
+        apply_changes(&mut tx, student_id, changes).await?;
+        write_audit_log(&mut tx, session, endpoint, &target, before.clone()).await?;
+        Ok::<_, sqlx::Error>(())
     }
+    .await;
+    result.map_err(|err| ApiError::database(t!(UpdateFailed), err))?;
 
+    // 入學年度改變時，適用的通過規則可能不同，需在同一個交易內重新計算
+    if entrance_year_changed {
+        update_students_status(&mut tx, &AuditActor::from_session(session, endpoint), [student_id.to_string()])
+            .await
+            .map_err(|err| ApiError::database(t!(UpdatePassStatusFailed), err))?;
+    }
+    // 重新計算後才讀取新的版本，回傳的 ETag 已包含最新的通過狀態
+    let result = async {
+        let after = target.snapshot(&mut tx).await?;
+        tx.commit().await?;
+        Ok::<_, sqlx::Error>(after)
+    }
+    .await;
+    let after = result.map_err(|err| ApiError::database(t!(UpdateFailed), err))?;
+
+    let mut response = HttpResponse::Ok();
+    if let Some(after) = after {
+        response.insert_header(("ETag", etag_of(&after)));
+    }
+    Ok(response.body(t!(UpdateSuccess)))
+}
+
+/// 從資料庫讀出目前的資料，格式與查詢時存入 session 的相同
+async fn load_original(conn: &mut MySqlConnection, student_id: &str) -> Result<ModifyData, sqlx::Error> {
+    let row = sqlx::query(
+        r#"
+        SELECT si.Name, es.Status, sa.Attribute, si.Notes, si.EntranceYear
+        FROM StudentInfo si
+        LEFT JOIN EnrollmentStatus es ON si.EnrollmentStatus_SN = es.SN
+        LEFT JOIN StudentAttributes sa ON si.StudentAttribute_SN = sa.SN
+        WHERE si.StudentID = ?
+        "#,
+    )
+    .bind(student_id)
+    .fetch_one(conn)
+    .await?;
+    Ok(ModifyData {
+        name: row.try_get("Name")?,
+        enrollment_status: row.try_get("Status")?,
+        student_attribute: row.try_get("Attribute")?,
+        notes: row.try_get("Notes")?,
+        entrance_year: row.try_get("EntranceYear")?,
+    })
+}
+
+/// 比對原始資料與新資料，組合出需要更新的欄位與參數
+async fn diff_changes(
+    conn: &mut MySqlConnection,
+    original_data: ModifyData,
+    new_data: ModifyData,
+) -> Result<Changes, ApiError> {
     // 用來動態組合更新的 SQL 語句與參數
     let mut set_clauses = Vec::new();
     let mut query_args = MySqlArguments::default();
@@ -67,33 +210,31 @@ async fn modify_student_info(
         }
     }
 
-    // 處理 enrollment_status：前端是字串，要轉換成數字
+    // 處理 enrollment_status：前端是名稱或代碼，要對照資料庫轉換成 SN
     if let Some(new_enroll_str) = new_data.enrollment_status {
         if Some(new_enroll_str.clone()) != original_data.enrollment_status {
-            match convert_enrollment_status(&new_enroll_str) {
-                Ok(new_enroll_num) => {
-                    set_clauses.push("EnrollmentStatus_SN = ?");
-                    let _= query_args.add(new_enroll_num);
-                }
-                Err(err) => {
-                    return err;
-                }
-            }
+            let statuses = LookupTable::load(&mut *conn, LookupKind::EnrollmentStatus)
+                .await
+                .map_err(|err| ApiError::database(t!(LoadLookupFailed), err))?;
+            let Some(new_enroll_num) = statuses.find(&new_enroll_str).map(|entry| entry.sn) else {
+                return Err(ApiError::invalid_input(t!(EnrollmentStatusInvalid, statuses.names())).with_field("enrollment_status"));
+            };
+            set_clauses.push("EnrollmentStatus_SN = ?");
+            let _= query_args.add(new_enroll_num);
         }
     }
 
     // 處理 student_attribute：同樣需要轉換
     if let Some(new_attr_str) = new_data.student_attribute {
         if Some(new_attr_str.clone()) != original_data.student_attribute {
-            match convert_student_attribute(&new_attr_str) {
-                Ok(new_attr_num) => {
-                    set_clauses.push("StudentAttribute_SN = ?");
-                    let _= query_args.add(new_attr_num);
-                }
-                Err(err) => {
-                    return err;
-                }
-            }
+            let attributes = LookupTable::load(&mut *conn, LookupKind::StudentAttribute)
+                .await
+                .map_err(|err| ApiError::database(t!(LoadLookupFailed), err))?;
+            let Some(new_attr_num) = attributes.find(&new_attr_str).map(|entry| entry.sn) else {
+                return Err(ApiError::invalid_input(t!(StudentAttributeInvalid, attributes.names())).with_field("student_attribute"));
+            };
+            set_clauses.push("StudentAttribute_SN = ?");
+            let _= query_args.add(new_attr_num);
         }
     }
 
@@ -104,44 +245,46 @@ async fn modify_student_info(
         } else {
             Some(new_notes)
         };
-    
+
         if new_notes_val != original_data.notes {
             set_clauses.push("Notes = ?");
             let _= query_args.add(new_notes_val);
         }
     }
 
-    // 如果沒有任何欄位有變化，就直接回傳
-    if set_clauses.is_empty() {
-        clean_session(&mut session);
-        return HttpResponse::Ok().body("無更新內容");
+    // 處理 entrance_year：0 代表清除明確設定的入學年度
+    let mut entrance_year_changed = false;
+    if let Some(new_year) = new_data.entrance_year {
+        let new_year_val = if new_year == 0 { None } else { Some(new_year) };
+        if new_year_val.is_some_and(|year| year < 0) {
+            return Err(ApiError::invalid_input(t!(EntranceYearInvalid)).with_field("entrance_year"));
+        }
+        if new_year_val != original_data.entrance_year {
+            set_clauses.push("EntranceYear = ?");
+            let _= query_args.add(new_year_val);
+            entrance_year_changed = true;
+        }
     }
 
+    Ok(Changes { set_clauses, query_args, entrance_year_changed })
+}
+
+async fn apply_changes(conn: &mut MySqlConnection, student_id: &str, changes: Changes) -> Result<(), sqlx::Error> {
     // 組合 SQL 語句
-    let set_clause = set_clauses.join(", ");
+    let set_clause = changes.set_clauses.join(", ");
     let sql = format!("UPDATE StudentInfo SET {} WHERE StudentID = ?", set_clause);
     // 最後將 student_id 當作條件參數加入
-    let _= query_args.add(student_id);
-
-    // 執行更新
-    let result = sqlx::query_with(&sql, query_args)
-        .execute(db_pool.get_ref())
-        .await;
-
-    match result {
-        Ok(_res) => {
-            clean_session(&mut session);
-            HttpResponse::Ok().body("更新成功")
-        },
-        Err(_e) => {
-            HttpResponse::InternalServerError().body("更新失敗")
-        }
-    }
+    let mut query_args = changes.query_args;
+    let _= query_args.add(student_id.to_string());
+    sqlx::query_with(&sql, query_args).execute(conn).await?;
+    Ok(())
 }
+
 fn clean_session(session: &mut Session) {
     session.remove("modify_student_id");
     session.remove("modify_name");
     session.remove("modify_enrollment_status");
     session.remove("modify_student_attribute");
     session.remove("modify_notes");
+    session.remove("modify_entrance_year");
 }
\ No newline at end of file
@@ -0,0 +1,56 @@
+use actix_session::Session;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use sqlx::MySqlPool;
+use crate::api::lib::{current_username, is_authorization};
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::audit::{write_audit_log, AuditTarget};
+use crate::api::session_registry::SessionRegistry;
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema)]
+struct ModifyUserRole {
+    username: String,
+    role: Role,
+}
+
+#[utoipa::path(tag = "users", responses((status = 200, description = "已修改角色", body = String)))]
+#[post("/api/modify_user_role")]
+async fn modify_user_role(
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+    registry: web::Data<SessionRegistry>,
+    data: web::Json<ModifyUserRole>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Admin)?;
+    // 避免管理員把自己降級後系統沒有管理員
+    if current_username(&session).as_deref() == Some(data.username.as_str()) {
+        return Err(ApiError::new(ErrorCode::Conflict, t!(CannotChangeOwnRole)));
+    }
+    let target = AuditTarget::User(&data.username);
+    let result = async {
+        let mut tx = db_pool.begin().await?;
+        let before = target.snapshot(&mut tx).await?;
+        let res = sqlx::query("UPDATE users SET role = ? WHERE username = ?")
+            .bind(data.role.as_str())
+            .bind(&data.username)
+            .execute(&mut *tx)
+            .await?;
+        write_audit_log(&mut tx, &session, "/api/modify_user_role", &target, before).await?;
+        tx.commit().await?;
+        Ok::<_, sqlx::Error>(res)
+    }
+    .await;
+    match result {
+        Ok(res) if res.rows_affected() > 0 => {
+            // 角色存放在 session 中，需重新登入才會套用新角色
+            registry.revoke_after_change(&data.username).await?;
+            Ok(HttpResponse::Ok().body(t!(RoleChanged)))
+        }
+        Ok(_) => Err(ApiError::not_found(t!(UserNotFoundOrRoleUnchanged))),
+        Err(err) => Err(ApiError::database(t!(ChangeRoleFailed), err)),
+    }
+}
@@ -0,0 +1,37 @@
+use crate::api::audit::AuditActor;
+use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use crate::api::pass_status::{self, RecomputeFilter, RecomputeReport};
+use actix_session::Session;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use sqlx::MySqlPool;
+use utoipa::ToSchema;
+
+#[derive(Deserialize, Debug, ToSchema)]
+struct RecomputePassStatus {
+    #[serde(flatten)]
+    filter: RecomputeFilter,
+    #[serde(default)]
+    dry_run: bool, // 只回報會變動的學生，不寫入
+}
+
+/// 依目前啟用的通過規則重新計算學生的通過狀態，未指定條件時為所有學生
+/// 回傳通過狀態有變動的學生
+#[utoipa::path(tag = "pass_criteria", responses((status = 200, description = "重新計算的學生數與通過狀態有變動的學生", body = RecomputeReport)))]
+#[post("/api/recompute_pass_status")]
+async fn recompute_pass_status(
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+    data: web::Json<RecomputePassStatus>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Admin)?;
+    let actor = AuditActor::from_session(&session, "/api/recompute_pass_status");
+    let report = pass_status::recompute_pass_status(db_pool.get_ref(), &actor, &data.filter, data.dry_run)
+        .await
+        .map_err(|err| ApiError::database(t!(RecomputeStatusFailed), err))?;
+    Ok(HttpResponse::Ok().json(report))
+}
\ No newline at end of file
@@ -0,0 +1,66 @@
+use actix_session::Session;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use sqlx::MySqlPool;
+use crate::api::lib::{hash_password, is_authorization};
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use crate::api::audit::{insert_audit_log_as, AuditActor, AuditTarget};
+use crate::api::session_registry::SessionRegistry;
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema)]
+struct ResetUserPassword {
+    username: String,
+    new_password: String,
+}
+
+#[utoipa::path(tag = "users", responses((status = 200, description = "已重設密碼", body = String)))]
+#[post("/api/reset_user_password")]
+async fn reset_user_password(
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+    registry: web::Data<SessionRegistry>,
+    data: web::Json<ResetUserPassword>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Admin)?;
+    reset_password(db_pool.get_ref(), &AuditActor::from_session(&session, "/api/reset_user_password"), &data.username, &data.new_password).await?;
+    // 重設密碼後要求此使用者重新登入
+    registry.revoke_after_change(&data.username).await?;
+    Ok(HttpResponse::Ok().body(t!(PasswordReset)))
+}
+
+/// 重設使用者的密碼，使用者不存在時回傳 NotFound，cpe-admin 的 reset-password 共用
+/// 呼叫端需自行撤銷此使用者的 session
+pub async fn reset_password(
+    db_pool: &MySqlPool,
+    actor: &AuditActor,
+    username: &str,
+    new_password: &str,
+) -> Result<(), ApiError> {
+    let hashed_password = hash_password(new_password)?;
+    let target = AuditTarget::User(username);
+    let result = async {
+        let mut tx = db_pool.begin().await?;
+        let res = sqlx::query("UPDATE users SET password = ? WHERE username = ?")
+            .bind(hashed_password)
+            .bind(username)
+            .execute(&mut *tx)
+            .await?;
+        // 不記錄密碼內容，只記錄有重設過
+        if res.rows_affected() > 0 {
+            let after = Some(r#"{"password":"reset"}"#.to_string());
+            insert_audit_log_as(&mut tx, actor, &target, None, after).await?;
+        }
+        tx.commit().await?;
+        Ok::<_, sqlx::Error>(res)
+    }
+    .await;
+    match result {
+        Ok(res) if res.rows_affected() > 0 => Ok(()),
+        Ok(_) => Err(ApiError::not_found(t!(UserNotFound))),
+        Err(err) => Err(ApiError::database(t!(ResetPasswordFailed), err)),
+    }
+}
@@ -0,0 +1,52 @@
+use actix_session::Session;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use crate::api::lib::is_authorization;
+use crate::api::login_throttle::{LoginThrottle, ThrottleTarget};
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema)]
+struct UnlockLogin {
+    username: Option<String>,
+    ip: Option<String>,
+}
+
+#[utoipa::path(tag = "users", responses((status = 200, description = "已解除鎖定", body = String)))]
+#[post("/api/unlock_login")]
+async fn unlock_login(
+    req: HttpRequest,
+    session: Session,
+    throttle: web::Data<LoginThrottle>,
+    data: web::Json<UnlockLogin>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session, Role::Admin)?;
+    let mut targets = Vec::new();
+    if let Some(username) = &data.username {
+        targets.push((ThrottleTarget::User, username));
+    }
+    if let Some(ip) = &data.ip {
+        targets.push((ThrottleTarget::Ip, ip));
+    }
+    if targets.is_empty() {
+        return Err(ApiError::invalid_input(t!(UnlockTargetRequired)));
+    }
+    let mut unlocked = 0;
+    for (target, value) in targets {
+        match throttle.unlock(target, value).await {
+            Ok(true) => unlocked += 1,
+            Ok(false) => (),
+            Err(err) => {
+                eprintln!("Redis error: {:?}", err);
+                return Err(ApiError::internal(t!(UnlockFailed)));
+            }
+        }
+    }
+    if unlocked == 0 {
+        Ok(HttpResponse::Ok().body(t!(NotLockedCleared)))
+    } else {
+        Ok(HttpResponse::Ok().body(t!(UnlockSuccess)))
+    }
+}
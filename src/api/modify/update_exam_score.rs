@@ -1,41 +1,49 @@
-use crate::api::lib::{is_authorization, update_student_status};
+use crate::api::lib::{is_authorization, update_students_status};
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::audit::{write_audit_log, AuditActor, AuditTarget};
+use crate::api::etag::{attendance_etag, check_version, required_if_match};
 use actix_session::Session;
 use actix_web::{post, web, HttpRequest, HttpResponse};
-use sqlx::MySqlPool;
+use sqlx::{MySqlConnection, MySqlPool};
 use serde::Deserialize;
 use chrono::NaiveDate;
+use std::collections::HashSet;
+use utoipa::ToSchema;
 
 // 定義接收的 JSON 數據結構
-#[derive(Deserialize)]
-struct StudentData {
+#[derive(Deserialize, ToSchema)]
+#[schema(as = UpdateScoreItem)]
+pub(crate) struct StudentData {
     student_id: String,
     status: String,
     correct_answers_count: i32,
     notes: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
+#[schema(as = UpdateExamScore)]
 struct ModifyRequest {
     session: String, // 場次名稱，例如 "場次1"
     students: Vec<StudentData>,
 }
 
+#[utoipa::path(tag = "scores", responses((status = 200, description = "更新的筆數", body = String)))]
 #[post("/api/update_exam_score")]
 pub async fn update_exam_score(
     req: HttpRequest,
     session: Session,
     db_pool: web::Data<MySqlPool>,
     mut data: web::Json<ModifyRequest>,
-) -> HttpResponse {
+) -> Result<HttpResponse, ApiError> {
     // 驗證授權
-    if !is_authorization(req, session.clone()) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
+    is_authorization(req, session.clone(), Role::Staff)?;
 
     // 處理 session 字串，格式為 "日期,場次類型"
     let session_parts: Vec<&str> = data.session.split(',').collect();
     if session_parts.len() != 2 {
-        return HttpResponse::BadRequest().body("場次格式無效，應為 '日期,場次類型'（例如 '2025-01-06,自辦'）");
+        return Err(ApiError::invalid_input(t!(SessionFormatInvalid)));
     }
 
     let date_str = session_parts[0].trim();
@@ -44,14 +52,14 @@ pub async fn update_exam_score(
     // 解析日期
     let exam_date = match NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
         Ok(date) => date,
-        Err(_) => return HttpResponse::BadRequest().body("無效的日期格式，應為 'YYYY-MM-DD'"),
+        Err(_) => return Err(ApiError::invalid_input(t!(DateFormatInvalid)).with_field("session")),
     };
 
     // 從 session 中獲取 exam_session_sn（可選，根據需求決定是否保留）
     let exam_session_sn:i32 = match session.get("delete_exam_session_sn") {
         Ok(Some(sn)) => sn,
-        Ok(None) => return HttpResponse::BadRequest().body("請先查詢後再進行修改。"),
-        Err(_) => return HttpResponse::InternalServerError().body("server error"),
+        Ok(None) => return Err(ApiError::invalid_input(t!(QueryBeforeModify))),
+        Err(err) => return Err(err.into()),
     };
 
     // 查詢 ExamSessions，獲取場次日期並驗證
@@ -64,12 +72,13 @@ pub async fn update_exam_score(
 
     let stored_exam_date: NaiveDate = match session_info {
         Ok(Some(record)) => record.ExamDate,
-        _ => return HttpResponse::BadRequest().body("無效的場次"),
+        Ok(None) => return Err(ApiError::new(ErrorCode::ExamSessionNotFound, t!(InvalidExamSession))),
+        Err(err) => return Err(ApiError::database(t!(QuerySessionFailed), err)),
     };
 
     // 驗證日期是否匹配
     if stored_exam_date != exam_date {
-        return HttpResponse::BadRequest().body("場次日期不匹配");
+        return Err(ApiError::new(ErrorCode::Conflict, t!(SessionDateMismatch)));
     }
 
     // 驗證場次類型是否匹配
@@ -84,13 +93,91 @@ pub async fn update_exam_score(
 
     match session_match {
         Ok(Some(_)) => {} // 符合，繼續執行
-        _ => return HttpResponse::BadRequest().body("場次資訊與場次序號不匹配"),
+        Ok(None) => return Err(ApiError::new(ErrorCode::Conflict, t!(SessionSnMismatch))),
+        Err(err) => return Err(ApiError::database(t!(QuerySessionFailed), err)),
     };
 
     // 處理學生資料並更新
+    let mut tx = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
+    let updated_count =
+        update_scores(&mut tx, &session, "/api/update_exam_score", exam_session_sn, &mut data.students).await?;
+    tx.commit().await.map_err(|err| ApiError::database(t!(CommitFailed), err))?;
+
+    Ok(HttpResponse::Ok().body(t!(UpdatedCount, updated_count)))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct UpdateScores {
+    pub(crate) students: Vec<StudentData>,
+}
+
+/// 以場次 SN 指定要修改的場次，不依賴查詢時存入 session 的資料
+/// If-Match 需帶入 GET /api/exam_scores/{sn} 回應的 ETag，場次內任一筆成績已被修改時回傳 412
+#[utoipa::path(
+    tag = "scores",
+    params(("If-Match" = String, Header, description = "讀取時回應的 ETag")),
+    responses((status = 200, description = "更新的筆數，ETag 標頭為新的版本", body = String)),
+)]
+#[post("/api/update_exam_score/{sn}")]
+pub async fn update_exam_score_by_sn(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+    mut data: web::Json<UpdateScores>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req.clone(), session.clone(), Role::Staff)?;
+    let if_match = required_if_match(&req)?;
+    let exam_session_sn = path.into_inner();
+    modify_scores(&db_pool, &session, "/api/update_exam_score", exam_session_sn, &if_match, &mut data.students).await
+}
+
+/// 比對場次內成績的版本後更新成績，/api/v2/exam-sessions/{sn}/attendance 共用
+pub(crate) async fn modify_scores(
+    db_pool: &web::Data<MySqlPool>,
+    session: &Session,
+    endpoint: &str,
+    exam_session_sn: i32,
+    if_match: &str,
+    students: &mut [StudentData],
+) -> Result<HttpResponse, ApiError> {
+    let mut tx = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
+    let exists = sqlx::query_scalar::<_, i32>("SELECT SN FROM ExamSessions WHERE SN = ?")
+        .bind(exam_session_sn)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| ApiError::database(t!(QuerySessionFailed), err))?;
+    if exists.is_none() {
+        return Err(ApiError::new(ErrorCode::ExamSessionNotFound, t!(ExamSessionNotFound)));
+    }
+    let current = attendance_etag(&mut tx, exam_session_sn, true)
+        .await
+        .map_err(|err| ApiError::database(t!(ReadOriginalFailed), err))?;
+    check_version(if_match, &current)?;
+
+    let updated_count = update_scores(&mut tx, session, endpoint, exam_session_sn, students).await?;
+    let etag = attendance_etag(&mut tx, exam_session_sn, false)
+        .await
+        .map_err(|err| ApiError::database(t!(ReadOriginalFailed), err))?;
+    tx.commit().await.map_err(|err| ApiError::database(t!(CommitFailed), err))?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("ETag", etag))
+        .body(t!(UpdatedCount, updated_count)))
+}
+
+/// 更新場次內的學生成績，並在同一個交易內重新計算有變更的學生的通過狀態，回傳更新筆數
+async fn update_scores(
+    tx: &mut MySqlConnection,
+    session: &Session,
+    endpoint: &str,
+    exam_session_sn: i32,
+    students: &mut [StudentData],
+) -> Result<i32, ApiError> {
     let mut updated_count = 0;
+    let mut update_list = HashSet::new();
 
-    for student in &mut data.students {
+    for student in students.iter_mut() {
         // 根據 status 設置 IsAbsent 和 IsExcused
         let (is_absent, is_excused) = match student.status.as_str() {
             "缺考" => {
@@ -111,6 +198,12 @@ pub async fn update_exam_score(
             Some(student.notes.trim().to_string())
         };
 
+        let target = AuditTarget::ExamAttendance { exam_session_sn, student_id: &student.student_id };
+        let before = match target.snapshot(&mut *tx).await {
+            Ok(before) => before,
+            Err(e) => return Err(ApiError::database(t!(ReadOriginalFailed), e)),
+        };
+
         // 更新 ExamAttendance 表
         let result = sqlx::query!(
             r#"
@@ -136,16 +229,16 @@ pub async fn update_exam_score(
             student.correct_answers_count,
             notes
         )
-        .execute(db_pool.as_ref())
+        .execute(&mut *tx)
         .await;
 
         match result {
             Ok(res) => {
                 if res.rows_affected() > 0 {
-                    // 如果更新成功，調用 update_student_status
-                    if let Err(e) = update_student_status(db_pool.clone(), student.student_id.clone()).await {
-                        println!("更新學生狀態失敗: {}", e);
-                    }
+                    write_audit_log(&mut *tx, session, endpoint, &target, before)
+                        .await
+                        .map_err(|err| ApiError::database(t!(AuditLogFailed), err))?;
+                    update_list.insert(student.student_id.to_ascii_uppercase());
                     updated_count += 1;
                 }
             }
@@ -156,5 +249,9 @@ pub async fn update_exam_score(
         }
     }
 
-    HttpResponse::Ok().body(format!("成功更新 {} 筆資料", updated_count))
+    let actor = AuditActor::from_session(session, endpoint);
+    update_students_status(&mut *tx, &actor, update_list)
+        .await
+        .map_err(|err| ApiError::database(t!(UpdatePassStatusFailed), err))?;
+    Ok(updated_count)
 }
\ No newline at end of file
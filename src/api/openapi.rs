@@ -0,0 +1,173 @@
+use actix_web::{get, HttpResponse};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, SecurityScheme};
+use utoipa::openapi::{ContentBuilder, Ref, ResponseBuilder};
+use utoipa::{Modify, OpenApi, ToSchema};
+use crate::api::error::ErrorBody;
+use crate::api::{login, logout, check_session, download};
+use crate::api::query::{
+    excel_search_pass, student_id_search, get_exam_session_info, search_absent_and_excused, excel_search_absent,
+    query_exam_info, query_score_info, scholarship_query_json, query_scholarship_excel, query_passed_by_year,
+    query_pass_criteria, query_users, query_sessions, query_login_locks, query_audit_log, query_lookup,
+};
+use crate::api::create::{
+    add_exam, get_students_info_template, mutiple_add_student_info, get_exam_score_template, mutiple_add_exam_score,
+    single_add_student, single_add_exam_score, get_scholarship_template, mutiple_add_scholarship, add_pass_criteria,
+    create_user, add_lookup,
+};
+use crate::api::modify::{
+    modify_student_info, modify_exam_info, modify_exam_score, update_exam_score, update_scholarship,
+    activate_pass_criteria, recompute_pass_status, modify_user_role, disable_user, reset_user_password, unlock_login,
+    modify_language, modify_lookup,
+};
+use crate::api::delete::{
+    delete_student_info, delete_exam_info, delete_exam_score, delete_scholarship_records, delete_scholarship,
+    delete_student, delete_pass_criteria, delete_user, revoke_session, delete_lookup,
+};
+use crate::api::v2::{students, exam_sessions, attendance, scholarships};
+
+/// 由各 API 的請求與回應型別產生的 OpenAPI 3 文件
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "cpe_backend", description = "CPE 檢定成績管理系統 API"),
+    paths(
+        login::login,
+        logout::logout,
+        check_session::check_session,
+        download::download,
+        excel_search_pass::excel_search_pass,
+        student_id_search::student_id_search,
+        student_id_search::get_student_info,
+        get_exam_session_info::get_exam_session_info,
+        search_absent_and_excused::search_absent_and_excused,
+        excel_search_absent::excel_search_absent,
+        query_exam_info::query_exam_info,
+        query_exam_info::get_exam_info,
+        query_score_info::query_score_info,
+        query_score_info::get_exam_scores,
+        scholarship_query_json::query_scholarship_json,
+        query_scholarship_excel::query_scholarship_excel,
+        query_passed_by_year::query_passed_by_year,
+        query_pass_criteria::query_pass_criteria,
+        query_users::query_users,
+        query_sessions::query_sessions,
+        query_login_locks::query_login_locks,
+        query_audit_log::query_audit_log,
+        query_lookup::query_lookup,
+        add_exam::add_exam,
+        get_students_info_template::get_students_info_template,
+        mutiple_add_student_info::mutiple_add_student_info,
+        get_exam_score_template::get_exam_score_template,
+        mutiple_add_exam_score::mutiple_add_exam_score,
+        single_add_student::single_add_student,
+        single_add_exam_score::single_add_exam_score,
+        get_scholarship_template::get_scholarship_template,
+        mutiple_add_scholarship::mutiple_add_scholarship,
+        add_pass_criteria::add_pass_criteria,
+        create_user::create_user,
+        add_lookup::add_lookup,
+        modify_student_info::modify_student_info,
+        modify_student_info::modify_student_info_by_id,
+        modify_exam_info::modify_exam_info,
+        modify_exam_info::modify_exam_info_by_sn,
+        modify_exam_score::modify_exam_score,
+        modify_exam_score::modify_exam_score_by_sn,
+        update_exam_score::update_exam_score,
+        update_exam_score::update_exam_score_by_sn,
+        update_scholarship::update_scholarship,
+        activate_pass_criteria::activate_pass_criteria,
+        recompute_pass_status::recompute_pass_status,
+        modify_user_role::modify_user_role,
+        disable_user::disable_user,
+        reset_user_password::reset_user_password,
+        unlock_login::unlock_login,
+        modify_language::modify_language,
+        modify_lookup::modify_lookup,
+        delete_student_info::delete_student_info,
+        delete_exam_info::delete_exam_info,
+        delete_exam_score::delete_exam_score,
+        delete_exam_score::delete_exam_score_by_sn,
+        delete_scholarship_records::delete_scholarship_records,
+        delete_scholarship::delete_scholarship,
+        delete_student::delete_student,
+        delete_pass_criteria::delete_pass_criteria,
+        delete_user::delete_user,
+        revoke_session::revoke_session,
+        delete_lookup::delete_lookup,
+        openapi_json,
+    ),
+    nest((path = "/api/v2", api = V2Doc)),
+    components(schemas(ErrorBody)),
+    modifiers(&SessionSecurity, &ErrorResponses),
+    security(("session" = [], "csrf_token" = [])),
+)]
+pub struct ApiDoc;
+
+/// /api/v2 資源路由，路徑相對於 /api/v2 的 scope
+#[derive(OpenApi)]
+#[openapi(paths(
+    students::get_student,
+    students::create_student,
+    students::patch_student,
+    students::delete_student,
+    exam_sessions::get_exam_session,
+    exam_sessions::create_exam_session,
+    exam_sessions::patch_exam_session,
+    exam_sessions::delete_exam_session,
+    attendance::get_attendance,
+    attendance::create_attendance,
+    attendance::patch_attendance,
+    attendance::delete_attendance,
+    scholarships::get_scholarship,
+    scholarships::create_scholarship,
+    scholarships::patch_scholarship,
+    scholarships::delete_scholarship,
+))]
+struct V2Doc;
+
+/// 上傳 Excel 檔案的 multipart/form-data 內容，檔案欄位名稱不限
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct UploadForm {
+    #[schema(value_type = String, format = Binary)]
+    file: Vec<u8>,
+}
+
+/// 登入後以 cookie 維持 session，除下載外的請求都需要帶入登入時取得的 X-CSRF-Token
+struct SessionSecurity;
+
+impl Modify for SessionSecurity {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme("session", SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new("id"))));
+        components.add_security_scheme("csrf_token", SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-CSRF-Token"))));
+    }
+}
+
+/// 所有 API 的錯誤回應格式相同，統一加上 default 回應，不需要在每個 API 重複列出
+struct ErrorResponses;
+
+impl Modify for ErrorResponses {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let response = ResponseBuilder::new()
+            .description("錯誤，code 為錯誤代碼，HTTP 狀態碼見 README 的錯誤格式")
+            .content("application/json", ContentBuilder::new().schema(Some(Ref::from_schema_name("ErrorBody"))).build())
+            .build();
+        for item in openapi.paths.paths.values_mut() {
+            let operations = [&mut item.get, &mut item.post, &mut item.patch, &mut item.delete];
+            for operation in operations.into_iter().flatten() {
+                operation.responses.responses.insert("default".to_string(), response.clone().into());
+            }
+        }
+    }
+}
+
+/// 提供 OpenAPI 文件，可用來產生前端的型別與 client，或做 contract test
+#[utoipa::path(
+    tag = "docs",
+    security(()),
+    responses((status = 200, description = "OpenAPI 3 文件", content_type = "application/json", body = Object)),
+)]
+#[get("/api/openapi.json")]
+async fn openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
@@ -0,0 +1,608 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::mysql::MySql;
+use sqlx::{Error, Executor, Row};
+use crate::api::messages::t;
+use utoipa::ToSchema;
+
+/// 學生的考試統計，作為通過規則的判斷依據
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExamStats {
+    pub total_correct: i32, // 累計答對題數
+    pub max_correct: i32,   // 單次最高答對題數
+}
+
+impl ExamStats {
+    /// 由每場考試的答對題數計算統計值
+    pub fn from_counts<I: IntoIterator<Item = i32>>(counts: I) -> Self {
+        let mut stats = ExamStats::default();
+        for count in counts {
+            stats.add(count);
+        }
+        stats
+    }
+
+    pub fn add(&mut self, count: i32) {
+        self.total_correct += count;
+        self.max_correct = self.max_correct.max(count);
+    }
+}
+
+/// CPE 通過規則，以 JSON 存放於 PassCriteria 表
+/// 例如預設規則：
+/// {"type":"any","rules":[{"type":"single_exam","min_correct":2},{"type":"cumulative","min_total":3}]}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PassRule {
+    /// 單次考試答對題數達門檻
+    SingleExam {
+        min_correct: i32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
+    },
+    /// 累計答對題數達門檻
+    Cumulative {
+        min_total: i32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
+    },
+    /// 所有子規則皆須成立
+    All {
+        #[schema(no_recursion)]
+        rules: Vec<PassRule>,
+    },
+    /// 任一子規則成立即可
+    Any {
+        #[schema(no_recursion)]
+        rules: Vec<PassRule>,
+    },
+}
+
+impl Default for PassRule {
+    /// 系上原本寫死的規則：一次兩題或累計3題
+    fn default() -> Self {
+        PassRule::Any {
+            rules: vec![
+                PassRule::SingleExam { min_correct: 2, label: Some("一次兩題".to_string()) },
+                PassRule::Cumulative { min_total: 3, label: Some("累計3題".to_string()) },
+            ],
+        }
+    }
+}
+
+impl PassRule {
+    /// 判斷是否通過，通過時回傳成立的單一條件（SingleExam 或 Cumulative）
+    pub fn matched_conditions(&self, stats: &ExamStats) -> Option<Vec<&PassRule>> {
+        match self {
+            PassRule::SingleExam { min_correct, .. } => (stats.max_correct >= *min_correct).then(|| vec![self]),
+            PassRule::Cumulative { min_total, .. } => (stats.total_correct >= *min_total).then(|| vec![self]),
+            PassRule::All { rules } => {
+                let mut conditions = Vec::new();
+                for rule in rules {
+                    conditions.extend(rule.matched_conditions(stats)?);
+                }
+                Some(conditions)
+            }
+            PassRule::Any { rules } => {
+                let conditions: Vec<&PassRule> = rules
+                    .iter()
+                    .filter_map(|rule| rule.matched_conditions(stats))
+                    .flatten()
+                    .collect();
+                if conditions.is_empty() {
+                    None
+                } else {
+                    Some(conditions)
+                }
+            }
+        }
+    }
+
+    /// 判斷是否通過，通過時回傳成立的條件名稱
+    pub fn evaluate(&self, stats: &ExamStats) -> Option<Vec<String>> {
+        self.matched_conditions(stats)
+            .map(|conditions| conditions.iter().map(|rule| rule.condition_name()).collect())
+    }
+
+    /// 條件名稱，未設定 label 時依門檻產生，例如「一次2題」
+    pub fn condition_name(&self) -> String {
+        match self {
+            PassRule::SingleExam { min_correct, label } => {
+                label.clone().unwrap_or_else(|| format!("一次{}題", min_correct))
+            }
+            PassRule::Cumulative { min_total, label } => {
+                label.clone().unwrap_or_else(|| format!("累計{}題", min_total))
+            }
+            PassRule::All { rules } => rules.iter().map(|rule| rule.condition_name()).collect::<Vec<_>>().join("且"),
+            PassRule::Any { rules } => rules.iter().map(|rule| rule.condition_name()).collect::<Vec<_>>().join("或"),
+        }
+    }
+
+    /// 依考試日期逐場累計，回傳第一次達到通過標準的應考紀錄與當時成立的條件
+    pub fn first_pass<'a>(&self, attempts: &'a [ExamAttempt]) -> Option<(&'a ExamAttempt, Vec<PassRule>)> {
+        let mut stats = ExamStats::default();
+        for attempt in attempts {
+            stats.add(attempt.correct);
+            if let Some(conditions) = self.matched_conditions(&stats) {
+                return Some((attempt, conditions.into_iter().cloned().collect()));
+            }
+        }
+        None
+    }
+
+    /// 計算寫入 StudentInfo 的 IsPassed 與 PassingCriteria
+    pub fn passing_criteria(&self, stats: &ExamStats) -> (bool, Option<String>) {
+        match self.evaluate(stats) {
+            Some(conditions) => (true, Some(conditions.join("且"))),
+            None => (false, None),
+        }
+    }
+
+    /// 檢查規則內容是否合理
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            PassRule::SingleExam { min_correct, .. } if *min_correct <= 0 => {
+                Err(t!(SingleExamThresholdInvalid))
+            }
+            PassRule::Cumulative { min_total, .. } if *min_total <= 0 => {
+                Err(t!(CumulativeThresholdInvalid))
+            }
+            PassRule::All { rules } | PassRule::Any { rules } => {
+                if rules.is_empty() {
+                    return Err(t!(CompositeRuleEmpty));
+                }
+                rules.iter().try_for_each(|rule| rule.validate())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 由學號推算入學年度（民國年）
+/// 一碼英文開頭的學號使用兩碼年份，例如 S05351006 → 105、G09358002 → 109
+/// 兩碼英文開頭的學號使用三碼年份，例如 OU1090059 → 109
+pub fn derive_entrance_year(student_id: &str) -> Option<i32> {
+    let id = student_id.trim();
+    let prefix_len = id.chars().take_while(|c| c.is_ascii_alphabetic()).count();
+    let digits = &id[prefix_len..];
+    let year_len = match prefix_len {
+        1 => 2,
+        2 => 3,
+        _ => return None,
+    };
+    if digits.len() <= year_len || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let year: i32 = digits[..year_len].parse().ok()?;
+    if year_len == 2 && year < 50 {
+        Some(year + 100)
+    } else {
+        Some(year)
+    }
+}
+
+/// 一次應考紀錄，需依考試日期排序後才能找出通過的場次
+#[derive(Debug, Clone, Copy)]
+pub struct ExamAttempt {
+    pub session_sn: i32,
+    pub exam_date: NaiveDate,
+    pub correct: i32, // 答對題數，缺考與請假為 0
+}
+
+/// 學生第一次達到通過標準的場次、日期與當時成立的條件，存放於 StudentInfo
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct PassRecord {
+    pub session_sn: i32,
+    pub exam_date: NaiveDate,
+    pub criteria_sn: Option<i32>, // 適用的 PassCriteria SN，使用預設規則時為 None
+    pub conditions: Vec<PassRule>,
+}
+
+impl PassRecord {
+    /// 由 StudentInfo 的 PassedSession_SN、PassedDate、PassCriteria_SN 與 PassingConditions 組成
+    /// 場次被刪除（PassedSession_SN 被清空）或尚未計算時回傳 None
+    pub fn from_columns(
+        session_sn: Option<i32>,
+        exam_date: Option<NaiveDate>,
+        criteria_sn: Option<i32>,
+        conditions: Option<String>,
+    ) -> Option<Self> {
+        Some(PassRecord {
+            session_sn: session_sn?,
+            exam_date: exam_date?,
+            criteria_sn,
+            conditions: conditions
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// 寫入 PassingConditions 的 JSON
+    pub fn conditions_json(&self) -> String {
+        serde_json::to_string(&self.conditions).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// 報表中顯示的通過時成立的條件，例如「一次兩題且累計3題」
+    pub fn condition_names(&self) -> String {
+        self.conditions.iter().map(PassRule::condition_name).collect::<Vec<_>>().join("且")
+    }
+
+    /// 報表中顯示的通過場次，與上傳成績的場次欄標題相同，例如「2024-05-01,官辦」
+    pub fn session_label(&self, exam_type: Option<&str>) -> String {
+        let date = self.exam_date.format("%Y-%m-%d");
+        match exam_type {
+            Some(exam_type) => format!("{},{}", date, exam_type),
+            None => date.to_string(),
+        }
+    }
+
+    /// 報表中顯示的適用規則：PassCriteria 的名稱，使用預設規則時為「預設規則」，規則已被刪除時為 SN
+    pub fn rule_name(&self, criteria_name: Option<&str>) -> String {
+        match (self.criteria_sn, criteria_name) {
+            (None, _) => "預設規則".to_string(),
+            (Some(_), Some(name)) => name.to_string(),
+            (Some(sn), None) => format!("#{}", sn),
+        }
+    }
+}
+
+/// 依目前規則重新判斷的結果
+#[derive(Debug, Clone)]
+pub struct PassOutcome {
+    pub is_passed: bool,
+    pub passing_criteria: Option<String>,
+    pub record: Option<PassRecord>,
+}
+
+/// 學生實際適用的入學年度：有明確設定時優先，否則由學號推算
+pub fn effective_entrance_year(explicit: Option<i32>, student_id: &str) -> Option<i32> {
+    explicit.or_else(|| derive_entrance_year(student_id))
+}
+
+/// 綁定入學年度區間的通過規則，區間上下限為 NULL 代表不限
+#[derive(Debug, Clone)]
+pub struct CohortRule {
+    pub sn: i32,
+    pub entrance_year_from: Option<i32>,
+    pub entrance_year_to: Option<i32>,
+    pub rule: PassRule,
+}
+
+impl CohortRule {
+    fn is_catch_all(&self) -> bool {
+        self.entrance_year_from.is_none() && self.entrance_year_to.is_none()
+    }
+
+    fn matches(&self, entrance_year: Option<i32>) -> bool {
+        if self.is_catch_all() {
+            return true;
+        }
+        let Some(year) = entrance_year else {
+            return false;
+        };
+        self.entrance_year_from.map_or(true, |from| year >= from)
+            && self.entrance_year_to.map_or(true, |to| year <= to)
+    }
+}
+
+/// 所有啟用中的通過規則
+#[derive(Debug, Clone, Default)]
+pub struct PassRuleSet {
+    rules: Vec<CohortRule>,
+    fallback: PassRule,
+}
+
+impl PassRuleSet {
+    /// 取得指定入學年度適用的規則
+    /// 有綁定年度區間的規則優先於不限年度的規則，同類型時以最新（SN 最大）的為準
+    pub fn rule_for(&self, entrance_year: Option<i32>) -> &PassRule {
+        self.cohort_for(entrance_year)
+            .map(|cohort| &cohort.rule)
+            .unwrap_or(&self.fallback)
+    }
+
+    fn cohort_for(&self, entrance_year: Option<i32>) -> Option<&CohortRule> {
+        self.rules
+            .iter()
+            .filter(|cohort| cohort.matches(entrance_year))
+            .min_by_key(|cohort| (cohort.is_catch_all(), std::cmp::Reverse(cohort.sn)))
+    }
+
+    /// 依學生的應考紀錄判斷是否通過，通過時一併找出第一次達到標準的場次
+    /// attempts 需依考試日期排序
+    pub fn evaluate_history(&self, explicit_year: Option<i32>, student_id: &str, attempts: &[ExamAttempt]) -> PassOutcome {
+        let cohort = self.cohort_for(effective_entrance_year(explicit_year, student_id));
+        let rule = cohort.map(|cohort| &cohort.rule).unwrap_or(&self.fallback);
+        let stats = ExamStats::from_counts(attempts.iter().map(|attempt| attempt.correct));
+        let (is_passed, passing_criteria) = rule.passing_criteria(&stats);
+        let record = if is_passed {
+            rule.first_pass(attempts).map(|(attempt, conditions)| PassRecord {
+                session_sn: attempt.session_sn,
+                exam_date: attempt.exam_date,
+                criteria_sn: cohort.map(|cohort| cohort.sn),
+                conditions,
+            })
+        } else {
+            None
+        };
+        PassOutcome { is_passed, passing_criteria, record }
+    }
+}
+
+/// 讀取所有啟用中的通過規則，若尚未設定則使用預設規則
+/// 可傳入連線池或交易內的連線，在交易內讀取時能看到尚未提交的規則
+pub async fn load_rule_set<'e, E>(executor: E) -> Result<PassRuleSet, Error>
+where
+    E: Executor<'e, Database = MySql>,
+{
+    let rows = sqlx::query(
+        r#"
+        SELECT SN, EntranceYearFrom, EntranceYearTo, RuleDefinition
+        FROM PassCriteria
+        WHERE IsActive = TRUE
+        "#,
+    )
+    .fetch_all(executor)
+    .await?;
+
+    let mut rules = Vec::with_capacity(rows.len());
+    for row in rows {
+        let definition: String = row.try_get("RuleDefinition")?;
+        rules.push(CohortRule {
+            sn: row.try_get("SN")?,
+            entrance_year_from: row.try_get("EntranceYearFrom")?,
+            entrance_year_to: row.try_get("EntranceYearTo")?,
+            rule: serde_json::from_str(&definition).map_err(|e| Error::Decode(Box::new(e)))?,
+        });
+    }
+    Ok(PassRuleSet { rules, fallback: PassRule::default() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn single(min_correct: i32) -> PassRule {
+        PassRule::SingleExam { min_correct, label: None }
+    }
+
+    fn cumulative(min_total: i32) -> PassRule {
+        PassRule::Cumulative { min_total, label: None }
+    }
+
+    fn cumulative_with_label(min_total: i32, label: &str) -> PassRule {
+        PassRule::Cumulative { min_total, label: Some(label.to_string()) }
+    }
+
+    fn stats(counts: &[i32]) -> ExamStats {
+        ExamStats::from_counts(counts.iter().copied())
+    }
+
+    fn attempts(counts: &[i32]) -> Vec<ExamAttempt> {
+        counts
+            .iter()
+            .enumerate()
+            .map(|(i, &correct)| ExamAttempt {
+                session_sn: i as i32 + 1,
+                exam_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap() + chrono::Days::new(i as u64 * 30),
+                correct,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn single_exam_uses_best_session() {
+        let rule = single(2);
+        assert!(rule.evaluate(&stats(&[1, 1, 1])).is_none());
+        assert_eq!(rule.evaluate(&stats(&[0, 2, 1])), Some(vec!["一次2題".to_string()]));
+    }
+
+    #[test]
+    fn cumulative_adds_up_sessions() {
+        let rule = cumulative(3);
+        assert!(rule.evaluate(&stats(&[1, 1])).is_none());
+        assert_eq!(rule.evaluate(&stats(&[1, 1, 1])), Some(vec!["累計3題".to_string()]));
+    }
+
+    #[test]
+    fn default_rule_reports_every_satisfied_condition() {
+        let rule = PassRule::default();
+        assert_eq!(rule.passing_criteria(&stats(&[1, 1])), (false, None));
+        assert_eq!(rule.passing_criteria(&stats(&[2])), (true, Some("一次兩題".to_string())));
+        assert_eq!(rule.passing_criteria(&stats(&[1, 1, 1])), (true, Some("累計3題".to_string())));
+        assert_eq!(rule.passing_criteria(&stats(&[2, 1])), (true, Some("一次兩題且累計3題".to_string())));
+    }
+
+    #[test]
+    fn nested_all_and_any() {
+        // 累計 5 題，且單次 3 題或累計 6 題
+        let rule = PassRule::All {
+            rules: vec![cumulative(5), PassRule::Any { rules: vec![single(3), cumulative(6)] }],
+        };
+        assert!(rule.evaluate(&stats(&[2, 2, 1])).is_none());
+        assert_eq!(
+            rule.evaluate(&stats(&[3, 2])),
+            Some(vec!["累計5題".to_string(), "一次3題".to_string()])
+        );
+        assert_eq!(
+            rule.evaluate(&stats(&[2, 2, 2])),
+            Some(vec!["累計5題".to_string(), "累計6題".to_string()])
+        );
+        let matched = rule.matched_conditions(&stats(&[3, 3])).unwrap();
+        assert_eq!(matched, vec![&cumulative(5), &single(3), &cumulative(6)]);
+    }
+
+    #[test]
+    fn all_fails_when_any_child_fails() {
+        let rule = PassRule::All { rules: vec![single(2), cumulative(10)] };
+        assert!(rule.matched_conditions(&stats(&[4, 4])).is_none());
+    }
+
+    #[test]
+    fn first_pass_stops_at_first_qualifying_session() {
+        let rule = PassRule::default();
+        let history = attempts(&[1, 1, 1, 2]);
+        let (attempt, conditions) = rule.first_pass(&history).unwrap();
+        assert_eq!(attempt.session_sn, 3);
+        assert_eq!(conditions, vec![cumulative_with_label(3, "累計3題")]);
+        assert!(rule.first_pass(&history[..2]).is_none());
+    }
+
+    #[test]
+    fn validate_rejects_malformed_rules() {
+        assert!(PassRule::default().validate().is_ok());
+        assert_eq!(single(0).validate(), Err(t!(SingleExamThresholdInvalid)));
+        assert_eq!(cumulative(-1).validate(), Err(t!(CumulativeThresholdInvalid)));
+        assert_eq!(PassRule::Any { rules: vec![] }.validate(), Err(t!(CompositeRuleEmpty)));
+        let nested = PassRule::Any { rules: vec![single(2), PassRule::All { rules: vec![cumulative(0)] }] };
+        assert_eq!(nested.validate(), Err(t!(CumulativeThresholdInvalid)));
+    }
+
+    #[test]
+    fn rule_definition_json() {
+        let json = r#"{"type":"any","rules":[{"type":"single_exam","min_correct":2,"label":"一次兩題"},{"type":"cumulative","min_total":3,"label":"累計3題"}]}"#;
+        assert_eq!(serde_json::from_str::<PassRule>(json).unwrap(), PassRule::default());
+        assert!(serde_json::from_str::<PassRule>(r#"{"type":"single_exam"}"#).is_err());
+        assert!(serde_json::from_str::<PassRule>(r#"{"type":"majority","rules":[]}"#).is_err());
+    }
+
+    #[test]
+    fn derive_entrance_year_from_student_id() {
+        let cases = [
+            ("S05351006", Some(105)), // 一碼英文，兩碼年份，小於 50 加 100
+            ("G09358002", Some(109)),
+            ("s05351006", Some(105)),
+            ("B49123456", Some(149)),
+            ("B50123456", Some(50)), // 50 以上視為民國 50 年代
+            ("B99123456", Some(99)),
+            ("OU1090059", Some(109)), // 兩碼英文，三碼年份
+            ("OU0990059", Some(99)),
+            (" S05351006 ", Some(105)),
+            ("ABC1090059", None), // 三碼英文無法判斷
+            ("1090059", None),
+            ("S05", None), // 年份之後沒有其他數字
+            ("S0535X006", None),
+            ("", None),
+        ];
+        for (student_id, expected) in cases {
+            assert_eq!(derive_entrance_year(student_id), expected, "{}", student_id);
+        }
+    }
+
+    #[test]
+    fn explicit_entrance_year_takes_precedence() {
+        assert_eq!(effective_entrance_year(Some(110), "S05351006"), Some(110));
+        assert_eq!(effective_entrance_year(None, "S05351006"), Some(105));
+        assert_eq!(effective_entrance_year(None, "X"), None);
+    }
+
+    fn cohort(sn: i32, from: Option<i32>, to: Option<i32>, min_correct: i32) -> CohortRule {
+        CohortRule { sn, entrance_year_from: from, entrance_year_to: to, rule: single(min_correct) }
+    }
+
+    #[test]
+    fn rule_for_selects_cohort() {
+        let rule_set = PassRuleSet {
+            rules: vec![
+                cohort(1, None, None, 10),           // 不限年度
+                cohort(2, Some(105), Some(108), 20), // 105–108
+                cohort(3, Some(107), None, 30),      // 107 之後，與 SN 2 重疊
+                cohort(4, None, Some(100), 40),      // 100 以前
+                cohort(5, None, None, 50),           // 較新的不限年度規則
+            ],
+            fallback: PassRule::default(),
+        };
+        let cases = [
+            (Some(104), single(50)), // 只符合不限年度的規則，取 SN 較大的
+            (Some(105), single(20)), // 有綁定年度的規則優先於不限年度
+            (Some(106), single(20)),
+            (Some(107), single(30)), // 重疊時取 SN 較大的
+            (Some(108), single(30)),
+            (Some(120), single(30)),
+            (Some(100), single(40)),
+            (Some(95), single(40)),
+            (None, single(50)), // 無法判斷入學年度時只使用不限年度的規則
+        ];
+        for (entrance_year, expected) in cases {
+            assert_eq!(rule_set.rule_for(entrance_year), &expected, "{:?}", entrance_year);
+        }
+    }
+
+    #[test]
+    fn rule_for_falls_back_to_default() {
+        let rule_set = PassRuleSet { rules: vec![cohort(1, Some(105), Some(105), 20)], fallback: PassRule::default() };
+        assert_eq!(rule_set.rule_for(Some(105)), &single(20));
+        assert_eq!(rule_set.rule_for(Some(106)), &PassRule::default());
+        assert_eq!(rule_set.rule_for(None), &PassRule::default());
+        assert_eq!(PassRuleSet::default().rule_for(Some(105)), &PassRule::default());
+    }
+
+    #[test]
+    fn evaluate_history_records_first_passing_session() {
+        let rule_set = PassRuleSet { rules: vec![cohort(7, Some(105), Some(105), 2)], fallback: PassRule::default() };
+        let history = attempts(&[1, 2, 3]);
+        let outcome = rule_set.evaluate_history(None, "S05351006", &history);
+        assert!(outcome.is_passed);
+        assert_eq!(outcome.passing_criteria, Some("一次2題".to_string()));
+        // 第三場也達到標準，但記錄的是第一次通過的第二場
+        assert_eq!(
+            outcome.record,
+            Some(PassRecord {
+                session_sn: 2,
+                exam_date: history[1].exam_date,
+                criteria_sn: Some(7),
+                conditions: vec![single(2)],
+            })
+        );
+    }
+
+    #[test]
+    fn evaluate_history_uses_conditions_met_at_the_qualifying_session() {
+        // 入學年度 106 沒有對應的規則，使用預設規則
+        let rule_set = PassRuleSet { rules: vec![cohort(7, Some(105), Some(105), 2)], fallback: PassRule::default() };
+        let history = attempts(&[1, 1, 1, 2]);
+        let outcome = rule_set.evaluate_history(None, "S06351006", &history);
+        // PassingCriteria 依所有場次判斷，通過紀錄只記錄第三場累計達到 3 題時成立的條件
+        assert_eq!(outcome.passing_criteria, Some("一次兩題且累計3題".to_string()));
+        let record = outcome.record.unwrap();
+        assert_eq!((record.session_sn, record.exam_date, record.criteria_sn), (3, history[2].exam_date, None));
+        assert_eq!(record.conditions, vec![cumulative_with_label(3, "累計3題")]);
+
+        // 明確設定的入學年度優先於學號推算
+        let outcome = rule_set.evaluate_history(Some(105), "S06351006", &history);
+        let record = outcome.record.unwrap();
+        assert_eq!((record.session_sn, record.criteria_sn), (4, Some(7)));
+    }
+
+    #[test]
+    fn evaluate_history_without_passing_session() {
+        let rule_set = PassRuleSet::default();
+        for counts in [&[][..], &[0], &[1, 1], &[1, 0, 0]] {
+            let outcome = rule_set.evaluate_history(None, "S05351006", &attempts(counts));
+            assert!(!outcome.is_passed, "{:?}", counts);
+            assert_eq!(outcome.passing_criteria, None);
+            assert_eq!(outcome.record, None);
+        }
+    }
+
+    #[test]
+    fn pass_record_round_trips_through_columns() {
+        let record = PassRecord {
+            session_sn: 12,
+            exam_date: NaiveDate::from_ymd_opt(2024, 10, 15).unwrap(),
+            criteria_sn: None,
+            conditions: vec![cumulative_with_label(3, "累計3題")],
+        };
+        let stored = PassRecord::from_columns(Some(12), Some(record.exam_date), None, Some(record.conditions_json()));
+        assert_eq!(stored, Some(record.clone()));
+        assert_eq!(record.condition_names(), "累計3題");
+        assert_eq!(record.session_label(Some("官辦")), "2024-10-15,官辦");
+        assert_eq!(record.rule_name(Some("舊規則")), "預設規則");
+        let record = PassRecord { criteria_sn: Some(7), ..record };
+        assert_eq!(record.rule_name(Some("110 學年度起")), "110 學年度起");
+        assert_eq!(record.rule_name(None), "#7");
+        // 通過的場次被刪除時 PassedSession_SN 被清空
+        assert_eq!(PassRecord::from_columns(None, Some(record.exam_date), None, Some(record.conditions_json())), None);
+        assert_eq!(PassRecord::from_columns(Some(12), None, None, None), None);
+    }
+}
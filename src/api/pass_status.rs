@@ -0,0 +1,401 @@
+use serde::{Deserialize, Serialize};
+use sqlx::mysql::{MySqlConnection, MySqlPool, MySqlRow};
+use sqlx::{Error, Row};
+use std::collections::HashMap;
+use std::time::Duration;
+use crate::api::audit::{snapshot_all, write_audit_log_as, AuditActor, AuditTarget};
+use crate::api::pass_rule::{effective_entrance_year, load_rule_set, ExamAttempt, PassRecord, PassRuleSet};
+use utoipa::ToSchema;
+
+/// 每次 UPDATE 寫入的學生數，避免單一語句的參數過多
+const UPDATE_CHUNK_SIZE: usize = 500;
+
+/// 重新計算多位學生時每個交易處理的學生數，只鎖住這一批學生，不會在整個執行期間擋住寫入成績的交易
+const RECOMPUTE_BATCH_SIZE: usize = 200;
+
+/// 背景工作寫入稽核紀錄時的操作者
+const SYSTEM_USERNAME: &str = "system";
+
+/// 重新計算的範圍，未指定的條件不限制
+#[derive(Deserialize, Debug, Default, ToSchema)]
+pub struct RecomputeFilter {
+    #[serde(default)]
+    pub student_ids: Vec<String>, // 空白時為所有學生
+    pub entrance_year_from: Option<i32>, // 入學年度（民國年），未填入學年度時由學號推算
+    pub entrance_year_to: Option<i32>,
+}
+
+impl RecomputeFilter {
+    /// 只重新計算指定的學生
+    pub fn students(student_ids: Vec<String>) -> Self {
+        RecomputeFilter { student_ids, ..Default::default() }
+    }
+
+    fn matches_year(&self, entrance_year: Option<i32>) -> bool {
+        if self.entrance_year_from.is_none() && self.entrance_year_to.is_none() {
+            return true;
+        }
+        let Some(year) = entrance_year else {
+            return false;
+        };
+        self.entrance_year_from.map_or(true, |from| year >= from) && self.entrance_year_to.map_or(true, |to| year <= to)
+    }
+}
+
+/// 通過狀態有變動的學生
+#[derive(Serialize, Debug, ToSchema)]
+pub struct StatusChange {
+    pub student_id: String,
+    pub name: String,
+    pub was_passed: bool,
+    pub is_passed: bool,
+    pub previous_criteria: Option<String>,
+    pub passing_criteria: Option<String>,
+    pub pass_record: Option<PassRecord>, // 新的通過場次與條件，未通過時為 None
+}
+
+/// StudentInfo 中目前儲存的通過狀態
+struct StoredStatus {
+    student_id: String,
+    name: String,
+    explicit_year: Option<i32>,
+    was_passed: bool,
+    previous_criteria: Option<String>,
+    previous_record: Option<PassRecord>,
+}
+
+impl StoredStatus {
+    fn from_row(row: &MySqlRow) -> Result<Self, Error> {
+        Ok(StoredStatus {
+            student_id: row.try_get("StudentID")?,
+            name: row.try_get("Name")?,
+            explicit_year: row.try_get("EntranceYear")?,
+            was_passed: row.try_get::<Option<bool>, _>("IsPassed")?.unwrap_or(false),
+            previous_criteria: row.try_get("PassingCriteria")?,
+            previous_record: PassRecord::from_columns(
+                row.try_get("PassedSession_SN")?,
+                row.try_get("PassedDate")?,
+                row.try_get("PassCriteria_SN")?,
+                row.try_get("PassingConditions")?,
+            ),
+        })
+    }
+}
+
+/// 以依日期排序的應考紀錄重新判斷一位學生，通過狀態、條件與通過紀錄都沒有變動時回傳 None
+fn status_change(rule_set: &PassRuleSet, stored: StoredStatus, attempts: &[ExamAttempt]) -> Option<StatusChange> {
+    let outcome = rule_set.evaluate_history(stored.explicit_year, &stored.student_id, attempts);
+    if stored.was_passed == outcome.is_passed
+        && stored.previous_criteria == outcome.passing_criteria
+        && stored.previous_record == outcome.record
+    {
+        return None;
+    }
+    Some(StatusChange {
+        student_id: stored.student_id,
+        name: stored.name,
+        was_passed: stored.was_passed,
+        is_passed: outcome.is_passed,
+        previous_criteria: stored.previous_criteria,
+        passing_criteria: outcome.passing_criteria,
+        pass_record: outcome.record,
+    })
+}
+
+#[derive(Serialize, Debug, Default, ToSchema)]
+pub struct RecomputeReport {
+    pub checked: usize, // 符合條件的學生數
+    pub changed: Vec<StatusChange>,
+    pub dry_run: bool, // 為 true 時只回報變動，未寫入資料庫
+}
+
+/// 依目前啟用的規則重新判斷 IsPassed、PassingCriteria 與通過紀錄
+/// 先取得學號清單，再分批在各自的交易內鎖定學生後重新計算並寫入
+pub async fn recompute_pass_status(
+    db_pool: &MySqlPool,
+    actor: &AuditActor,
+    filter: &RecomputeFilter,
+    dry_run: bool,
+) -> Result<RecomputeReport, Error> {
+    let student_ids: Vec<String> = if filter.student_ids.is_empty() {
+        sqlx::query_scalar("SELECT StudentID FROM StudentInfo ORDER BY StudentID")
+            .fetch_all(db_pool)
+            .await?
+    } else {
+        filter.student_ids.clone()
+    };
+
+    let mut report = RecomputeReport { dry_run, ..Default::default() };
+    for chunk in student_ids.chunks(RECOMPUTE_BATCH_SIZE) {
+        let batch = RecomputeFilter {
+            student_ids: chunk.to_vec(),
+            entrance_year_from: filter.entrance_year_from,
+            entrance_year_to: filter.entrance_year_to,
+        };
+        let mut tx = db_pool.begin().await?;
+        let rule_set = load_rule_set(&mut *tx).await?;
+        let batch_report = recompute_students(&mut tx, actor, &rule_set, &batch, dry_run).await?;
+        tx.commit().await?;
+        report.checked += batch_report.checked;
+        report.changed.extend(batch_report.changed);
+    }
+    Ok(report)
+}
+
+/// 以一次查詢取得學生資料、一次查詢取得依日期排序的應考紀錄，找出每位學生通過的場次
+/// 只有變動的學生會被寫回，並以批次 UPDATE 寫入，每位變動的學生各寫入一筆稽核紀錄
+/// 學生以 FOR UPDATE、應考紀錄以 FOR SHARE 讀取，同時寫入同一位學生成績的交易會等待此交易提交，
+/// 不會各自依缺少對方資料的快照計算後互相覆蓋；不提交交易，由呼叫端決定提交或回滾
+pub async fn recompute_students(
+    conn: &mut MySqlConnection,
+    actor: &AuditActor,
+    rule_set: &PassRuleSet,
+    filter: &RecomputeFilter,
+    dry_run: bool,
+) -> Result<RecomputeReport, Error> {
+    let student_ids: Vec<String> = filter.student_ids.iter().map(|id| id.to_ascii_uppercase()).collect();
+    let filter_by = |column: &str| {
+        if student_ids.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {} IN ({})", column, vec!["?"; student_ids.len()].join(", "))
+        }
+    };
+
+    let query = format!(
+        r#"
+        SELECT StudentID, Name, EntranceYear, IsPassed, PassingCriteria,
+            PassedSession_SN, PassedDate, PassCriteria_SN, PassingConditions
+        FROM StudentInfo
+        {}
+        FOR UPDATE
+        "#,
+        filter_by("StudentID")
+    );
+    let mut students = sqlx::query(&query);
+    for student_id in &student_ids {
+        students = students.bind(student_id);
+    }
+    let students = students.fetch_all(&mut *conn).await?;
+
+    let query = format!(
+        r#"
+        SELECT ea.StudentID, ea.ExamSession_SN, es.ExamDate,
+            CAST(COALESCE(ea.CorrectAnswersCount, 0) AS SIGNED) AS CorrectAnswersCount
+        FROM ExamAttendance ea
+        JOIN ExamSessions es ON es.SN = ea.ExamSession_SN
+        {}
+        ORDER BY es.ExamDate, es.SN
+        FOR SHARE
+        "#,
+        filter_by("ea.StudentID")
+    );
+    let mut attendance = sqlx::query(&query);
+    for student_id in &student_ids {
+        attendance = attendance.bind(student_id);
+    }
+    let mut history: HashMap<String, Vec<ExamAttempt>> = HashMap::new();
+    for row in attendance.fetch_all(&mut *conn).await? {
+        // 與 StudentInfo 的學號大小寫可能不同，統一轉成大寫比對
+        let student_id = row.try_get::<String, _>("StudentID")?.to_ascii_uppercase();
+        history.entry(student_id).or_default().push(ExamAttempt {
+            session_sn: row.try_get("ExamSession_SN")?,
+            exam_date: row.try_get("ExamDate")?,
+            correct: row.try_get::<i64, _>("CorrectAnswersCount")? as i32,
+        });
+    }
+
+    let mut report = RecomputeReport { dry_run, ..Default::default() };
+    for row in students {
+        let stored = StoredStatus::from_row(&row)?;
+        if !filter.matches_year(effective_entrance_year(stored.explicit_year, &stored.student_id)) {
+            continue;
+        }
+        report.checked += 1;
+        let attempts = history.get(&stored.student_id.to_ascii_uppercase()).map(Vec::as_slice).unwrap_or_default();
+        report.changed.extend(status_change(rule_set, stored, attempts));
+    }
+
+    if dry_run {
+        return Ok(report);
+    }
+    let targets = report.changed.iter().map(|change| AuditTarget::Student(&change.student_id)).collect();
+    let snapshots = snapshot_all(&mut *conn, targets).await?;
+    for chunk in report.changed.chunks(UPDATE_CHUNK_SIZE) {
+        let values = vec![
+            "SELECT ? AS StudentID, ? AS IsPassed, ? AS PassingCriteria, ? AS PassedSession_SN, ? AS PassedDate, ? AS PassCriteria_SN, ? AS PassingConditions";
+            chunk.len()
+        ]
+        .join(" UNION ALL ");
+        let query = format!(
+            r#"
+            UPDATE StudentInfo si
+            JOIN ({}) AS v ON v.StudentID = si.StudentID
+            SET si.IsPassed = v.IsPassed, si.PassingCriteria = v.PassingCriteria,
+                si.PassedSession_SN = v.PassedSession_SN, si.PassedDate = v.PassedDate,
+                si.PassCriteria_SN = v.PassCriteria_SN, si.PassingConditions = v.PassingConditions
+            "#,
+            values
+        );
+        let mut update = sqlx::query(&query);
+        for change in chunk {
+            let record = change.pass_record.as_ref();
+            update = update
+                .bind(&change.student_id)
+                .bind(change.is_passed)
+                .bind(&change.passing_criteria)
+                .bind(record.map(|record| record.session_sn))
+                .bind(record.map(|record| record.exam_date))
+                .bind(record.and_then(|record| record.criteria_sn))
+                .bind(record.map(PassRecord::conditions_json));
+        }
+        update.execute(&mut *conn).await?;
+    }
+    for (target, before) in snapshots {
+        write_audit_log_as(&mut *conn, actor, &target, before).await?;
+    }
+    Ok(report)
+}
+
+/// 有已通過卻沒有通過紀錄的學生時重新計算所有學生，例如剛套用 0007 遷移，或通過的場次已被刪除
+pub async fn backfill_pass_records(db_pool: &MySqlPool) -> Result<Option<RecomputeReport>, Error> {
+    let missing: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM StudentInfo WHERE IsPassed = TRUE AND PassedSession_SN IS NULL",
+    )
+    .fetch_one(db_pool)
+    .await?;
+    if missing == 0 {
+        return Ok(None);
+    }
+    let actor = AuditActor::new(SYSTEM_USERNAME, "backfill_pass_records");
+    recompute_pass_status(db_pool, &actor, &RecomputeFilter::default(), false).await.map(Some)
+}
+
+/// 背景定期重新計算所有學生的通過狀態，修正因刪除成績等操作而過時的 IsPassed
+/// 間隔由 `PASS_STATUS_RECOMPUTE_HOURS` 設定，預設 24 小時，設為 0 關閉定期執行
+/// 啟動時一律先補上缺少的通過紀錄
+pub fn spawn_recompute_job(db_pool: MySqlPool) {
+    let hours = std::env::var("PASS_STATUS_RECOMPUTE_HOURS")
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .unwrap_or(24);
+    tokio::spawn(async move {
+        match backfill_pass_records(&db_pool).await {
+            Ok(Some(report)) => log_report(&report),
+            Ok(None) => {}
+            Err(err) => eprintln!("補上通過紀錄失敗: {:?}", err),
+        }
+        if hours == 0 {
+            return;
+        }
+        // 啟動時不立即執行，避免每次重新啟動都掃描一次
+        let period = Duration::from_secs(hours * 60 * 60);
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        loop {
+            interval.tick().await;
+            let actor = AuditActor::new(SYSTEM_USERNAME, "spawn_recompute_job");
+            match recompute_pass_status(&db_pool, &actor, &RecomputeFilter::default(), false).await {
+                Ok(report) => log_report(&report),
+                Err(err) => eprintln!("重新計算通過狀態失敗: {:?}", err),
+            }
+        }
+    });
+}
+
+fn log_report(report: &RecomputeReport) {
+    for change in &report.changed {
+        println!(
+            "通過狀態已更新: {} {} {} -> {}",
+            change.student_id, change.name, change.was_passed, change.is_passed
+        );
+    }
+    println!("已重新計算 {} 位學生的通過狀態，{} 位有變動", report.checked, report.changed.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::pass_rule::PassRule;
+    use chrono::NaiveDate;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    /// 依日期排序的應考紀錄：(場次SN, 月, 日, 答對題數)
+    fn history(attempts: &[(i32, u32, u32, i32)]) -> Vec<ExamAttempt> {
+        attempts
+            .iter()
+            .map(|&(session_sn, month, day, correct)| ExamAttempt { session_sn, exam_date: date(month, day), correct })
+            .collect()
+    }
+
+    fn stored(was_passed: bool, previous_criteria: Option<&str>, previous_record: Option<PassRecord>) -> StoredStatus {
+        StoredStatus {
+            student_id: "S05351006".to_string(),
+            name: "王小明".to_string(),
+            explicit_year: None,
+            was_passed,
+            previous_criteria: previous_criteria.map(str::to_string),
+            previous_record,
+        }
+    }
+
+    fn single_exam_record(session_sn: i32, exam_date: NaiveDate) -> PassRecord {
+        PassRecord {
+            session_sn,
+            exam_date,
+            criteria_sn: None,
+            conditions: vec![PassRule::SingleExam { min_correct: 2, label: Some("一次兩題".to_string()) }],
+        }
+    }
+
+    #[test]
+    fn new_pass_records_first_qualifying_session() {
+        let attempts = history(&[(3, 3, 1, 0), (5, 5, 1, 2), (8, 9, 1, 4)]);
+        let change = status_change(&PassRuleSet::default(), stored(false, None, None), &attempts).unwrap();
+        assert!(!change.was_passed && change.is_passed);
+        assert_eq!(change.passing_criteria, Some("一次兩題且累計3題".to_string()));
+        assert_eq!(change.pass_record, Some(single_exam_record(5, date(5, 1))));
+    }
+
+    #[test]
+    fn unchanged_status_is_not_reported() {
+        let attempts = history(&[(3, 3, 1, 0), (5, 5, 1, 2)]);
+        let current = stored(true, Some("一次兩題"), Some(single_exam_record(5, date(5, 1))));
+        assert!(status_change(&PassRuleSet::default(), current, &attempts).is_none());
+        assert!(status_change(&PassRuleSet::default(), stored(false, None, None), &history(&[(3, 3, 1, 1)])).is_none());
+    }
+
+    #[test]
+    fn moved_qualifying_session_is_reported() {
+        // 通過的場次被刪除後仍由較晚的場次通過，IsPassed 與 PassingCriteria 不變但通過紀錄不同
+        let attempts = history(&[(8, 9, 1, 2)]);
+        let current = stored(true, Some("一次兩題"), None);
+        let change = status_change(&PassRuleSet::default(), current, &attempts).unwrap();
+        assert!(change.was_passed && change.is_passed);
+        assert_eq!(change.previous_criteria, change.passing_criteria);
+        assert_eq!(change.pass_record, Some(single_exam_record(8, date(9, 1))));
+    }
+
+    #[test]
+    fn lost_pass_clears_record() {
+        let current = stored(true, Some("一次兩題"), Some(single_exam_record(5, date(5, 1))));
+        let change = status_change(&PassRuleSet::default(), current, &history(&[(3, 3, 1, 1)])).unwrap();
+        assert!(change.was_passed && !change.is_passed);
+        assert_eq!(change.passing_criteria, None);
+        assert_eq!(change.pass_record, None);
+    }
+
+    #[test]
+    fn filter_matches_entrance_year_range() {
+        let filter = RecomputeFilter { entrance_year_from: Some(105), entrance_year_to: Some(107), ..Default::default() };
+        let cases = [(Some(104), false), (Some(105), true), (Some(107), true), (Some(108), false), (None, false)];
+        for (year, expected) in cases {
+            assert_eq!(filter.matches_year(year), expected, "{:?}", year);
+        }
+        assert!(RecomputeFilter::default().matches_year(None));
+        let from_only = RecomputeFilter { entrance_year_from: Some(105), ..Default::default() };
+        assert!(from_only.matches_year(Some(120)) && !from_only.matches_year(Some(104)));
+    }
+}
@@ -1,20 +1,22 @@
 use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use crate::api::columns::read_student_ids;
+use crate::api::upload::{read_upload, SPREADSHEET_EXTENSIONS, SheetSelection};
+use crate::api::export::{ExportColumn, ExportFormat, ExportQuery, ExportTable};
+use crate::api::download::DownloadStore;
 use actix_multipart::Multipart;
 use actix_session::Session;
 use actix_web::{post, web, HttpRequest, HttpResponse};
-use calamine::DataType;
-use calamine::Reader;
 use chrono::NaiveDate;
-use futures_util::StreamExt as _;
 use sqlx::MySqlPool;
 use sqlx::Row;
-use std::fs::File;
-use std::io::Write;
-use xlsxwriter::Workbook;
-use base64::Engine as _;
 use serde::Serialize;
+use utoipa::ToSchema;
+use crate::api::openapi::UploadForm;
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct AbsentResult {
     student_id: String,
     absent_status: String,
@@ -23,89 +25,44 @@ struct AbsentResult {
     notes: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
+#[schema(as = SearchAbsentResponse)]
 struct ApiResponse {
     results: Vec<AbsentResult>,
-    excel_file: String, // base64 編碼的 Excel 檔案
+    download_url: String, // 下載 Excel 檔案的網址，短時間內有效
 }
+const ABSENT_COLUMNS: [ExportColumn; 5] = [
+    ExportColumn { key: "student_id", title: "學號" },
+    ExportColumn { key: "absent_status", title: "缺考/請假" },
+    ExportColumn { key: "exam_date", title: "考試日期" },
+    ExportColumn { key: "exam_type", title: "考試種類" },
+    ExportColumn { key: "notes", title: "備註" },
+];
+
+#[utoipa::path(
+    tag = "scores",
+    request_body(content = UploadForm, content_type = "multipart/form-data"),
+    responses((status = 200, description = "上傳學號清單，查詢缺考與請假紀錄；帶 format 時直接回傳檔案", body = ApiResponse)),
+)]
 #[post("/api/excel_search_absent")]
 async fn excel_search_absent(
     mut payload: Multipart,
     req: HttpRequest,
     session: Session,
     db_pool: web::Data<MySqlPool>,
-) -> HttpResponse {
-    if !is_authorization(req, session) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
-
-    let temp_filepath = "./uploads/temp_file.xlsx";
-    //儲存上傳的檔案
-    while let Some(Ok(field)) = payload.next().await {
-        let content_disposition = field.content_disposition();
-        if let Some(filename) = content_disposition.and_then(|cd| cd.get_filename()) {
-            let file_ext = std::path::Path::new(filename)
-                .extension()
-                .and_then(|s| s.to_str());
-            if file_ext != Some("xlsx") {
-                return HttpResponse::BadRequest().body("請上傳xlsx檔案");
-            }
-            let mut f = File::create(temp_filepath).expect("Failed to create file");
-            let mut field_stream = field;
-            while let Some(chunk) = field_stream.next().await {
-                let data = chunk.expect("Error reading chunk");
-                f.write_all(&data).expect("Error writing chunk");
-            }
-        }
-    }
+    selection: web::Query<SheetSelection>,
+    export: web::Query<ExportQuery>,
+    downloads: web::Data<DownloadStore>,
+) -> Result<HttpResponse, ApiError> {
+    let format = ExportFormat::negotiate(&export, req.headers());
+    is_authorization(req, session.clone(), Role::ReadOnly)?;
 
-    if !std::path::Path::new(temp_filepath).exists() {
-        println!("File does not exist: {}", temp_filepath);
-        return HttpResponse::InternalServerError().body("Failed to process file");
-    }
-    //解析上傳的檔案，取得學號的list
-    let mut workbook = match calamine::open_workbook_auto(temp_filepath) {
-        Ok(wb) => wb,
-        Err(err) => {
-            println!("Failed to open Excel file: {}", err);
-            return HttpResponse::InternalServerError().body("無效的 Excel file");
-        }
-    };
-
-    let range = match workbook.worksheet_range("工作表1") {
-        Ok(range) => range,
-        Err(err) => {
-            println!("Error reading sheet: {}", err);
-            return HttpResponse::BadRequest().body("請將需要查詢的資料放入工作表1");
-        }
-    };
-    let header_row = range.rows().next().unwrap();
-    let id_col_index = header_row.iter().position(|cell| {
-        if let Some(value) = cell.get_string() {
-            value == "學號"
-        } else {
-            false
-        }
-    });
-
-    let mut student_ids: Vec<String> = Vec::new();
-    if let Some(col_index) = id_col_index {
-        // 遍歷資料列，篩選符合條件的學號
-        student_ids = range
-            .rows()
-            .skip(1) // 跳過標題列
-            .filter_map(|row| row.get(col_index)) // 取出對應列的資料
-            .filter_map(|cell| cell.get_string()) // 只取字串
-            .map(|s| s.to_string())
-            .collect();
-    } else {
-        println!("未找到 '學號' 標題");
-        return HttpResponse::BadRequest()
-            .body("請將學號那欄(column)的第一列(row)的標題改為 '學號'");
-    }
+    let upload = read_upload(&mut payload, &SPREADSHEET_EXTENSIONS).await?;
+    let sheets = upload.read_sheets(&selection)?;
+    let student_ids = read_student_ids(&sheets)?;
 
     if student_ids.is_empty() {
-        return HttpResponse::BadRequest().body("沒有找到任何有效的學號");
+        return Err(ApiError::invalid_input(t!(NoValidStudentIdFound)));
     }
     let query = r#"
     SELECT ea.IsExcused , es.ExamDate, es.ExamType, ea.Notes
@@ -126,11 +83,11 @@ async fn excel_search_absent(
         {
             Ok(Some(row)) => {
                 // 如果查到資料
-                let is_excused: bool = row.try_get("IsExcused").expect("Failed to get IsExcused");
+                let is_excused: bool = row.try_get("IsExcused")?;
                 let absent_status = if is_excused { "請假".to_string() } else { "缺考".to_string()};
-                let exam_date: NaiveDate = row.try_get("ExamDate").expect("Failed to get ExamDate");
-                let exam_type: String = row.try_get("ExamType").expect("Failed to get ExamType");
-                let notes: Option<String> = row.try_get("Notes").expect("Failed to get Notes");
+                let exam_date: NaiveDate = row.try_get("ExamDate")?;
+                let exam_type: String = row.try_get("ExamType")?;
+                let notes: Option<String> = row.try_get("Notes")?;
                 results.push(AbsentResult {
                     student_id: student_id.to_string(),
                     absent_status,
@@ -144,59 +101,34 @@ async fn excel_search_absent(
             }
             Err(err) => {
                 // 如果查詢過程中發生錯誤
-                return HttpResponse::InternalServerError().body(format!("查詢失敗: {}", err));
+                return Err(ApiError::database(t!(QueryFailed), err));
             }
         }
     }
 
-    let output_filepath = "./uploads/result_file.xlsx";
-    let workbook = Workbook::new(output_filepath).expect("Failed to create workbook");
-    let mut worksheet = workbook.add_worksheet(None).unwrap();
-
-    worksheet.write_string(0, 0, "學號", None).unwrap();
-    worksheet.write_string(0, 1, "缺考/請假", None).unwrap();
-    worksheet.write_string(0, 2, "考試日期", None).unwrap();
-    worksheet.write_string(0, 3, "考試種類", None).unwrap();
-    worksheet.write_string(0, 4, "備註", None).unwrap();
-
-    for (i, result) in results.iter().enumerate() {
-        worksheet
-            .write_string(i as u32 + 1, 0, &result.student_id, None)
-            .unwrap();
-        worksheet
-            .write_string(i as u32 + 1, 1, &result.absent_status, None)
-            .unwrap();
-        worksheet
-            .write_string(i as u32 + 1, 2, &result.exam_date, None)
-            .unwrap();
-        worksheet
-            .write_string(i as u32 + 1, 3, &result.exam_type, None)
-            .unwrap();
-        worksheet
-            .write_string(i as u32 + 1, 4, &result.notes.clone().unwrap_or_default(), None)
-            .unwrap();
+    let mut table = ExportTable::new(&ABSENT_COLUMNS);
+    for result in results.iter() {
+        table.push(vec![
+            result.student_id.as_str().into(),
+            result.absent_status.as_str().into(),
+            result.exam_date.as_str().into(),
+            result.exam_type.as_str().into(),
+            result.notes.clone().into(),
+        ]);
+    }
+    if let Some(format) = format {
+        return table.respond(format, "search_absent");
     }
 
-    workbook.close().unwrap();
-
-    // 讀取 Excel 檔案並轉為 base64
-    let excel_file_data = match std::fs::read(output_filepath) {
-        Ok(data) => data,
-        Err(err) => {
-            println!("Error reading generated file: {}", err);
-            return HttpResponse::InternalServerError()
-                .body("Failed to generate or retrieve result Excel file");
-        }
-    };
-    let excel_file_base64 = base64::engine::general_purpose::STANDARD.encode(&excel_file_data);
-
+    // 生成 Excel 檔案並提供下載網址
+    let download_url = downloads.register(&session, &table, ExportFormat::Xlsx, "search_absent")?;
     // 構建 JSON 響應
     let response = ApiResponse {
         results,
-        excel_file: excel_file_base64,
+        download_url,
     };
 
-    HttpResponse::Ok()
+    Ok(HttpResponse::Ok()
         .content_type("application/json")
-        .json(response)
+        .json(response))
 }
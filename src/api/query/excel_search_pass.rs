@@ -1,127 +1,111 @@
 use actix_multipart::Multipart;
 use actix_session::Session;
 use actix_web::{post, web, HttpRequest, HttpResponse};
-use calamine::{DataType, Reader};
-use futures_util::StreamExt as _;
 use serde::Serialize;
 use sqlx::{MySqlPool, Row};
-use std::fs::File;
-use std::io::Write;
-use xlsxwriter::Workbook;
-use base64::Engine as _; // 用於 base64 編碼
 use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::columns::read_student_ids;
+use crate::api::upload::{read_upload, SPREADSHEET_EXTENSIONS, SheetSelection};
+use crate::api::export::{ExportColumn, ExportFormat, ExportQuery, ExportTable};
+use crate::api::download::DownloadStore;
+use crate::api::pass_rule::PassRecord;
+use chrono::NaiveDate;
+use utoipa::ToSchema;
+use crate::api::openapi::UploadForm;
 
 // 定義用於 JSON 序列化的結構體
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
+#[schema(as = SearchPassResult)]
 struct StudentResult {
     student_id: String,
     name: String,
     total_correct_answers: u16,
     max_correct_answers: u8,
     passed: bool,
+    passed_date: Option<NaiveDate>, // 第一次達到通過標準的考試日期
+    passed_session: Option<String>, // 通過的場次，例如 "2024-05-01,官辦"
+    pass_rule: Option<String>, // 適用的通過規則名稱
+    passing_criteria: Option<String>,
+    qualifying_conditions: Option<String>, // 在通過的場次成立的條件
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
+#[schema(as = SearchPassResponse)]
 struct ApiResponse {
     results: Vec<StudentResult>,
-    excel_file: String, // base64 編碼的 Excel 檔案
+    download_url: String, // 下載 Excel 檔案的網址，短時間內有效
 }
 
+const PASS_COLUMNS: [ExportColumn; 10] = [
+    ExportColumn { key: "student_id", title: "學號" },
+    ExportColumn { key: "name", title: "姓名" },
+    ExportColumn { key: "total_correct_answers", title: "累計題數" },
+    ExportColumn { key: "max_correct_answers", title: "最高題數" },
+    ExportColumn { key: "passed", title: "是否通過" },
+    ExportColumn { key: "passed_date", title: "通過日期" },
+    ExportColumn { key: "passed_session", title: "通過場次" },
+    ExportColumn { key: "pass_rule", title: "適用規則" },
+    ExportColumn { key: "passing_criteria", title: "通過條件" },
+    ExportColumn { key: "qualifying_conditions", title: "通過時成立的條件" },
+];
+
+#[utoipa::path(
+    tag = "students",
+    request_body(content = UploadForm, content_type = "multipart/form-data"),
+    responses((status = 200, description = "上傳學號清單，查詢是否通過；帶 format 時直接回傳檔案", body = ApiResponse)),
+)]
 #[post("/api/excel_search_pass")]
 async fn excel_search_pass(
     mut payload: Multipart,
     req: HttpRequest,
     session: Session,
     db_pool: web::Data<MySqlPool>,
-) -> HttpResponse {
-    if !is_authorization(req, session) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
-
-    let temp_filepath = "./uploads/temp_file.xlsx";
-    // 儲存上傳的檔案
-    while let Some(Ok(field)) = payload.next().await {
-        let content_disposition = field.content_disposition();
-        if let Some(filename) = content_disposition.and_then(|cd| cd.get_filename()) {
-            let file_ext = std::path::Path::new(filename)
-                .extension()
-                .and_then(|s| s.to_str());
-            if file_ext != Some("xlsx") {
-                return HttpResponse::BadRequest().body("請上傳xlsx檔案");
-            }
-            let mut f = File::create(temp_filepath).expect("Failed to create file");
-            let mut field_stream = field;
-            while let Some(chunk) = field_stream.next().await {
-                let data = chunk.expect("Error reading chunk");
-                f.write_all(&data).expect("Error writing chunk");
-            }
-        }
-    }
-
-    if !std::path::Path::new(temp_filepath).exists() {
-        println!("File does not exist: {}", temp_filepath);
-        return HttpResponse::InternalServerError().body("Failed to process file");
-    }
-
-    // 解析上傳的檔案，取得學號的list
-    let mut workbook = match calamine::open_workbook_auto(temp_filepath) {
-        Ok(wb) => wb,
-        Err(err) => {
-            println!("Failed to open Excel file: {}", err);
-            return HttpResponse::InternalServerError().body("無效的 Excel file");
-        }
-    };
-
-    let range = match workbook.worksheet_range("工作表1") {
-        Ok(range) => range,
-        Err(err) => {
-            println!("Error reading sheet: {}", err);
-            return HttpResponse::BadRequest().body("請將需要查詢的資料放入工作表1");
-        }
-    };
+    selection: web::Query<SheetSelection>,
+    export: web::Query<ExportQuery>,
+    downloads: web::Data<DownloadStore>,
+) -> Result<HttpResponse, ApiError> {
+    let format = ExportFormat::negotiate(&export, req.headers());
+    is_authorization(req, session.clone(), Role::ReadOnly)?;
 
-    let header_row = range.rows().next().unwrap();
-    let id_col_index = header_row.iter().position(|cell| {
-        if let Some(value) = cell.get_string() {
-            value == "學號"
-        } else {
-            false
-        }
-    });
+    let upload = read_upload(&mut payload, &SPREADSHEET_EXTENSIONS).await?;
+    let sheets = upload.read_sheets(&selection)?;
 
-    let mut student_ids: Vec<String> = Vec::new();
-    if let Some(col_index) = id_col_index {
-        student_ids = range
-            .rows()
-            .skip(1)
-            .filter_map(|row| row.get(col_index))
-            .filter_map(|cell| cell.get_string())
-            .map(|s| s.to_string())
-            .collect();
-    } else {
-        println!("未找到 '學號' 標題");
-        return HttpResponse::BadRequest()
-            .body("請將學號那欄(column)的第一列(row)的標題改為 '學號'");
-    }
+    let student_ids = read_student_ids(&sheets)?;
 
     if student_ids.is_empty() {
-        return HttpResponse::BadRequest().body("沒有找到任何有效的學號");
+        return Err(ApiError::invalid_input(t!(NoValidStudentIdFound)));
     }
 
     let query = r#"
     SELECT 
         si.StudentID AS StudentID, 
         si.Name AS Name,
+        si.IsPassed AS IsPassed,
+        si.PassedDate AS PassedDate,
+        si.PassingCriteria AS PassingCriteria,
+        si.PassedSession_SN AS PassedSession_SN,
+        si.PassCriteria_SN AS PassCriteria_SN,
+        si.PassingConditions AS PassingConditions,
+        ps.ExamType AS PassedExamType,
+        pc.Name AS PassCriteriaName,
         CAST(COALESCE(SUM(ea.CorrectAnswersCount), 0) AS UNSIGNED INTEGER) AS TotalCorrectAnswers, 
         CAST(COALESCE(MAX(ea.CorrectAnswersCount), 0) AS UNSIGNED INTEGER) AS MaxCorrectAnswers
     FROM 
         StudentInfo si
     LEFT JOIN 
         ExamAttendance ea ON si.StudentID = ea.StudentID
+    LEFT JOIN 
+        ExamSessions ps ON ps.SN = si.PassedSession_SN
+    LEFT JOIN 
+        PassCriteria pc ON pc.SN = si.PassCriteria_SN
     WHERE 
         si.StudentID = (?)
     GROUP BY 
-        si.StudentID, si.Name;
+        si.StudentID, si.Name, si.IsPassed, si.PassedDate, si.PassingCriteria,
+        si.PassedSession_SN, si.PassCriteria_SN, si.PassingConditions, ps.ExamType, pc.Name;
     "#;
 
     let mut results: Vec<StudentResult> = Vec::new();
@@ -133,24 +117,36 @@ async fn excel_search_pass(
         {
             Ok(row) => row,
             Err(sqlx::Error::RowNotFound) => {
-                return HttpResponse::NotFound().body(format!("查無此學生:{}，請先建立此學生的資料再進行查詢", student_id));
+                return Err(ApiError::new(
+                    ErrorCode::StudentNotFound,
+                    t!(StudentNotFoundCreateFirst, student_id),
+                ));
             }
             Err(err) => {
-                return HttpResponse::InternalServerError().body(format!("查詢失敗: {}", err));
+                return Err(ApiError::database(t!(QueryFailed), err));
             }
         };
 
-        let student_id: String = row.try_get("StudentID").expect("Failed to get StudentID");
+        let student_id: String = row.try_get("StudentID")?;
         let name: String = row
             .try_get("Name")
             .unwrap_or_else(|_| "Unknown".to_string());
         let total_correct_answers: u16 = row
-            .try_get("TotalCorrectAnswers")
-            .expect("Failed to get TotalCorrectAnswers");
+            .try_get("TotalCorrectAnswers")?;
         let max_correct_answers: u8 = row
-            .try_get("MaxCorrectAnswers")
-            .expect("Failed to get MaxCorrectAnswers");
-        let passed = total_correct_answers >= 3 || max_correct_answers >= 2;
+            .try_get("MaxCorrectAnswers")?;
+        // 直接使用寫入成績時記錄的通過狀態，不再依規則重新判斷
+        let passed = row.try_get::<Option<bool>, _>("IsPassed")?.unwrap_or(false);
+        let passed_date: Option<NaiveDate> = row.try_get("PassedDate")?;
+        let passing_criteria: Option<String> = row.try_get("PassingCriteria")?;
+        let record = PassRecord::from_columns(
+            row.try_get("PassedSession_SN")?,
+            passed_date,
+            row.try_get("PassCriteria_SN")?,
+            row.try_get("PassingConditions")?,
+        );
+        let passed_exam_type: Option<String> = row.try_get("PassedExamType")?;
+        let pass_rule_name: Option<String> = row.try_get("PassCriteriaName")?;
 
         results.push(StudentResult {
             student_id,
@@ -158,58 +154,42 @@ async fn excel_search_pass(
             total_correct_answers,
             max_correct_answers,
             passed,
+            passed_date,
+            passed_session: record.as_ref().map(|record| record.session_label(passed_exam_type.as_deref())),
+            pass_rule: record.as_ref().map(|record| record.rule_name(pass_rule_name.as_deref())),
+            passing_criteria,
+            qualifying_conditions: record.as_ref().map(PassRecord::condition_names),
         });
     }
 
-    // 生成 Excel 檔案
-    let output_filepath = "./uploads/result_file.xlsx";
-    let workbook = Workbook::new(output_filepath).expect("Failed to create workbook");
-    let mut worksheet = workbook.add_worksheet(None).unwrap();
-
-    worksheet.write_string(0, 0, "學號", None).unwrap();
-    worksheet.write_string(0, 1, "姓名", None).unwrap();
-    worksheet.write_string(0, 2, "累計題數", None).unwrap();
-    worksheet.write_string(0, 3, "最高題數", None).unwrap();
-    worksheet.write_string(0, 4, "是否通過", None).unwrap();
-
-    for (i, result) in results.iter().enumerate() {
-        worksheet
-            .write_string(i as u32 + 1, 0, &result.student_id, None)
-            .unwrap();
-        worksheet
-            .write_string(i as u32 + 1, 1, &result.name, None)
-            .unwrap();
-        worksheet
-            .write_number(i as u32 + 1, 2, result.total_correct_answers as f64, None)
-            .unwrap();
-        worksheet
-            .write_number(i as u32 + 1, 3, result.max_correct_answers as f64, None)
-            .unwrap();
-        worksheet
-            .write_string(i as u32 + 1, 4, if result.passed { "通過" } else { "不通過" }, None)
-            .unwrap();
+    let mut table = ExportTable::new(&PASS_COLUMNS);
+    for result in results.iter() {
+        table.push(vec![
+            result.student_id.as_str().into(),
+            result.name.as_str().into(),
+            result.total_correct_answers.into(),
+            result.max_correct_answers.into(),
+            if result.passed { "通過" } else { "不通過" }.into(),
+            result.passed_date.map(|date| date.format("%Y-%m-%d").to_string()).into(),
+            result.passed_session.as_deref().into(),
+            result.pass_rule.as_deref().into(),
+            result.passing_criteria.as_deref().into(),
+            result.qualifying_conditions.as_deref().into(),
+        ]);
+    }
+    if let Some(format) = format {
+        return table.respond(format, "search_pass");
     }
 
-    workbook.close().unwrap();
-
-    // 讀取 Excel 檔案並轉為 base64
-    let excel_file_data = match std::fs::read(output_filepath) {
-        Ok(data) => data,
-        Err(err) => {
-            println!("Error reading generated file: {}", err);
-            return HttpResponse::InternalServerError()
-                .body("Failed to generate or retrieve result Excel file");
-        }
-    };
-    let excel_file_base64 = base64::engine::general_purpose::STANDARD.encode(&excel_file_data);
-
+    // 生成 Excel 檔案並提供下載網址
+    let download_url = downloads.register(&session, &table, ExportFormat::Xlsx, "search_pass")?;
     // 構建 JSON 響應
     let response = ApiResponse {
         results,
-        excel_file: excel_file_base64,
+        download_url,
     };
 
-    HttpResponse::Ok()
+    Ok(HttpResponse::Ok()
         .content_type("application/json")
-        .json(response)
+        .json(response))
 }
\ No newline at end of file
@@ -1,23 +1,26 @@
 use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
 use actix_session::Session;
 use actix_web::{get, web, HttpRequest, HttpResponse};
 use serde::Serialize;
 use sqlx::MySqlPool;
 use sqlx::Row;
 use chrono::NaiveDate;
-#[derive(Serialize, Debug)]
+use utoipa::ToSchema;
+#[derive(Serialize, Debug, ToSchema)]
 struct ExamSessionsInfo {
     info: Vec<String>
 }
+#[utoipa::path(tag = "exam_sessions", responses((status = 200, description = "所有場次，格式為「日期,種類」", body = ExamSessionsInfo)))]
 #[get("/api/get_exam_session_info")]
 async fn get_exam_session_info(
     req: HttpRequest,
     session: Session,
     db_pool: web::Data<MySqlPool>,
-) -> HttpResponse {
-    if !is_authorization(req, session) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session, Role::ReadOnly)?;
     let query = r#"
     SELECT 
         ExamDate,
@@ -28,16 +31,16 @@ async fn get_exam_session_info(
     let rows = match sqlx::query(query).fetch_all(db_pool.get_ref()).await {
         Ok(rows) => rows,
         Err(err) => {
-            return HttpResponse::InternalServerError().body(format!("Internal server error.: {}", err));
+            return Err(ApiError::database(t!(InternalServerError), err));
         }
         
     };
     let mut info: Vec<String> = Vec::new();
     for row in rows {
-        let date: NaiveDate = row.try_get("ExamDate").unwrap();
-        let exam_type: String = row.try_get("ExamType").unwrap();
+        let date: NaiveDate = row.try_get("ExamDate")?;
+        let exam_type: String = row.try_get("ExamType")?;
         info.push(format!("{},{}", date, exam_type));
     }
     info.reverse();
-    HttpResponse::Ok().json(ExamSessionsInfo { info })
+    Ok(HttpResponse::Ok().json(ExamSessionsInfo { info }))
 }
@@ -7,4 +7,10 @@ pub mod query_exam_info;
 pub mod query_score_info;
 pub mod scholarship_query_json;
 pub mod query_scholarship_excel;
-pub mod query_passed_by_year;
\ No newline at end of file
+pub mod query_passed_by_year;
+pub mod query_pass_criteria;
+pub mod query_users;
+pub mod query_sessions;
+pub mod query_login_locks;
+pub mod query_audit_log;
+pub mod query_lookup;
\ No newline at end of file
@@ -0,0 +1,114 @@
+use actix_session::Session;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::{MySqlPool, mysql::MySqlArguments};
+use sqlx::{Arguments, Row};
+use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Deserialize, IntoParams)]
+struct QueryParams {
+    username: Option<String>,
+    entity_type: Option<String>, // student、exam_session、exam_attendance、scholarship、pass_criteria、user
+    entity_key: Option<String>,
+    student_id: Option<String>, // 查詢與此學號相關的所有異動
+    date_from: Option<NaiveDate>,
+    date_to: Option<NaiveDate>,
+    limit: Option<u32>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct AuditLogInfo {
+    sn: i64,
+    username: String,
+    endpoint: String,
+    entity_type: String,
+    entity_key: String,
+    student_id: Option<String>,
+    #[schema(value_type = Option<Object>)]
+    before: Option<serde_json::Value>,
+    #[schema(value_type = Option<Object>)]
+    after: Option<serde_json::Value>,
+    created_at: NaiveDateTime,
+}
+
+#[utoipa::path(tag = "audit", responses((status = 200, description = "稽核紀錄", body = Vec<AuditLogInfo>)))]
+#[get("/api/query_audit_log")]
+async fn query_audit_log(
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+    params: web::Query<QueryParams>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session, Role::Admin)?;
+    let params = params.into_inner();
+    let mut where_clauses = Vec::new();
+    let mut query_args = MySqlArguments::default();
+    if let Some(username) = params.username {
+        where_clauses.push("Username = ?");
+        let _ = query_args.add(username);
+    }
+    if let Some(entity_type) = params.entity_type {
+        where_clauses.push("EntityType = ?");
+        let _ = query_args.add(entity_type);
+    }
+    if let Some(entity_key) = params.entity_key {
+        where_clauses.push("EntityKey = ?");
+        let _ = query_args.add(entity_key);
+    }
+    if let Some(student_id) = params.student_id {
+        where_clauses.push("StudentID = ?");
+        let _ = query_args.add(student_id.trim().to_ascii_uppercase());
+    }
+    if let Some(date_from) = params.date_from {
+        where_clauses.push("CreatedAt >= ?");
+        let _ = query_args.add(date_from.and_hms_opt(0, 0, 0));
+    }
+    if let Some(date_to) = params.date_to {
+        // 包含結束日期當天
+        where_clauses.push("CreatedAt < ?");
+        let _ = query_args.add(date_to.succ_opt().and_then(|d| d.and_hms_opt(0, 0, 0)));
+    }
+    let where_clause = if where_clauses.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", where_clauses.join(" AND "))
+    };
+    let limit = params.limit.unwrap_or(200).clamp(1, 1000);
+    let sql = format!(
+        r#"
+        SELECT SN, Username, Endpoint, EntityType, EntityKey, StudentID, BeforeValue, AfterValue, CreatedAt
+        FROM AuditLog
+        {}
+        ORDER BY SN DESC
+        LIMIT {}
+        "#,
+        where_clause, limit
+    );
+
+    let rows = match sqlx::query_with(&sql, query_args).fetch_all(db_pool.get_ref()).await {
+        Ok(rows) => rows,
+        Err(err) => return Err(ApiError::database(t!(QueryAuditLogFailed), err)),
+    };
+    // 前後內容以 JSON 物件回傳，方便前端比對差異
+    let parse = |value: Option<String>| value.and_then(|v| serde_json::from_str(&v).ok());
+    let logs: Vec<AuditLogInfo> = rows
+        .iter()
+        .map(|row| AuditLogInfo {
+            sn: row.get("SN"),
+            username: row.get("Username"),
+            endpoint: row.get("Endpoint"),
+            entity_type: row.get("EntityType"),
+            entity_key: row.get("EntityKey"),
+            student_id: row.get("StudentID"),
+            before: parse(row.get("BeforeValue")),
+            after: parse(row.get("AfterValue")),
+            created_at: row.get("CreatedAt"),
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(logs))
+}
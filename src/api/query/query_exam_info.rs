@@ -1,35 +1,40 @@
 use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::audit::AuditTarget;
+use crate::api::etag::etag_of;
 use actix_session::Session;
-use actix_web::{post, web, HttpRequest, HttpResponse};
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
 use serde::{Deserialize, Serialize};
 use sqlx::MySqlPool;
 use chrono::NaiveDate;
 use sqlx::Row;
+use utoipa::ToSchema;
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, ToSchema)]
 struct ExamDate {
     date: NaiveDate,
     exam_type: String,
 }
 
-#[derive(Debug, Serialize)]
-struct ExamInfo {
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct ExamInfo {
     sn: i32,
     exam_date: chrono::NaiveDate,
     exam_type: String,
     notes: Option<String>,
 }
 
+#[utoipa::path(tag = "exam_sessions", responses((status = 200, description = "場次資料，並記住此場次供 /api/modify_exam_info 使用", body = ExamInfo)))]
 #[post("/api/query_exam_info")]
 async fn query_exam_info(
     req: HttpRequest,
     session: Session,
     db_pool: web::Data<MySqlPool>,
     data: web::Json<ExamDate>,
-) -> HttpResponse {
-    if !is_authorization(req, session.clone()) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::ReadOnly)?;
     let query = r#"
         SELECT SN, ExamDate, ExamType, Notes
         FROM ExamSessions
@@ -50,14 +55,57 @@ async fn query_exam_info(
                 exam_type: row.get("ExamType"),
                 notes: row.get("Notes"),
             };
-            session.insert("modify_exam_sn", &exam_info.sn).unwrap();
-            session.insert("modify_exam_date", &exam_info.exam_date).unwrap();
-            session.insert("modify_exam_type", &exam_info.exam_type).unwrap();
-            session.insert("modify_notes", &exam_info.notes).unwrap();
-            return HttpResponse::Ok().json(exam_info);
+            session.insert("modify_exam_sn", &exam_info.sn)?;
+            session.insert("modify_exam_date", &exam_info.exam_date)?;
+            session.insert("modify_exam_type", &exam_info.exam_type)?;
+            session.insert("modify_notes", &exam_info.notes)?;
+            return Ok(HttpResponse::Ok().json(exam_info));
+        }
+        Err(sqlx::Error::RowNotFound) => {
+            return Err(ApiError::new(ErrorCode::ExamSessionNotFound, t!(ExamSessionNotFound)));
         }
         Err(err) => {
-            return HttpResponse::InternalServerError().body(format!("Internal server error.: {}", err));
+            return Err(ApiError::database(t!(InternalServerError), err));
         }
     }
+}
+
+/// 以場次 SN 取得場次資料，回應的 ETag 用於 /api/modify_exam_info/{sn} 的 If-Match
+#[utoipa::path(tag = "exam_sessions", responses((status = 200, description = "場次資料，ETag 標頭為目前的版本", body = ExamInfo)))]
+#[get("/api/exam_info/{sn}")]
+async fn get_exam_info(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session, Role::ReadOnly)?;
+    exam_info_response(db_pool.get_ref(), path.into_inner()).await
+}
+
+/// 場次資料與代表目前版本的 ETag，/api/v2/exam-sessions/{sn} 共用
+pub(crate) async fn exam_info_response(db_pool: &MySqlPool, exam_sn: i32) -> Result<HttpResponse, ApiError> {
+    let row = sqlx::query("SELECT SN, ExamDate, ExamType, Notes FROM ExamSessions WHERE SN = ?")
+        .bind(exam_sn)
+        .fetch_optional(db_pool)
+        .await
+        .map_err(|err| ApiError::database(t!(InternalServerError), err))?;
+    let Some(row) = row else {
+        return Err(ApiError::new(ErrorCode::ExamSessionNotFound, t!(ExamSessionNotFound)));
+    };
+    let exam_info = ExamInfo {
+        sn: row.try_get("SN")?,
+        exam_date: row.try_get("ExamDate")?,
+        exam_type: row.try_get("ExamType")?,
+        notes: row.try_get("Notes")?,
+    };
+    let snapshot = AuditTarget::ExamSession(exam_sn)
+        .snapshot(&mut *db_pool.acquire().await?)
+        .await
+        .map_err(|err| ApiError::database(t!(InternalServerError), err))?;
+    let mut response = HttpResponse::Ok();
+    if let Some(snapshot) = snapshot {
+        response.insert_header(("ETag", etag_of(&snapshot)));
+    }
+    Ok(response.json(exam_info))
 }
\ No newline at end of file
@@ -0,0 +1,25 @@
+use actix_session::Session;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use crate::api::lib::is_authorization;
+use crate::api::login_throttle::LoginThrottle;
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use crate::api::login_throttle::LoginLock;
+
+#[utoipa::path(tag = "users", responses((status = 200, description = "目前被鎖定的帳號與 IP", body = Vec<LoginLock>)))]
+#[get("/api/query_login_locks")]
+async fn query_login_locks(
+    req: HttpRequest,
+    session: Session,
+    throttle: web::Data<LoginThrottle>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session, Role::Admin)?;
+    match throttle.list_locks().await {
+        Ok(locks) => Ok(HttpResponse::Ok().json(locks)),
+        Err(err) => {
+            eprintln!("Redis error: {:?}", err);
+            Err(ApiError::internal(t!(QueryLocksFailed)))
+        }
+    }
+}
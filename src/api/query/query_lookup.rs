@@ -0,0 +1,64 @@
+use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use crate::api::lookup::LookupKind;
+use actix_session::Session;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use sqlx::MySqlPool;
+use sqlx::Row;
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Deserialize, IntoParams)]
+struct LookupQuery {
+    kind: LookupKind,
+}
+
+#[derive(Serialize, ToSchema)]
+struct LookupInfo {
+    sn: i32,
+    name: String,
+    code: Option<String>,
+    notes: Option<String>,
+    student_count: i64, // 使用此項目的學生人數，不為 0 時無法刪除
+}
+
+#[utoipa::path(tag = "lookup", responses((status = 200, description = "對照表的所有項目", body = Vec<LookupInfo>)))]
+#[get("/api/query_lookup")]
+async fn query_lookup(
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+    query: web::Query<LookupQuery>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session, Role::ReadOnly)?;
+    let kind = query.kind;
+    let sql = format!(
+        r#"
+        SELECT l.SN, l.{name} AS Name, l.Code, l.Notes, COUNT(s.StudentID) AS StudentCount
+        FROM {table} l
+        LEFT JOIN StudentInfo s ON s.{column} = l.SN
+        GROUP BY l.SN, l.{name}, l.Code, l.Notes
+        ORDER BY l.SN
+        "#,
+        name = kind.name_column(),
+        table = kind.table(),
+        column = kind.student_column(),
+    );
+    let rows = sqlx::query(&sql)
+        .fetch_all(db_pool.get_ref())
+        .await
+        .map_err(|err| ApiError::database(t!(QueryLookupFailed), err))?;
+    let mut entries = Vec::with_capacity(rows.len());
+    for row in rows {
+        entries.push(LookupInfo {
+            sn: row.try_get("SN")?,
+            name: row.try_get("Name")?,
+            code: row.try_get("Code")?,
+            notes: row.try_get("Notes")?,
+            student_count: row.try_get("StudentCount")?,
+        });
+    }
+    Ok(HttpResponse::Ok().json(entries))
+}
@@ -0,0 +1,67 @@
+use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use crate::api::pass_rule::PassRule;
+use actix_session::Session;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::MySqlPool;
+use sqlx::Row;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+struct PassCriteriaInfo {
+    sn: i32,
+    name: String,
+    rule: PassRule,
+    entrance_year_from: Option<i32>,
+    entrance_year_to: Option<i32>,
+    is_active: bool,
+    created_at: Option<NaiveDateTime>,
+    notes: Option<String>,
+}
+
+#[utoipa::path(tag = "pass_criteria", responses((status = 200, description = "所有通過規則", body = Vec<PassCriteriaInfo>)))]
+#[get("/api/query_pass_criteria")]
+async fn query_pass_criteria(
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session, Role::ReadOnly)?;
+    let query = r#"
+        SELECT SN, Name, RuleDefinition, EntranceYearFrom, EntranceYearTo, IsActive, CreatedAt, Notes
+        FROM PassCriteria
+        ORDER BY SN DESC
+    "#;
+    let rows = match sqlx::query(query).fetch_all(db_pool.get_ref()).await {
+        Ok(rows) => rows,
+        Err(err) => {
+            return Err(ApiError::database(t!(QueryRulesFailed), err));
+        }
+    };
+    let mut criteria: Vec<PassCriteriaInfo> = Vec::new();
+    for row in rows {
+        let definition: String = row.get("RuleDefinition");
+        let rule = match serde_json::from_str(&definition) {
+            Ok(rule) => rule,
+            Err(err) => {
+                println!("通過規則格式錯誤: {}", err);
+                return Err(ApiError::internal(t!(StoredRuleInvalid)));
+            }
+        };
+        criteria.push(PassCriteriaInfo {
+            sn: row.get("SN"),
+            name: row.get("Name"),
+            rule,
+            entrance_year_from: row.get("EntranceYearFrom"),
+            entrance_year_to: row.get("EntranceYearTo"),
+            is_active: row.get("IsActive"),
+            created_at: row.get("CreatedAt"),
+            notes: row.get("Notes"),
+        });
+    }
+    Ok(HttpResponse::Ok().json(criteria))
+}
@@ -1,295 +1,223 @@
 use actix_session::Session;
 use actix_web::{post, web, HttpRequest, HttpResponse};
-use base64::{engine::general_purpose, Engine as _};
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
-use sqlx::MySqlPool;
-use std::collections::{BTreeMap, HashMap, HashSet};
-use xlsxwriter::Workbook;
-
-use crate::api::lib::is_authorization;
-
-#[derive(Deserialize)]
+use sqlx::{MySqlPool, Row};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::api::lib::{academic_year_range, is_authorization};
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use crate::api::export::{ExportColumn, ExportFormat, ExportQuery, ExportTable};
+use crate::api::download::DownloadStore;
+use crate::api::pass_rule::PassRecord;
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema)]
 pub struct PassedByYearForm {
     pub academic_year: u32,
 }
 
-#[derive(Serialize)]
-struct PassedByYearRow {
+#[derive(Serialize, ToSchema)]
+pub struct PassedByYearRow {
     student_id: String,
     name: String,
+    passed_date: NaiveDate,
+    passed_session: Option<String>, // 通過的場次，例如 "2024-05-01,官辦"，尚未補上通過紀錄時為 None
+    pass_rule: Option<String>, // 適用的通過規則名稱
+    passing_criteria: Option<String>,
+    qualifying_conditions: Option<String>, // 在通過的場次成立的條件
     total_correct_answers: i32,
     max_correct_answers: i32,
     sessions_joined: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct PassedByYearResponse {
     results: Vec<PassedByYearRow>,
-    excel_file: String,
+    download_url: String,
 }
 
-const PASS_TOTAL_THRESHOLD: i32 = 3;
-const PASS_SINGLE_THRESHOLD: i32 = 2;
-
+const PASSED_BY_YEAR_COLUMNS: [ExportColumn; 10] = [
+    ExportColumn { key: "student_id", title: "學號" },
+    ExportColumn { key: "name", title: "姓名" },
+    ExportColumn { key: "passed_date", title: "通過日期" },
+    ExportColumn { key: "passed_session", title: "通過場次" },
+    ExportColumn { key: "pass_rule", title: "適用規則" },
+    ExportColumn { key: "passing_criteria", title: "通過條件" },
+    ExportColumn { key: "qualifying_conditions", title: "通過時成立的條件" },
+    ExportColumn { key: "total_correct_answers", title: "累計題數(本學年度)" },
+    ExportColumn { key: "max_correct_answers", title: "最高題數(本學年度)" },
+    ExportColumn { key: "sessions_joined", title: "各場次題數(全歷年)" },
+];
+
+#[utoipa::path(tag = "students", responses((status = 200, description = "該學年度通過的學生；帶 format 時直接回傳檔案", body = PassedByYearResponse)))]
 #[post("/api/query_passed_by_year")]
 pub async fn query_passed_by_year(
     req: HttpRequest,
     session: Session,
     db: web::Data<MySqlPool>,
     form: web::Form<PassedByYearForm>,
-) -> HttpResponse {
-    if !is_authorization(req, session) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期");
+    export: web::Query<ExportQuery>,
+    downloads: web::Data<DownloadStore>,
+) -> Result<HttpResponse, ApiError> {
+    let format = ExportFormat::negotiate(&export, req.headers());
+    is_authorization(req, session.clone(), Role::ReadOnly)?;
+
+    let results = passed_by_year_rows(db.get_ref(), form.academic_year).await?;
+    let table = passed_by_year_table(&results);
+    if let Some(format) = format {
+        return table.respond(format, "passed_by_year");
     }
 
-    let curr_start = NaiveDate::from_ymd_opt((form.academic_year as i32) + 1911, 8, 1).unwrap();
-    let curr_end   = NaiveDate::from_ymd_opt((form.academic_year as i32) + 1912, 7, 31).unwrap();
-    let history_end = curr_start.pred_opt().unwrap();
-
-    let curr_rows = match sqlx::query!(
-        r#"
-        SELECT 
-            si.StudentID                         AS student_id,
-            si.Name                              AS name,
-            es.SN                                AS session_sn,
-            es.ExamDate                          AS exam_date,
-            COALESCE(ea.CorrectAnswersCount, 0)  AS correct
-        FROM ExamAttendance ea
-        JOIN ExamSessions es ON es.SN = ea.ExamSession_SN
-        JOIN StudentInfo  si ON si.StudentID = ea.StudentID
-        WHERE ea.IsAbsent = FALSE
-          AND ea.IsExcused = FALSE
-          AND es.ExamDate >= ? AND es.ExamDate <= ?
-        ORDER BY si.StudentID, es.ExamDate
-        "#,
-        curr_start, curr_end
-    )
-    .fetch_all(db.get_ref())
-    .await
-    {
-        Ok(v) => v,
+    let download_url = match downloads.register(&session, &table, ExportFormat::Xlsx, "passed_by_year") {
+        Ok(url) => url,
         Err(e) => {
-            eprintln!("查詢本學年度資料失敗: {e}");
-            return HttpResponse::InternalServerError().body("查詢本學年度資料失敗");
+            eprintln!("匯出 Excel 失敗: {e}");
+            return Err(ApiError::internal(t!(ExportExcelFailed)));
         }
     };
 
-    struct CurrAgg {
-        name: String,
-        per_sn_max: HashMap<i64, i32>,
-        total: i32,
-        maxv: i32,
-    }
-    let mut curr_map: HashMap<String, CurrAgg> = HashMap::new();
-    for row in curr_rows {
-        let sid = row.student_id;
-        let name = row.name;
-        let sn = row.session_sn as i64;
-        let cnt = row.correct as i32;
-
-        let entry = curr_map.entry(sid.clone()).or_insert_with(|| CurrAgg {
-            name,
-            per_sn_max: HashMap::new(),
-            total: 0,
-            maxv: 0,
-        });
-        let prev = *entry.per_sn_max.get(&sn).unwrap_or(&0);
-        if cnt > prev {
-            entry.per_sn_max.insert(sn, cnt);
-        }
-    }
-    for agg in curr_map.values_mut() {
-        let mut total = 0;
-        let mut maxv = 0;
-        for (_sn, c) in agg.per_sn_max.iter() {
-            total += *c;
-            if *c > maxv { maxv = *c; }
-        }
-        agg.total = total;
-        agg.maxv  = maxv;
-    }
+    Ok(HttpResponse::Ok().json(PassedByYearResponse { results, download_url }))
+}
 
-    let prev_agg = match sqlx::query!(
-        r#"
-        SELECT 
-            ea.StudentID AS student_id,
-            CAST(COALESCE(SUM(ea.CorrectAnswersCount),0) AS SIGNED) AS total_correct,
-            CAST(COALESCE(MAX(ea.CorrectAnswersCount),0) AS SIGNED) AS max_correct
-        FROM ExamAttendance ea
-        JOIN ExamSessions es ON es.SN = ea.ExamSession_SN
-        WHERE ea.IsAbsent = FALSE
-          AND ea.IsExcused = FALSE
-          AND es.ExamDate <= ?
-        GROUP BY ea.StudentID
-        "#,
-        history_end
-    )
-    .fetch_all(db.get_ref())
-    .await
-    {
-        Ok(v) => v,
-        Err(e) => {
-            eprintln!("查詢歷史(至前一年末)彙總失敗: {e}");
-            return HttpResponse::InternalServerError().body("查詢歷史資料失敗");
-        }
-    };
+/// 該學年度（民國年）首次達到通過標準的學生，依 StudentInfo 記錄的通過日期篩選並依學號排序
+/// cpe-admin 的 export passed-by-year 共用
+pub async fn passed_by_year_rows(db: &MySqlPool, academic_year: u32) -> Result<Vec<PassedByYearRow>, ApiError> {
+    let (curr_start, curr_end) = academic_year_range(academic_year, 8).map_err(|e| e.with_field("academic_year"))?;
 
-    let upto_curr_agg = match sqlx::query!(
+    let passed_rows = match sqlx::query(
         r#"
-        SELECT 
-            ea.StudentID AS student_id,
-            CAST(COALESCE(SUM(ea.CorrectAnswersCount),0) AS SIGNED) AS total_correct,
-            CAST(COALESCE(MAX(ea.CorrectAnswersCount),0) AS SIGNED) AS max_correct
-        FROM ExamAttendance ea
-        JOIN ExamSessions es ON es.SN = ea.ExamSession_SN
-        WHERE ea.IsAbsent = FALSE
-          AND ea.IsExcused = FALSE
-          AND es.ExamDate <= ?
-        GROUP BY ea.StudentID
+        SELECT si.StudentID, si.Name, si.PassedDate, si.PassingCriteria,
+            si.PassedSession_SN, si.PassCriteria_SN, si.PassingConditions,
+            ps.ExamType AS PassedExamType, pc.Name AS PassCriteriaName
+        FROM StudentInfo si
+        LEFT JOIN ExamSessions ps ON ps.SN = si.PassedSession_SN
+        LEFT JOIN PassCriteria pc ON pc.SN = si.PassCriteria_SN
+        WHERE si.IsPassed = TRUE
+          AND si.PassedDate >= ? AND si.PassedDate <= ?
+        ORDER BY si.StudentID
         "#,
-        curr_end
     )
-    .fetch_all(db.get_ref())
+    .bind(curr_start)
+    .bind(curr_end)
+    .fetch_all(db)
     .await
     {
         Ok(v) => v,
         Err(e) => {
-            eprintln!("查詢歷史(至本年末)彙總失敗: {e}");
-            return HttpResponse::InternalServerError().body("查詢歷史資料失敗");
+            eprintln!("查詢本學年度通過學生失敗: {e}");
+            return Err(ApiError::internal(t!(QueryCurrentYearFailed)));
         }
     };
 
-    let mut passed_before_y: HashMap<String, bool> = HashMap::new();
-    for r in prev_agg {
-        let total = r.total_correct as i32;
-        let maxv  = r.max_correct as i32;
-        let passed = total >= PASS_TOTAL_THRESHOLD || maxv >= PASS_SINGLE_THRESHOLD;
-        passed_before_y.insert(r.student_id, passed);
-    }
-
-    let mut passed_by_end_y: HashMap<String, bool> = HashMap::new();
-    for r in upto_curr_agg {
-        let total = r.total_correct as i32;
-        let maxv  = r.max_correct as i32;
-        let passed = total >= PASS_TOTAL_THRESHOLD || maxv >= PASS_SINGLE_THRESHOLD;
-        passed_by_end_y.insert(r.student_id, passed);
-    }
-
-    let mut selected_ids: HashSet<String> = HashSet::new();
-    for (sid, &is_passed_end_y) in passed_by_end_y.iter() {
-        if !is_passed_end_y { continue; }
-        let was_passed_before = *passed_before_y.get(sid).unwrap_or(&false);
-        if !was_passed_before {
-            selected_ids.insert(sid.clone());
-        }
-    }
-
-    let all_rows = match sqlx::query!(
+    // 只讀取這些學生的應考紀錄，用於本學年度的題數與全歷年各場次題數
+    let attendance_rows = match sqlx::query(
         r#"
-        SELECT 
-            si.StudentID                         AS student_id,
-            si.Name                              AS name,
-            es.ExamDate                          AS exam_date,
-            COALESCE(ea.CorrectAnswersCount, 0)  AS correct
+        SELECT
+            si.StudentID                                           AS student_id,
+            es.SN                                                  AS session_sn,
+            es.ExamDate                                            AS exam_date,
+            CAST(COALESCE(ea.CorrectAnswersCount, 0) AS SIGNED)    AS correct
         FROM ExamAttendance ea
         JOIN ExamSessions es ON es.SN = ea.ExamSession_SN
         JOIN StudentInfo  si ON si.StudentID = ea.StudentID
         WHERE ea.IsAbsent = FALSE
           AND ea.IsExcused = FALSE
+          AND si.IsPassed = TRUE
+          AND si.PassedDate >= ? AND si.PassedDate <= ?
         ORDER BY si.StudentID, es.ExamDate
-        "#
+        "#,
     )
-    .fetch_all(db.get_ref())
+    .bind(curr_start)
+    .bind(curr_end)
+    .fetch_all(db)
     .await
     {
         Ok(v) => v,
         Err(e) => {
             eprintln!("查詢全歷年資料失敗: {e}");
-            return HttpResponse::InternalServerError().body("查詢歷年資料失敗");
+            return Err(ApiError::internal(t!(QueryYearsFailed)));
         }
     };
 
-    let mut lifetime_map: HashMap<String, BTreeMap<String, i32>> = HashMap::new();
-    let mut name_map: HashMap<String, String> = HashMap::new();
-    for r in all_rows {
-        if !selected_ids.contains(&r.student_id) { continue; }
-        name_map.entry(r.student_id.clone()).or_insert(r.name);
-        let date_str = r.exam_date.format("%Y-%m-%d").to_string();
-        let e = lifetime_map.entry(r.student_id.clone()).or_insert_with(BTreeMap::new);
-        let prev = e.get(&date_str).copied().unwrap_or(0);
-        let c = r.correct as i32;
-        if c > prev {
-            e.insert(date_str, c);
+    #[derive(Default)]
+    struct Agg {
+        curr_per_sn_max: HashMap<i32, i32>,        // 本學年度各場次的題數
+        lifetime_per_date: BTreeMap<NaiveDate, i32>, // 全歷年各考試日期的題數
+    }
+    let mut agg_map: HashMap<String, Agg> = HashMap::new();
+    for row in attendance_rows {
+        let sid: String = row.try_get("student_id")?;
+        let sn: i32 = row.try_get("session_sn")?;
+        let date: NaiveDate = row.try_get("exam_date")?;
+        let c = row.try_get::<i64, _>("correct")? as i32;
+
+        let agg = agg_map.entry(sid).or_default();
+        if date >= curr_start && date <= curr_end {
+            let prev = agg.curr_per_sn_max.entry(sn).or_insert(0);
+            *prev = (*prev).max(c);
         }
+        let prev = agg.lifetime_per_date.entry(date).or_insert(0);
+        *prev = (*prev).max(c);
     }
 
     let mut results: Vec<PassedByYearRow> = Vec::new();
-    for sid in selected_ids {
-        let (total, maxv, name) = if let Some(agg) = curr_map.get(&sid) {
-            (agg.total, agg.maxv, agg.name.clone())
-        } else {
-            (0, 0, name_map.get(&sid).cloned().unwrap_or_default())
-        };
-
-        let joined = lifetime_map
-            .get(&sid)
-            .map(|m| {
-                m.iter()
-                    .filter(|(_d, &c)| c > 0)
-                    .map(|(d, &c)| format!("{}({})", d, c))
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            })
-            .unwrap_or_default();
+    for row in passed_rows {
+        let sid: String = row.try_get("StudentID")?;
+        let agg = agg_map.remove(&sid).unwrap_or_default();
+        let total = agg.curr_per_sn_max.values().sum();
+        let maxv = agg.curr_per_sn_max.values().copied().max().unwrap_or(0);
+        let joined = agg
+            .lifetime_per_date
+            .iter()
+            .filter(|(_d, &c)| c > 0)
+            .map(|(d, &c)| format!("{}({})", d.format("%Y-%m-%d"), c))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let passed_date: NaiveDate = row.try_get("PassedDate")?;
+        let record = PassRecord::from_columns(
+            row.try_get("PassedSession_SN")?,
+            Some(passed_date),
+            row.try_get("PassCriteria_SN")?,
+            row.try_get("PassingConditions")?,
+        );
+        let passed_exam_type: Option<String> = row.try_get("PassedExamType")?;
+        let pass_rule_name: Option<String> = row.try_get("PassCriteriaName")?;
 
         results.push(PassedByYearRow {
             student_id: sid,
-            name,
+            name: row.try_get("Name")?,
+            passed_date,
+            passed_session: record.as_ref().map(|record| record.session_label(passed_exam_type.as_deref())),
+            pass_rule: record.as_ref().map(|record| record.rule_name(pass_rule_name.as_deref())),
+            passing_criteria: row.try_get("PassingCriteria")?,
+            qualifying_conditions: record.as_ref().map(PassRecord::condition_names),
             total_correct_answers: total,
             max_correct_answers: maxv,
             sessions_joined: joined,
         });
     }
+    Ok(results)
+}
 
-    results.sort_by(|a, b| a.student_id.cmp(&b.student_id));
-    let filepath = format!("./uploads/passed_by_year_{}.xlsx", form.academic_year);
-    let workbook = match Workbook::new(&filepath) {
-        Ok(wb) => wb,
-        Err(e) => {
-            eprintln!("建立 Excel 失敗: {e}");
-            return HttpResponse::InternalServerError().body("建立 Excel 失敗");
-        }
-    };
-    let mut sheet = workbook.add_worksheet(None).unwrap();
-    sheet.write_string(0, 0, "學號", None).unwrap();
-    sheet.write_string(0, 1, "姓名", None).unwrap();
-    sheet.write_string(0, 2, "累計題數(本學年度)", None).unwrap();
-    sheet.write_string(0, 3, "最高題數(本學年度)", None).unwrap();
-    sheet.write_string(0, 4, "各場次題數(全歷年)", None).unwrap();
-
-    for (i, row) in results.iter().enumerate() {
-        let r = (i + 1) as u32;
-        sheet.write_string(r, 0, &row.student_id, None).unwrap();
-        sheet.write_string(r, 1, &row.name, None).unwrap();
-        sheet.write_number(r, 2, row.total_correct_answers as f64, None).unwrap();
-        sheet.write_number(r, 3, row.max_correct_answers as f64, None).unwrap();
-        sheet.write_string(r, 4, &row.sessions_joined, None).unwrap();
-    }
-
-    if let Err(e) = workbook.close() {
-        eprintln!("關閉 Excel 失敗: {e}");
-        return HttpResponse::InternalServerError().body("匯出 Excel 失敗");
+pub fn passed_by_year_table(rows: &[PassedByYearRow]) -> ExportTable {
+    let mut table = ExportTable::new(&PASSED_BY_YEAR_COLUMNS);
+    for row in rows {
+        table.push(vec![
+            row.student_id.as_str().into(),
+            row.name.as_str().into(),
+            row.passed_date.format("%Y-%m-%d").to_string().into(),
+            row.passed_session.as_deref().into(),
+            row.pass_rule.as_deref().into(),
+            row.passing_criteria.as_deref().into(),
+            row.qualifying_conditions.as_deref().into(),
+            row.total_correct_answers.into(),
+            row.max_correct_answers.into(),
+            row.sessions_joined.as_str().into(),
+        ]);
     }
-
-    let excel_base64 = match std::fs::read(&filepath) {
-        Ok(bytes) => general_purpose::STANDARD.encode(bytes),
-        Err(e) => {
-            eprintln!("讀取 Excel 失敗: {e}");
-            return HttpResponse::InternalServerError().body("讀取 Excel 錯誤");
-        }
-    };
-    let _ = std::fs::remove_file(&filepath);
-
-    HttpResponse::Ok().json(PassedByYearResponse { results, excel_file: excel_base64 })
-}
+    table
+}
\ No newline at end of file
@@ -1,33 +1,55 @@
 use actix_web::{post, web, HttpRequest, HttpResponse};
 use actix_session::Session;
-use chrono::NaiveDate;
 use serde::Deserialize;
 use sqlx::MySqlPool;
-use xlsxwriter::Workbook;
-use crate::api::lib::is_authorization;
+use crate::api::lib::{academic_year_range, is_authorization};
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::export::{ExportColumn, ExportFormat, ExportQuery, ExportTable};
+use utoipa::ToSchema;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct ScholarshipExcelForm {
-    academic_year: Option<u32>,
-    exam_academic_year: Option<u32>,
-    status: String, // all | claimed | unclaimed
+    pub academic_year: Option<u32>,
+    pub exam_academic_year: Option<u32>,
+    pub status: String, // all | claimed | unclaimed
 }
 
+const SCHOLARSHIP_COLUMNS: [ExportColumn; 8] = [
+    ExportColumn { key: "student_id", title: "學號" },
+    ExportColumn { key: "name", title: "姓名" },
+    ExportColumn { key: "correct_answers", title: "答對題數" },
+    ExportColumn { key: "exam_date", title: "考試日期" },
+    ExportColumn { key: "scholarship_amount", title: "獎學金金額" },
+    ExportColumn { key: "notes", title: "備註" },
+    ExportColumn { key: "claimed", title: "是否領獎" },
+    ExportColumn { key: "received_date", title: "領獎日期" },
+];
+
+#[utoipa::path(
+    tag = "scholarships",
+    responses((status = 200, description = "獎學金名單檔案，預設為 xlsx", content_type = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet", body = Vec<u8>)),
+)]
 #[post("/api/query_scholarship_excel")]
 pub async fn query_scholarship_excel(
     req: HttpRequest,
     session: Session,
     db: web::Data<MySqlPool>,
     form: web::Form<ScholarshipExcelForm>,
-) -> HttpResponse {
-    if !is_authorization(req, session) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期");
-    }
+    export: web::Query<ExportQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let format = ExportFormat::negotiate(&export, req.headers());
+    is_authorization(req, session, Role::ReadOnly)?;
+
+    let table = scholarship_table(db.get_ref(), &form).await?;
+    table.respond(format.unwrap_or(ExportFormat::Xlsx), "scholarship_result")
+}
 
+/// 依領獎學年度、考試學年度與領獎狀態產生獎學金名單，cpe-admin 的 export scholarships 共用
+pub async fn scholarship_table(db: &MySqlPool, form: &ScholarshipExcelForm) -> Result<ExportTable, ApiError> {
     let (recv_start, recv_end) = match form.academic_year {
         Some(year) => {
-            let s = NaiveDate::from_ymd_opt((year as i32) + 1911, 8, 1).unwrap();
-            let e = NaiveDate::from_ymd_opt((year as i32) + 1912, 7, 31).unwrap();
+            let (s, e) = academic_year_range(year, 8).map_err(|e| e.with_field("academic_year"))?;
             (Some(s), Some(e))
         }
         None => (None, None),
@@ -35,8 +57,7 @@ pub async fn query_scholarship_excel(
 
     let (exam_start, exam_end) = match form.exam_academic_year {
         Some(year) => {
-            let s = NaiveDate::from_ymd_opt((year as i32) + 1911, 8, 1).unwrap();
-            let e = NaiveDate::from_ymd_opt((year as i32) + 1912, 7, 31).unwrap();
+            let (s, e) = academic_year_range(year, 8).map_err(|e| e.with_field("exam_academic_year"))?;
             (Some(s), Some(e))
         }
         None => (None, None),
@@ -73,7 +94,7 @@ pub async fn query_scholarship_excel(
             recv_start, recv_start,
             recv_end, recv_end
         )
-        .fetch_all(db.get_ref())
+        .fetch_all(db)
         .await;
 
         if let Ok(rows) = claimed {
@@ -125,7 +146,7 @@ pub async fn query_scholarship_excel(
             exam_start, exam_start,
             exam_end, exam_end
         )
-        .fetch_all(db.get_ref())
+        .fetch_all(db)
         .await;
 
         if let Ok(rows) = unclaimed {
@@ -144,44 +165,18 @@ pub async fn query_scholarship_excel(
         }
     }
 
-    // 寫入 Excel
-    let filepath = "./uploads/scholarship_result.xlsx";
-    let workbook = Workbook::new(filepath).unwrap();
-    let mut sheet = workbook.add_worksheet(None).unwrap();
-
-    sheet.write_string(0, 0, "學號", None).unwrap();
-    sheet.write_string(0, 1, "姓名", None).unwrap();
-    sheet.write_string(0, 2, "答對題數", None).unwrap();
-    sheet.write_string(0, 3, "考試日期", None).unwrap();
-    sheet.write_string(0, 4, "獎學金金額", None).unwrap();
-    sheet.write_string(0, 5, "備註", None).unwrap();
-    sheet.write_string(0, 6, "是否領獎", None).unwrap();
-    sheet.write_string(0, 7, "領獎日期", None).unwrap();
-
-    for (i, row) in all_records.iter().enumerate() {
-        let i = (i + 1) as u32;
-        sheet.write_string(i, 0, &row.0, None).unwrap();
-        sheet.write_string(i, 1, &row.1, None).unwrap();
-        sheet.write_number(i, 2, row.2 as f64, None).unwrap();
-        sheet.write_string(i, 3, &row.3, None).unwrap();
-        if let Some(amount) = row.4 {
-            sheet.write_number(i, 4, amount as f64, None).unwrap();
-        }
-        sheet.write_string(i, 5, &row.5.clone().unwrap_or_default(), None).unwrap();
-        sheet.write_string(i, 6, if row.6 { "是" } else { "否" }, None).unwrap();
-        sheet.write_string(i, 7, &row.7.clone().unwrap_or_default(), None).unwrap();
-    }
-
-    workbook.close().unwrap();
-
-    match std::fs::read(filepath) {
-        Ok(data) => HttpResponse::Ok()
-            .content_type("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet")
-            .append_header(("Content-Disposition", "attachment; filename=scholarship_result.xlsx"))
-            .body(data),
-        Err(e) => {
-            println!("讀取 Excel 失敗: {}", e);
-            HttpResponse::InternalServerError().body("讀取 Excel 錯誤")
-        }
+    let mut table = ExportTable::new(&SCHOLARSHIP_COLUMNS);
+    for row in all_records {
+        table.push(vec![
+            row.0.into(),
+            row.1.into(),
+            row.2.into(),
+            row.3.into(),
+            row.4.into(),
+            row.5.unwrap_or_default().into(),
+            if row.6 { "是" } else { "否" }.into(),
+            row.7.unwrap_or_default().into(),
+        ]);
     }
-}
+    Ok(table)
+}
\ No newline at end of file
@@ -1,11 +1,16 @@
 use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::etag::attendance_etag;
+use crate::api::export::{ExportColumn, ExportFormat, ExportQuery, ExportTable};
 use actix_session::Session;
-use actix_web::{post, web, HttpRequest, HttpResponse};
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
 use serde::{Deserialize, Serialize};
 use sqlx::MySqlPool;
 use chrono::NaiveDate;
-use xlsxwriter::Workbook;
-#[derive(Deserialize, Debug)]
+use utoipa::ToSchema;
+#[derive(Deserialize, Debug, ToSchema)]
 enum CRUD {
     #[serde(rename = "update")]
     Update,
@@ -14,29 +19,43 @@ enum CRUD {
     #[serde(rename = "delete")]
     Delete,
 }
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct QueryScoreInfoForm {
     date: NaiveDate,
     exam_type: String,
     crud_type:CRUD,
 }
-#[derive(Serialize)]
-struct ScoreInfo {
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ScoreInfo {
     student_id: String,
     status:String,
     correct_number: Option<i32>,
     notes: Option<String>,
 }
+const SCORE_COLUMNS: [ExportColumn; 4] = [
+    ExportColumn { key: "student_id", title: "學號" },
+    ExportColumn { key: "status", title: "請假/缺考" },
+    ExportColumn { key: "correct_number", title: "答對題數" },
+    ExportColumn { key: "notes", title: "備註" },
+];
+
+#[utoipa::path(
+    tag = "scores",
+    responses((status = 200, description = "crud_type 為 query 時回傳可修改後上傳的成績檔案，其他為 JSON", content(
+        (Vec<ScoreInfo> = "application/json"),
+        (Vec<u8> = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+    ))),
+)]
 #[post("/api/query_score_info")]
 async fn query_score_info(
     pool: web::Data<MySqlPool>,
     req: HttpRequest,
     session: Session,
     data: web::Json<QueryScoreInfoForm>,
-) -> HttpResponse {
-    if !is_authorization(req, session.clone()) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
+    export: web::Query<ExportQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let format = ExportFormat::negotiate(&export, req.headers());
+    is_authorization(req, session.clone(), Role::ReadOnly)?;
     let exam_session_result = sqlx::query!(
         r#"
         SELECT SN FROM ExamSessions
@@ -48,11 +67,14 @@ async fn query_score_info(
     .await;
     let exam_session_sn = match exam_session_result {
         Ok(record) => record.SN,
-        Err(_) => return HttpResponse::NotFound().body("未找到對應的考試場次"),
+        Err(sqlx::Error::RowNotFound) => {
+            return Err(ApiError::new(ErrorCode::ExamSessionNotFound, t!(ExamSessionNotMatched)))
+        }
+        Err(err) => return Err(ApiError::database(t!(QueryExamSessionFailed), err)),
     };
     if let CRUD::Update = data.crud_type {
         if let Err(_) = session.insert("modify_exam_session_sn", exam_session_sn) {
-            return HttpResponse::InternalServerError().body("無法存入 session");
+            return Err(ApiError::internal(t!(SessionSaveFailed)));
         }
     }
     let exam_attendance_result = sqlx::query!(
@@ -67,7 +89,7 @@ async fn query_score_info(
     .await;
     let exam_attendance_records = match exam_attendance_result {
         Ok(records) => records,
-        Err(_) => return HttpResponse::InternalServerError().body("查詢考試成績資料失敗"),
+        Err(err) => return Err(ApiError::database(t!(QueryScoresFailed), err)),
     };
 
     if let CRUD::Delete = data.crud_type {
@@ -86,62 +108,87 @@ async fn query_score_info(
             });
         }
         if let Err(_) = session.insert("delete_exam_session_sn", exam_session_sn) {
-            return HttpResponse::InternalServerError().body("無法存入 session，請再試一次");
+            return Err(ApiError::internal(t!(SessionSaveRetry)));
         }
-        return HttpResponse::Ok().json(score_info);
+        return Ok(HttpResponse::Ok().json(score_info));
     };
 
-    let output_filepath = "./uploads/exam_score_excel.xlsx";
-    let workbook = Workbook::new(output_filepath).expect("Failed to create workbook");
-    let mut worksheet = workbook.add_worksheet(None).unwrap();
-
-    // 寫入第一行：傳過來的 date 和 exam_type
-    worksheet.write_string(0, 0, &format!("考試日期: {}", data.date), None).unwrap();
-    worksheet.write_string(0, 1, &format!("考試類別: {}", data.exam_type), None).unwrap();
-
-    // 寫入第二行：標題
-    worksheet.write_string(1, 0, "學號", None).unwrap();
-    worksheet.write_string(1, 1, "請假/缺考", None).unwrap();
-    worksheet.write_string(1, 2, "答對題數", None).unwrap();
-    worksheet.write_string(1, 3, "備註", None).unwrap();
-
-    // 寫入資料
-    for (i, record) in exam_attendance_records.iter().enumerate() {
-        let row = (i + 2) as u32; // 從第三行開始寫入資料
-
-        // 學號
-        worksheet.write_string(row, 0, &record.StudentID, None).unwrap();
-
-        // 請假/缺考
+    // 第一列為考試資訊，/api/modify_exam_score 上傳修改時會讀取
+    let mut table = ExportTable::new(&SCORE_COLUMNS).with_preamble(vec![
+        format!("考試日期: {}", data.date),
+        format!("考試類別: {}", data.exam_type),
+    ]);
+    for record in exam_attendance_records.iter() {
         let status = match (record.IsAbsent, record.IsExcused) {
             (Some(1), Some(1)) => "請假",
             (Some(1), Some(0)) => "缺考",
             _ => "無",       // 其他情況
         };
-        worksheet.write_string(row, 1, status, None).unwrap();
-
-        // 答對題數
-        worksheet.write_number(row, 2, record.CorrectAnswersCount.unwrap() as f64, None).unwrap();
-
-        // 備註
-        worksheet.write_string(row, 3, &<std::option::Option<std::string::String> as Clone>::clone(&record.Notes).unwrap_or_default(), None).unwrap();
+        table.push(vec![
+            record.StudentID.as_str().into(),
+            status.into(),
+            record.CorrectAnswersCount.into(),
+            record.Notes.clone().unwrap_or_default().into(),
+        ]);
     }
+    table.respond(format.unwrap_or(ExportFormat::Xlsx), "exam_score_excel")
+}
 
-    // 關閉並保存 Excel 文件
-    workbook.close().unwrap();
+/// 以場次 SN 取得場次內所有成績，回應的 ETag 用於
+/// /api/update_exam_score/{sn}、/api/modify_exam_score/{sn} 與 /api/delete_exam_score/{sn} 的 If-Match
+#[utoipa::path(tag = "scores", responses((status = 200, description = "場次內所有成績，ETag 標頭為目前的版本", body = Vec<ScoreInfo>)))]
+#[get("/api/exam_scores/{sn}")]
+async fn get_exam_scores(
+    path: web::Path<i32>,
+    pool: web::Data<MySqlPool>,
+    req: HttpRequest,
+    session: Session,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session, Role::ReadOnly)?;
+    exam_scores_response(pool.get_ref(), path.into_inner()).await
+}
 
-    match std::fs::read(output_filepath) {
-        Ok(file_data) => HttpResponse::Ok()
-            .content_type("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet")
-            .append_header((
-                "Content-Disposition",
-                "attachment; filename=exam_score_excel.xlsx",
-            ))
-            .body(file_data),
-        Err(err) => {
-            println!("Error reading generated file: {}", err);
-            HttpResponse::InternalServerError()
-                .body("Failed to generate or retrieve result Excel file")
-        }
+/// 場次內所有成績與代表目前版本的 ETag，/api/v2/exam-sessions/{sn}/attendance 共用
+pub(crate) async fn exam_scores_response(pool: &MySqlPool, exam_session_sn: i32) -> Result<HttpResponse, ApiError> {
+    let mut conn = pool.acquire().await?;
+    let exists = sqlx::query_scalar::<_, i32>("SELECT SN FROM ExamSessions WHERE SN = ?")
+        .bind(exam_session_sn)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|err| ApiError::database(t!(QueryExamSessionFailed), err))?;
+    if exists.is_none() {
+        return Err(ApiError::new(ErrorCode::ExamSessionNotFound, t!(ExamSessionNotFound)));
     }
+    let records = sqlx::query!(
+        r#"
+        SELECT StudentID, IsAbsent, IsExcused, CorrectAnswersCount, Notes
+        FROM ExamAttendance
+        WHERE ExamSession_SN = ?
+        ORDER BY StudentID
+        "#,
+        exam_session_sn
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|err| ApiError::database(t!(QueryScoresFailed), err))?;
+    let etag = attendance_etag(&mut conn, exam_session_sn, false)
+        .await
+        .map_err(|err| ApiError::database(t!(QueryScoresFailed), err))?;
+    let score_info: Vec<ScoreInfo> = records
+        .into_iter()
+        .map(|record| {
+            let status = match (record.IsAbsent, record.IsExcused) {
+                (Some(1), Some(1)) => "請假",
+                (Some(1), Some(0)) => "缺考",
+                _ => "出席",
+            };
+            ScoreInfo {
+                student_id: record.StudentID,
+                status: status.to_string(),
+                correct_number: record.CorrectAnswersCount,
+                notes: record.Notes,
+            }
+        })
+        .collect();
+    Ok(HttpResponse::Ok().insert_header(("ETag", etag)).json(score_info))
 }
\ No newline at end of file
@@ -0,0 +1,52 @@
+use actix_session::Session;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use crate::api::session_registry::{SessionRecord, SessionRegistry};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Deserialize, IntoParams)]
+struct QueryParams {
+    username: Option<String>, // 不填則列出所有使用者
+}
+
+#[derive(Serialize, ToSchema)]
+struct SessionInfo {
+    #[serde(flatten)]
+    record: SessionRecord,
+    is_current: bool, // 是否為目前發出請求的 session
+}
+
+#[utoipa::path(tag = "users", responses((status = 200, description = "登入中的 session", body = Vec<SessionInfo>)))]
+#[get("/api/query_sessions")]
+async fn query_sessions(
+    req: HttpRequest,
+    session: Session,
+    registry: web::Data<SessionRegistry>,
+    params: web::Query<QueryParams>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Admin)?;
+    let records = match &params.username {
+        Some(username) => registry.list(username).await,
+        None => registry.list_all().await,
+    };
+    let records = match records {
+        Ok(records) => records,
+        Err(err) => {
+            eprintln!("Redis error: {:?}", err);
+            return Err(ApiError::internal(t!(QuerySessionsFailed)));
+        }
+    };
+    let current_session_id: Option<String> = session.get("session_id").unwrap_or(None);
+    let sessions: Vec<SessionInfo> = records
+        .into_iter()
+        .map(|record| SessionInfo {
+            is_current: current_session_id.as_deref() == Some(record.session_id.as_str()),
+            record,
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(sessions))
+}
@@ -0,0 +1,49 @@
+use actix_session::Session;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use serde::Serialize;
+use sqlx::MySqlPool;
+use sqlx::Row;
+use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+struct UserInfo {
+    id: i32,
+    username: String,
+    role: String,
+    is_disabled: bool,
+}
+
+#[utoipa::path(tag = "users", responses((status = 200, description = "所有使用者", body = Vec<UserInfo>)))]
+#[get("/api/query_users")]
+async fn query_users(
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session, Role::Admin)?;
+    let query = r#"
+        SELECT id, username, role, is_disabled
+        FROM users
+        ORDER BY id
+    "#;
+    let rows = match sqlx::query(query).fetch_all(db_pool.get_ref()).await {
+        Ok(rows) => rows,
+        Err(err) => {
+            return Err(ApiError::database(t!(QueryUsersFailed), err));
+        }
+    };
+    let users: Vec<UserInfo> = rows
+        .iter()
+        .map(|row| UserInfo {
+            id: row.get("id"),
+            username: row.get("username"),
+            role: row.get("role"),
+            is_disabled: row.get("is_disabled"),
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(users))
+}
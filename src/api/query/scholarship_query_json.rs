@@ -1,18 +1,21 @@
 use actix_web::{post, web, HttpRequest, HttpResponse};
 use actix_session::Session;
-use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use sqlx::MySqlPool;
-use crate::api::lib::is_authorization;
+use crate::api::lib::{academic_year_range, is_authorization};
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
+use utoipa::ToSchema;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct ScholarshipQueryForm {
     status: String,                  // all | claimed | unclaimed
     academic_year: Option<u32>,
     exam_academic_year: Option<u32>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct ScholarshipRow {
     student_id: String,
     name: String,
@@ -24,29 +27,26 @@ pub struct ScholarshipRow {
     received_date: Option<String>,  // Some for claimed, None for unclaimed
 }
 
+#[utoipa::path(tag = "scholarships", responses((status = 200, description = "獎學金名單", body = Vec<ScholarshipRow>)))]
 #[post("/api/query_scholarship_json")]
 pub async fn query_scholarship_json(
     req: HttpRequest,
     session: Session,
     db: web::Data<MySqlPool>,
     form: web::Form<ScholarshipQueryForm>,
-) -> HttpResponse {
-    if !is_authorization(req, session) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期");
-    }
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session, Role::ReadOnly)?;
 
     let (start_date, end_date) = match form.academic_year {
         Some(year) => {
-            let start = NaiveDate::from_ymd_opt((year as i32) + 1911, 9, 1).unwrap();
-            let end = NaiveDate::from_ymd_opt((year as i32) + 1912, 8, 31).unwrap();
+            let (start, end) = academic_year_range(year, 9).map_err(|e| e.with_field("academic_year"))?;
             (Some(start), Some(end))
         }
         None => (None, None),
     };
     let (exam_start, exam_end) = match form.exam_academic_year {
         Some(y) => {
-            let s = NaiveDate::from_ymd_opt((y as i32) + 1911, 9, 1).unwrap();
-            let e = NaiveDate::from_ymd_opt((y as i32) + 1912, 8, 31).unwrap();
+            let (s, e) = academic_year_range(y, 9).map_err(|e| e.with_field("exam_academic_year"))?;
             (Some(s), Some(e))
         }
         None => (None, None),
@@ -105,7 +105,7 @@ pub async fn query_scholarship_json(
             }
             Err(err) => {
                 println!("查詢已領獎學金錯誤: {}", err);
-                return HttpResponse::InternalServerError().body("查詢已領資料時發生錯誤");
+                return Err(ApiError::internal(t!(QueryClaimedFailed)));
             }
         }
     }
@@ -164,10 +164,10 @@ pub async fn query_scholarship_json(
             }
             Err(err) => {
                 println!("查詢未領獎學金錯誤: {}", err);
-                return HttpResponse::InternalServerError().body("查詢未領資料時發生錯誤");
+                return Err(ApiError::internal(t!(QueryUnclaimedFailed)));
             }
         }
     }
 
-    HttpResponse::Ok().json(result)
+    Ok(HttpResponse::Ok().json(result))
 }
@@ -1,30 +1,34 @@
 use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::messages::t;
 use actix_session::Session;
 use actix_web::{post, web, HttpRequest, HttpResponse};
 use serde::{Serialize,Deserialize};
 use sqlx::MySqlPool;
 use sqlx::Row;
 use chrono::NaiveDate;
-#[derive(Deserialize)]
+use utoipa::ToSchema;
+#[derive(Deserialize, ToSchema)]
+#[schema(as = SearchAbsentForm)]
 struct Data {
     date: NaiveDate,
 }
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct SearchAbsentAndExcused {
     student_id: String,
     status: String,
     notes: Option<String>,
 }
+#[utoipa::path(tag = "scores", responses((status = 200, description = "該日期場次的缺考與請假名單", body = Vec<SearchAbsentAndExcused>)))]
 #[post("/api/search_absent_and_excused")]
 async fn search_absent_and_excused(
     data: web::Form<Data>,
     req: HttpRequest,
     session: Session,
     db_pool: web::Data<MySqlPool>,
-) -> HttpResponse{
-    if !is_authorization(req, session) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session, Role::ReadOnly)?;
     let query = r#"
         select
             ea.StudentID,
@@ -44,21 +48,18 @@ async fn search_absent_and_excused(
     "#;
     let rows = match sqlx::query(query).bind(data.date).fetch_all(db_pool.get_ref()).await {
         Ok(rows) => rows,
-        Err(err) => {
-            println!("{:?}", err);
-            return HttpResponse::InternalServerError().body(format!("Internal server error.: {}", err));
-        }
+        Err(err) => return Err(ApiError::database(t!(InternalServerError), err)),
     };
     let mut info: Vec<SearchAbsentAndExcused> = Vec::new();
     for row in rows {
-        let student_id: String = row.try_get("StudentID").unwrap();
-        let is_excused: bool = row.try_get("IsExcused").unwrap();
-        let notes: Option<String> = row.try_get("Notes").unwrap();
+        let student_id: String = row.try_get("StudentID")?;
+        let is_excused: bool = row.try_get("IsExcused")?;
+        let notes: Option<String> = row.try_get("Notes")?;
         info.push(SearchAbsentAndExcused {
             student_id,
             status: if is_excused {"請假".to_string()} else {"缺考".to_string()},
             notes
         });
     }
-    HttpResponse::Ok().json(info)
+    Ok(HttpResponse::Ok().json(info))
 }
\ No newline at end of file
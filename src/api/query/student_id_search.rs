@@ -1,28 +1,39 @@
 use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::pass_rule::{effective_entrance_year, PassRecord};
+use crate::api::audit::AuditTarget;
+use crate::api::etag::etag_of;
 use actix_session::Session;
-use actix_web::{post, web, HttpRequest, HttpResponse};
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
 use serde::{Deserialize, Serialize};
 use sqlx::MySqlPool;
 use sqlx::Row;
 use chrono::NaiveDate;
+use utoipa::ToSchema;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
+#[schema(as = StudentIdSearchForm)]
 struct FromData {
     student_id: String,
     need_exam_attendance: bool,
 }
-#[derive(Serialize, Debug)]
-struct QueryResult {
+#[derive(Serialize, Debug, ToSchema)]
+#[schema(as = StudentInfo)]
+pub(crate) struct QueryResult {
     student_id: String,
     name: String,
     enrollment_status: String,
     student_attribute: String,
     is_passed: bool,
     passing_criteria: Option<String>,
+    pass_record: Option<PassRecord>, // 第一次達到通過標準的場次、日期與成立的條件
     notes: Option<String>,
+    entrance_year: Option<i32>, // 適用的入學年度，未明確設定時由學號推算
     exam_attendance: Vec<ExamAttendance>,
 }
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, ToSchema)]
 struct ExamAttendance{
     exam_date: Option<NaiveDate>,
     exam_type: String,
@@ -32,71 +43,25 @@ struct ExamAttendance{
     correct_answers_count: u16,
     exam_notes: Option<String>,
 }
+#[utoipa::path(tag = "students", responses((status = 200, description = "學生資料，並記住此學生供 /api/modify_student_info 使用", body = QueryResult)))]
 #[post("/api/student_id_search")]
 async fn student_id_search(
     from_data: web::Form<FromData>,
     req: HttpRequest,
     session: Session,
     db_pool: web::Data<MySqlPool>,
-) -> HttpResponse {
-    if !is_authorization(req, session.clone()) {
-        return HttpResponse::Unauthorized().body("Session 無效或過期，或是無效的 CSRF Token");
-    }
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::ReadOnly)?;
     let student_id = from_data.student_id.to_ascii_uppercase().clone();
-    //查詢學生資料
-    let query = r#"
-    SELECT 
-        si.StudentID, 
-        si.Name, 
-        es.Status AS EnrollmentStatus, 
-        sa.Attribute AS StudentAttribute, 
-        si.IsPassed, 
-        si.PassingCriteria, 
-        si.Notes
-    FROM 
-        StudentInfo si
-    LEFT JOIN 
-        EnrollmentStatus es 
-    ON 
-        si.EnrollmentStatus_SN = es.SN
-    LEFT JOIN 
-        StudentAttributes sa 
-    ON 
-        si.StudentAttribute_SN = sa.SN
-    WHERE 
-        si.StudentID = (?);
-    "#;
-    let info = match sqlx::query(query)
-        .bind(student_id.clone())
-        .fetch_one(db_pool.get_ref())
-        .await
-    {
-        Ok(info) => info,
-        Err(sqlx::Error::RowNotFound) => {
-            return HttpResponse::NotFound().body("此學號不存在");
-        }
-        Err(e) => {
-            println!("查詢學號時發生錯誤: {}", e);
-            return HttpResponse::InternalServerError().body("查詢學號時發生錯誤");
-        }
-    };
-    let mut result = QueryResult {
-        student_id: info.try_get(0).unwrap(),
-        name: info.try_get(1).unwrap(),
-        enrollment_status: info.try_get(2).unwrap(),
-        student_attribute: info.try_get(3).unwrap(),
-        is_passed: info.try_get(4).unwrap(),
-        passing_criteria: info.try_get(5).unwrap(),
-        notes: info.try_get(6).unwrap(),
-        exam_attendance: Vec::new()
-    };
+    let (mut result, explicit_entrance_year) = load_student_info(db_pool.get_ref(), &student_id).await?;
     if !from_data.need_exam_attendance {
-        session.insert("modify_student_id", &result.student_id).unwrap();
-        session.insert("modify_name", &result.name).unwrap();
-        session.insert("modify_enrollment_status", &result.enrollment_status).unwrap();
-        session.insert("modify_student_attribute", &result.student_attribute).unwrap();
-        session.insert("modify_notes", &result.notes).unwrap();
-        return HttpResponse::Ok().json(result);
+        session.insert("modify_student_id", &result.student_id)?;
+        session.insert("modify_name", &result.name)?;
+        session.insert("modify_enrollment_status", &result.enrollment_status)?;
+        session.insert("modify_student_attribute", &result.student_attribute)?;
+        session.insert("modify_notes", &result.notes)?;
+        session.insert("modify_entrance_year", explicit_entrance_year)?;
+        return Ok(HttpResponse::Ok().json(result));
     }
     //查詢此學生的考試紀錄
     let query = r#"
@@ -123,20 +88,103 @@ async fn student_id_search(
         .await
     {
         Ok(exam_attendance) => exam_attendance,
-        Err(e) => {
-            return HttpResponse::InternalServerError().body(format!("查詢考試紀錄時發生錯誤:{}",e));
-        }
+        Err(e) => return Err(ApiError::database(t!(QueryExamRecordsFailed), e)),
     };
     for exam_attendance_row in exam_attendance_rows.iter() {
         result.exam_attendance.push( ExamAttendance{
-            exam_date: exam_attendance_row.try_get(0).expect("無法讀取 exam_date"),
-            exam_type: exam_attendance_row.try_get(1).expect("無法讀取 exam_type"),
-            session_notes:exam_attendance_row.try_get(2).expect("無法讀取 session_notes"),
-            is_absent:exam_attendance_row.try_get(3).expect("無法讀取 is_absent"),
-            is_excused:exam_attendance_row.try_get(4).expect("無法讀取 is_excused"),
-            correct_answers_count:exam_attendance_row.try_get(5).expect("無法讀取 correct_answers_count"),
-            exam_notes:exam_attendance_row.try_get(6).expect("無法讀取 exam_notes"),
+            exam_date: exam_attendance_row.try_get(0)?,
+            exam_type: exam_attendance_row.try_get(1)?,
+            session_notes:exam_attendance_row.try_get(2)?,
+            is_absent:exam_attendance_row.try_get(3)?,
+            is_excused:exam_attendance_row.try_get(4)?,
+            correct_answers_count:exam_attendance_row.try_get(5)?,
+            exam_notes:exam_attendance_row.try_get(6)?,
         });
     }
-    HttpResponse::Ok().json(result)
+    Ok(HttpResponse::Ok().json(result))
+}
+
+/// 以學號取得學生資料，回應的 ETag 用於 /api/modify_student_info/{student_id} 的 If-Match
+/// 與 student_id_search 不同，不會在 session 中留下修改用的資料
+#[utoipa::path(tag = "students", responses((status = 200, description = "學生資料，ETag 標頭為目前的版本", body = QueryResult)))]
+#[get("/api/student_info/{student_id}")]
+async fn get_student_info(
+    path: web::Path<String>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session, Role::ReadOnly)?;
+    student_info_response(db_pool.get_ref(), &path.into_inner().to_ascii_uppercase()).await
+}
+
+/// 學生資料與代表目前版本的 ETag，/api/v2/students/{student_id} 共用
+pub(crate) async fn student_info_response(db_pool: &MySqlPool, student_id: &str) -> Result<HttpResponse, ApiError> {
+    let (result, _) = load_student_info(db_pool, student_id).await?;
+    let snapshot = AuditTarget::Student(student_id)
+        .snapshot(&mut *db_pool.acquire().await?)
+        .await
+        .map_err(|e| ApiError::database(t!(QueryStudentIdFailed), e))?;
+    let mut response = HttpResponse::Ok();
+    if let Some(snapshot) = snapshot {
+        response.insert_header(("ETag", etag_of(&snapshot)));
+    }
+    Ok(response.json(result))
+}
+
+/// 查詢學生資料，一併回傳明確設定的入學年度（未設定時為 None）
+async fn load_student_info(db_pool: &MySqlPool, student_id: &str) -> Result<(QueryResult, Option<i32>), ApiError> {
+    //查詢學生資料
+    let query = r#"
+    SELECT 
+        si.StudentID, 
+        si.Name, 
+        es.Status AS EnrollmentStatus, 
+        sa.Attribute AS StudentAttribute, 
+        si.IsPassed, 
+        si.PassingCriteria, 
+        si.Notes,
+        si.EntranceYear,
+        si.PassedSession_SN,
+        si.PassedDate,
+        si.PassCriteria_SN,
+        si.PassingConditions
+    FROM 
+        StudentInfo si
+    LEFT JOIN 
+        EnrollmentStatus es 
+    ON 
+        si.EnrollmentStatus_SN = es.SN
+    LEFT JOIN 
+        StudentAttributes sa 
+    ON 
+        si.StudentAttribute_SN = sa.SN
+    WHERE 
+        si.StudentID = (?);
+    "#;
+    let info = match sqlx::query(query)
+        .bind(student_id)
+        .fetch_one(db_pool)
+        .await
+    {
+        Ok(info) => info,
+        Err(sqlx::Error::RowNotFound) => {
+            return Err(ApiError::new(ErrorCode::StudentNotFound, t!(StudentIdNotFound)));
+        }
+        Err(e) => return Err(ApiError::database(t!(QueryStudentIdFailed), e)),
+    };
+    let explicit_entrance_year: Option<i32> = info.try_get(7)?;
+    let result = QueryResult {
+        student_id: info.try_get(0)?,
+        name: info.try_get(1)?,
+        enrollment_status: info.try_get(2)?,
+        student_attribute: info.try_get(3)?,
+        is_passed: info.try_get(4)?,
+        passing_criteria: info.try_get(5)?,
+        pass_record: PassRecord::from_columns(info.try_get(8)?, info.try_get(9)?, info.try_get(10)?, info.try_get(11)?),
+        notes: info.try_get(6)?,
+        entrance_year: effective_entrance_year(explicit_entrance_year, student_id),
+        exam_attendance: Vec::new()
+    };
+    Ok((result, explicit_entrance_year))
 }
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// 使用者角色，權限由高到低為 Admin > Staff > ReadOnly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+pub enum Role {
+    #[serde(rename = "readonly")]
+    ReadOnly, //唯讀：只能查詢與匯出
+    #[serde(rename = "staff")]
+    Staff, //助理：可以新增、修改資料
+    #[serde(rename = "admin")]
+    Admin, //管理員：可以刪除資料、管理使用者與通過規則
+}
+
+impl Role {
+    /// 對應資料庫 users.role 欄位的值
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::ReadOnly => "readonly",
+            Role::Staff => "staff",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Option<Role> {
+        match value {
+            "readonly" => Some(Role::ReadOnly),
+            "staff" => Some(Role::Staff),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+
+    /// 是否擁有 required 以上的權限
+    pub fn satisfies(&self, required: Role) -> bool {
+        *self >= required
+    }
+}
@@ -0,0 +1,208 @@
+use actix_session::SessionExt;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpRequest};
+use chrono::{Local, NaiveDateTime};
+use redis::aio::MultiplexedConnection;
+use redis::{AsyncCommands, RedisResult};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::login_throttle::{client_ip, normalize_username};
+
+/// 與 SessionMiddleware 設定的 TTL 相同（3 小時）
+pub const SESSION_TTL_SECONDS: i64 = 3 * 60 * 60;
+const KEY_PREFIX: &str = "cpe:user_sessions:";
+
+/// 只在 session 仍登記在表上時才寫回，讀取後才被撤銷的 session 不會被重新寫入
+const TOUCH_SCRIPT: &str = r#"
+if redis.call('HEXISTS', KEYS[1], ARGV[1]) == 0 then
+    return 0
+end
+redis.call('HSET', KEYS[1], ARGV[1], ARGV[2])
+redis.call('EXPIRE', KEYS[1], ARGV[3])
+return 1
+"#;
+
+/// 每個登入 session 的紀錄，存放在 Redis hash `cpe:user_sessions:{username}`
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct SessionRecord {
+    pub session_id: String,
+    pub username: String,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub login_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+}
+
+impl SessionRecord {
+    pub fn new(session_id: String, username: String, req: &HttpRequest) -> Self {
+        let now = Local::now().naive_local();
+        SessionRecord {
+            session_id,
+            username,
+            ip: Some(client_ip(req)).filter(|ip| !ip.is_empty()),
+            user_agent: req
+                .headers()
+                .get("User-Agent")
+                .and_then(|header| header.to_str().ok())
+                .map(|ua| ua.to_string()),
+            login_at: now,
+            last_seen_at: now,
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        (Local::now().naive_local() - self.last_seen_at).num_seconds() > SESSION_TTL_SECONDS
+    }
+}
+
+/// 登入中的 session 登記表，用來列出與撤銷使用者的 session
+#[derive(Clone)]
+pub struct SessionRegistry {
+    conn: MultiplexedConnection,
+}
+
+impl SessionRegistry {
+    pub async fn connect(redis_url: &str) -> RedisResult<Self> {
+        let client = redis::Client::open(redis_url)?;
+        let conn = client.get_multiplexed_async_connection().await?;
+        Ok(SessionRegistry { conn })
+    }
+
+    /// 帳號不分大小寫，管理員以任何大小寫輸入帳號都能撤銷同一位使用者的 session
+    fn key(username: &str) -> String {
+        format!("{}{}", KEY_PREFIX, normalize_username(username))
+    }
+
+    pub async fn register(&self, record: &SessionRecord) -> RedisResult<()> {
+        let mut conn = self.conn.clone();
+        let key = Self::key(&record.username);
+        let value = serde_json::to_string(record).unwrap_or_default();
+        let _: () = conn.hset(&key, &record.session_id, value).await?;
+        let _: () = conn.expire(&key, SESSION_TTL_SECONDS).await?;
+        Ok(())
+    }
+
+    /// 確認 session 仍有效，有效時一併更新最後使用時間
+    pub async fn touch(&self, username: &str, session_id: &str) -> RedisResult<bool> {
+        let mut conn = self.conn.clone();
+        let key = Self::key(username);
+        let value: Option<String> = conn.hget(&key, session_id).await?;
+        let Some(mut record) = value.and_then(|v| serde_json::from_str::<SessionRecord>(&v).ok()) else {
+            return Ok(false);
+        };
+        if record.is_expired() {
+            let _: () = conn.hdel(&key, session_id).await?;
+            return Ok(false);
+        }
+        record.last_seen_at = Local::now().naive_local();
+        let value = serde_json::to_string(&record).unwrap_or_default();
+        let updated: i64 = redis::Script::new(TOUCH_SCRIPT)
+            .key(&key)
+            .arg(session_id)
+            .arg(value)
+            .arg(SESSION_TTL_SECONDS)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(updated == 1)
+    }
+
+    /// 列出某位使用者仍有效的 session，順便清除已過期的紀錄
+    pub async fn list(&self, username: &str) -> RedisResult<Vec<SessionRecord>> {
+        let mut conn = self.conn.clone();
+        let key = Self::key(username);
+        let entries: Vec<(String, String)> = conn.hgetall(&key).await?;
+        let mut records = Vec::new();
+        for (session_id, value) in entries {
+            match serde_json::from_str::<SessionRecord>(&value) {
+                Ok(record) if !record.is_expired() => records.push(record),
+                _ => {
+                    let _: () = conn.hdel(&key, &session_id).await?;
+                }
+            }
+        }
+        records.sort_by(|a, b| b.last_seen_at.cmp(&a.last_seen_at));
+        Ok(records)
+    }
+
+    /// 列出所有使用者仍有效的 session
+    pub async fn list_all(&self) -> RedisResult<Vec<SessionRecord>> {
+        let keys = scan_keys(&self.conn, &format!("{}*", KEY_PREFIX)).await?;
+        let mut records = Vec::new();
+        for key in keys {
+            records.extend(self.list(&key[KEY_PREFIX.len()..]).await?);
+        }
+        Ok(records)
+    }
+
+    /// 撤銷單一 session，回傳是否有撤銷到
+    pub async fn revoke(&self, username: &str, session_id: &str) -> RedisResult<bool> {
+        let mut conn = self.conn.clone();
+        let removed: i64 = conn.hdel(Self::key(username), session_id).await?;
+        Ok(removed > 0)
+    }
+
+    /// 撤銷某位使用者所有的 session，回傳撤銷數量
+    pub async fn revoke_all(&self, username: &str) -> RedisResult<usize> {
+        let mut conn = self.conn.clone();
+        let key = Self::key(username);
+        let count: usize = conn.hlen(&key).await?;
+        let _: () = conn.del(&key).await?;
+        Ok(count)
+    }
+
+    /// 刪除、停用帳號或變更角色、密碼後撤銷此使用者所有的 session
+    /// 失敗時回傳 503，讓管理員知道變更雖已寫入，但使用者仍維持登入
+    pub async fn revoke_after_change(&self, username: &str) -> Result<usize, ApiError> {
+        self.revoke_all(username).await.map_err(|err| {
+            eprintln!("Redis error: {:?}", err);
+            ApiError::new(ErrorCode::ServiceUnavailable, t!(RevokeAfterChangeFailed))
+        })
+    }
+}
+
+/// 以 SCAN 列出符合 pattern 的 key，不使用 KEYS 以免在共用的 Redis 上阻塞其他指令
+pub(crate) async fn scan_keys(conn: &MultiplexedConnection, pattern: &str) -> RedisResult<Vec<String>> {
+    let mut conn = conn.clone();
+    let mut iter = conn.scan_match::<_, String>(pattern).await?;
+    let mut keys = Vec::new();
+    while let Some(key) = iter.next_item().await {
+        keys.push(key);
+    }
+    Ok(keys)
+}
+
+/// 每個請求檢查 session 是否已被撤銷，被撤銷的 session 會直接清除，後續的 is_authorization 就會驗證失敗
+/// 無法連線 Redis 時無從確認 session 是否已被撤銷，已登入的請求一律回傳 503，不會放行
+/// 需註冊在 SessionMiddleware 與 localize 內層，錯誤訊息才會使用請求的語言
+pub async fn session_guard(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let session = req.get_session();
+    let is_logged_in = session.get::<bool>("is_logged_in").unwrap_or(None).unwrap_or(false);
+    if is_logged_in {
+        let username = session.get::<String>("username").unwrap_or(None);
+        let session_id = session.get::<String>("session_id").unwrap_or(None);
+        let registry = req.app_data::<web::Data<SessionRegistry>>().cloned();
+        match (username, session_id, registry) {
+            (Some(username), Some(session_id), Some(registry)) => {
+                match registry.touch(&username, &session_id).await {
+                    Ok(true) => (),
+                    Ok(false) => session.purge(),
+                    Err(err) => {
+                        eprintln!("Redis error: {:?}", err);
+                        return Err(ApiError::new(ErrorCode::ServiceUnavailable, t!(SessionCheckUnavailable)).into());
+                    }
+                }
+            }
+            // 登記表上線前建立的 session 沒有 session_id，一律要求重新登入
+            (_, None, Some(_)) | (None, _, Some(_)) => session.purge(),
+            _ => (),
+        }
+    }
+    next.call(req).await
+}
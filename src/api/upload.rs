@@ -0,0 +1,377 @@
+use actix_multipart::Multipart;
+use calamine::{Data, Range, Reader, Sheets};
+use serde::{Deserialize, Serialize};
+use futures_util::StreamExt as _;
+use rand::Rng;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use utoipa::IntoParams;
+
+/// 可以上傳的試算表格式
+pub const SPREADSHEET_EXTENSIONS: [&str; 3] = ["xlsx", "ods", "csv"];
+/// 上傳檔案大小上限（10 MB）
+pub const MAX_UPLOAD_SIZE: usize = 10 * 1024 * 1024;
+/// 檔案以外的表單欄位大小上限
+const MAX_FIELD_SIZE: usize = 4 * 1024;
+/// 暫存檔的存放位置與檔名前綴，只會清除符合前綴的檔案，不會動到範本檔
+const TEMP_DIR: &str = "./uploads";
+const TEMP_PREFIX: &str = "tmp_";
+/// 改用 TempFile 之前各 API 使用的固定檔名
+const LEGACY_FILES: [&str; 9] = [
+    "temp_file.xlsx",
+    "result_file.xlsx",
+    "exam_score.xlsx",
+    "scholarship.xlsx",
+    "students_info.xlsx",
+    "modify_exam_score.xlsx",
+    "delete_scholarship.xlsx",
+    "scholarship_result.xlsx",
+    "exam_score_excel.xlsx",
+];
+
+/// 要讀取的工作表，未指定時使用第一個工作表
+/// all_sheets 為 true 時讀取所有非空白的工作表（例如一個場次一個工作表）
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, IntoParams)]
+pub struct SheetSelection {
+    pub sheet: Option<String>,
+    #[serde(default)]
+    pub all_sheets: bool,
+}
+
+/// 讀出的工作表
+pub struct Sheet {
+    pub name: String,
+    pub range: Range<Data>,
+}
+
+impl Sheet {
+    /// 讀出的範圍左上角在工作表中的位置（列, 欄，從 0 開始）
+    /// calamine 會略過開頭的空白列與空白欄，因此不一定是 (0, 0)
+    pub fn origin(&self) -> (u32, u32) {
+        self.range.start().unwrap_or((0, 0))
+    }
+
+    /// 範圍內第 index 列（從 0 開始）在 Excel 中的列號（從 1 開始）
+    pub fn line_number(&self, index: usize) -> usize {
+        self.origin().0 as usize + index + 1
+    }
+
+    /// 範圍內第 index 欄（從 0 開始）在 Excel 中的欄號（從 1 開始）
+    pub fn column_number(&self, index: usize) -> usize {
+        self.origin().1 as usize + index + 1
+    }
+}
+
+/// 讀進記憶體的上傳檔案
+pub struct UploadedFile {
+    pub filename: String,
+    pub data: Vec<u8>,
+}
+
+impl UploadedFile {
+    /// 直接從記憶體解析活頁簿，不需要寫入暫存檔
+    pub fn open_workbook(&self) -> Result<Sheets<Cursor<&[u8]>>, calamine::Error> {
+        calamine::open_workbook_auto_from_rs(Cursor::new(self.data.as_slice()))
+    }
+
+    /// 依選擇讀取工作表，找不到指定的工作表時列出檔案中所有的工作表名稱
+    /// CSV 檔只有一個工作表，不受選擇影響
+    pub fn read_sheets(&self, selection: &SheetSelection) -> Result<Vec<Sheet>, ApiError> {
+        if self.extension().as_deref() == Some("csv") {
+            return Ok(vec![self.read_csv()?]);
+        }
+        let mut workbook = self.open_workbook().map_err(|err| {
+            println!("Failed to open Excel file: {}", err);
+            ApiError::new(ErrorCode::InvalidFile, t!(InvalidExcelFile))
+        })?;
+        let names = workbook.sheet_names();
+        let selected: Vec<String> = match &selection.sheet {
+            _ if selection.all_sheets => names.clone(),
+            Some(sheet) if names.contains(sheet) => vec![sheet.clone()],
+            Some(sheet) => {
+                return Err(ApiError::new(
+                    ErrorCode::SheetNotFound,
+                    t!(SheetNotFound, sheet, names.join(&t!(ListSeparator))),
+                ));
+            }
+            None => names.iter().take(1).cloned().collect(),
+        };
+
+        let mut sheets = Vec::with_capacity(selected.len());
+        for name in selected {
+            let range = workbook.worksheet_range(&name).map_err(|err| {
+                ApiError::new(ErrorCode::InvalidFile, t!(ReadSheetFailed, name, err))
+            })?;
+            // 匯入所有工作表時略過空白的工作表
+            if selection.all_sheets && range.is_empty() {
+                continue;
+            }
+            sheets.push(Sheet { name, range });
+        }
+        if sheets.is_empty() {
+            return Err(ApiError::new(ErrorCode::InvalidFile, t!(FileHasNoData)));
+        }
+        Ok(sheets)
+    }
+
+    /// 將 CSV 轉成與 Excel 相同的儲存格格式，之後走同樣的檢查流程
+    /// 有 BOM 時依 BOM 判斷編碼，否則先嘗試 UTF-8，失敗時視為 Big5
+    fn read_csv(&self) -> Result<Sheet, ApiError> {
+        let text = match encoding_rs::Encoding::for_bom(&self.data) {
+            Some((encoding, bom_length)) => encoding.decode_without_bom_handling(&self.data[bom_length..]).0,
+            None => match std::str::from_utf8(&self.data) {
+                Ok(text) => text.into(),
+                Err(_) => {
+                    let (text, _, had_errors) = encoding_rs::BIG5.decode(&self.data);
+                    if had_errors {
+                        return Err(ApiError::new(ErrorCode::InvalidFile, t!(UnknownCsvEncoding)));
+                    }
+                    text
+                }
+            },
+        };
+
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(text.as_bytes());
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let record = record.map_err(|e| ApiError::new(ErrorCode::InvalidFile, t!(InvalidCsv, e)))?;
+            rows.push(record.iter().map(csv_cell).collect::<Vec<_>>());
+        }
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if rows.is_empty() || width == 0 {
+            return Err(ApiError::new(ErrorCode::InvalidFile, t!(FileHasNoData)));
+        }
+
+        let mut range = Range::new((0, 0), (rows.len() as u32 - 1, width as u32 - 1));
+        for (r, row) in rows.into_iter().enumerate() {
+            for (c, cell) in row.into_iter().enumerate() {
+                range.set_value((r as u32, c as u32), cell);
+            }
+        }
+        let name = Path::new(&self.filename)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .map(|s| s.chars().filter(|c| !"[]:*?/\\".contains(*c)).take(31).collect::<String>())
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "Sheet1".to_string());
+        Ok(Sheet { name, range })
+    }
+
+    /// 只讀取一個工作表，未指定時使用第一個工作表
+    pub fn read_sheet(&self, sheet: Option<String>) -> Result<Sheet, ApiError> {
+        let selection = SheetSelection { sheet, all_sheets: false };
+        let mut sheets = self.read_sheets(&selection)?;
+        Ok(sheets.remove(0))
+    }
+
+    /// 檔案的副檔名（小寫）
+    pub fn extension(&self) -> Option<String> {
+        Path::new(&self.filename)
+            .extension()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_ascii_lowercase())
+    }
+}
+
+/// CSV 中一般的十進位數字視為數字，與 Excel 讀出的格式一致
+/// 有前導零（例如 00123）或科學記號等寫法保留為文字，避免學號等欄位被轉成數字
+fn csv_cell(value: &str) -> Data {
+    let value = value.trim();
+    if value.is_empty() {
+        Data::Empty
+    } else if let Some(number) = is_plain_decimal(value).then(|| value.parse::<f64>().ok()).flatten() {
+        Data::Float(number)
+    } else {
+        Data::String(value.to_string())
+    }
+}
+
+/// 是否為 `-?(0|[1-9][0-9]*)(\.[0-9]+)?` 形式的數字
+fn is_plain_decimal(value: &str) -> bool {
+    let unsigned = value.strip_prefix('-').unwrap_or(value);
+    let (integer, fraction) = match unsigned.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (unsigned, None),
+    };
+    let all_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    all_digits(integer)
+        && (integer == "0" || !integer.starts_with('0'))
+        && fraction.is_none_or(all_digits)
+}
+
+/// 讀取 multipart 中的檔案欄位，檢查副檔名與大小
+/// allowed_extensions 為允許的副檔名（小寫，不含點）
+pub async fn read_upload(
+    payload: &mut Multipart,
+    allowed_extensions: &[&str],
+) -> Result<UploadedFile, ApiError> {
+    let mut file: Option<(String, Vec<u8>)> = None;
+    while let Some(field) = payload.next().await {
+        let mut field = field.map_err(|e| ApiError::new(ErrorCode::InvalidFile, t!(ReadUploadFailed, e)))?;
+        let filename = field
+            .content_disposition()
+            .and_then(|cd| cd.get_filename())
+            .map(|name| name.to_string());
+        let limit = if filename.is_some() { MAX_UPLOAD_SIZE } else { MAX_FIELD_SIZE };
+
+        let mut data = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(|e| ApiError::new(ErrorCode::InvalidFile, t!(ReadUploadFailed, e)))?;
+            if data.len() + chunk.len() > limit {
+                return Err(ApiError::new(
+                    ErrorCode::FileTooLarge,
+                    t!(FileTooLarge, MAX_UPLOAD_SIZE / 1024 / 1024),
+                ));
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        match filename {
+            Some(filename) if file.is_none() => file = Some((filename, data)),
+            Some(_) => return Err(ApiError::new(ErrorCode::InvalidFile, t!(OneFileOnly))),
+            None => (),
+        }
+    }
+
+    let Some((filename, data)) = file else {
+        return Err(ApiError::new(ErrorCode::InvalidFile, t!(FileRequired)));
+    };
+    let upload = UploadedFile { filename, data };
+    let extension = upload.extension();
+    if !allowed_extensions.iter().any(|ext| Some(*ext) == extension.as_deref()) {
+        return Err(ApiError::new(ErrorCode::InvalidFile, t!(FileTypeRequired, allowed_extensions.join(&t!(ListSeparator)))));
+    }
+    if upload.data.is_empty() {
+        return Err(ApiError::new(ErrorCode::InvalidFile, t!(FileEmpty)));
+    }
+    Ok(upload)
+}
+
+/// 每個請求專用的暫存檔，離開作用域時自動刪除
+/// 用於 xlsxwriter 這類只能寫入檔案路徑的情況
+pub struct TempFile {
+    path: PathBuf,
+}
+
+impl TempFile {
+    pub fn new(extension: &str) -> std::io::Result<Self> {
+        std::fs::create_dir_all(TEMP_DIR)?;
+        let random_bytes: [u8; 16] = rand::thread_rng().gen();
+        let path = Path::new(TEMP_DIR).join(format!("{}{}.{}", TEMP_PREFIX, hex::encode(random_bytes), extension));
+        Ok(TempFile { path })
+    }
+
+    /// xlsxwriter 需要 &str 路徑
+    pub fn path_str(&self) -> &str {
+        self.path.to_str().unwrap_or_default()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// 清除先前異常結束時殘留的暫存檔，在伺服器啟動時呼叫
+pub fn cleanup_temp_files() {
+    let Ok(entries) = std::fs::read_dir(TEMP_DIR) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let is_temp = entry.file_name().to_str().is_some_and(|name| {
+            name.starts_with(TEMP_PREFIX)
+                || name.starts_with("passed_by_year_")
+                || LEGACY_FILES.contains(&name)
+        });
+        if is_temp {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn csv_file(data: Vec<u8>) -> UploadedFile {
+        UploadedFile { filename: "學生名單.csv".to_string(), data }
+    }
+
+    fn cells(sheet: &Sheet) -> Vec<Vec<Data>> {
+        sheet.range.rows().map(<[Data]>::to_vec).collect()
+    }
+
+    #[test]
+    fn csv_cell_converts_numbers() {
+        let cases = [
+            ("", Data::Empty),
+            ("   ", Data::Empty),
+            ("42", Data::Float(42.0)),
+            (" 3.5 ", Data::Float(3.5)),
+            ("-1", Data::Float(-1.0)),
+            ("0", Data::Float(0.0)),
+            ("0.5", Data::Float(0.5)),
+            ("00123", Data::String("00123".to_string())),
+            ("1e5", Data::String("1e5".to_string())),
+            ("+5", Data::String("+5".to_string())),
+            ("1.", Data::String("1.".to_string())),
+            (".5", Data::String(".5".to_string())),
+            ("B11012345", Data::String("B11012345".to_string())),
+            (" 王小明 ", Data::String("王小明".to_string())),
+            ("NaN", Data::String("NaN".to_string())),
+            ("inf", Data::String("inf".to_string())),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(csv_cell(input), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn read_csv_decodes_utf8() {
+        let sheet = csv_file("學號,姓名\nB11012345,王小明\n".as_bytes().to_vec()).read_csv().unwrap();
+        assert_eq!(sheet.name, "學生名單");
+        assert_eq!(
+            cells(&sheet),
+            vec![
+                vec![Data::String("學號".to_string()), Data::String("姓名".to_string())],
+                vec![Data::String("B11012345".to_string()), Data::String("王小明".to_string())],
+            ]
+        );
+    }
+
+    #[test]
+    fn read_csv_strips_bom() {
+        let mut data = b"\xEF\xBB\xBF".to_vec();
+        data.extend_from_slice("學號,答對題數\nB11012345,3\n".as_bytes());
+        let sheet = csv_file(data).read_csv().unwrap();
+        assert_eq!(sheet.range.get((0, 0)), Some(&Data::String("學號".to_string())));
+        assert_eq!(sheet.range.get((1, 1)), Some(&Data::Float(3.0)));
+
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend("學號\nB11012345\n".encode_utf16().flat_map(u16::to_le_bytes));
+        let sheet = csv_file(utf16).read_csv().unwrap();
+        assert_eq!(sheet.range.get((0, 0)), Some(&Data::String("學號".to_string())));
+    }
+
+    #[test]
+    fn read_csv_falls_back_to_big5() {
+        let (data, _, had_errors) = encoding_rs::BIG5.encode("學號,姓名\nB11012345,王小明\n");
+        assert!(!had_errors);
+        assert!(std::str::from_utf8(&data).is_err());
+        let sheet = csv_file(data.into_owned()).read_csv().unwrap();
+        assert_eq!(sheet.range.get((0, 0)), Some(&Data::String("學號".to_string())));
+        assert_eq!(sheet.range.get((1, 1)), Some(&Data::String("王小明".to_string())));
+    }
+
+    #[test]
+    fn read_csv_rejects_unknown_encoding_and_empty_files() {
+        assert!(csv_file(vec![0xFF, 0xFF, 0xFF]).read_csv().is_err());
+        assert!(csv_file(Vec::new()).read_csv().is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use actix_session::Session;
+use actix_web::{delete, get, patch, post, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use sqlx::MySqlPool;
+use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::etag::{optional_if_match, required_if_match};
+use crate::api::create::single_add_exam_score::insert_score;
+use crate::api::delete::delete_exam_score::{remove_scores, StudentSelection};
+use crate::api::modify::update_exam_score::{modify_scores, UpdateScores};
+use crate::api::query::query_score_info::{exam_scores_response, ScoreInfo};
+use utoipa::ToSchema;
+
+/// 寫入稽核紀錄的端點名稱
+const ENDPOINT: &str = "/api/v2/exam-sessions/{sn}/attendance";
+
+#[derive(Deserialize, Debug, ToSchema)]
+struct AddAttendance {
+    student_id: String,
+    num: String, // 答對題數、「請假」或「缺考」
+    #[serde(default)]
+    notes: String,
+}
+
+/// 取得場次內所有成績，回應的 ETag 用於 PATCH 與 DELETE 的 If-Match
+#[utoipa::path(
+    tag = "scores",
+    responses((status = 200, description = "場次內所有成績，ETag 標頭為目前的版本", body = Vec<ScoreInfo>)),
+)]
+#[get("/exam-sessions/{sn}/attendance")]
+async fn get_attendance(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session, Role::ReadOnly)?;
+    exam_scores_response(db_pool.get_ref(), path.into_inner()).await
+}
+
+/// 新增一位學生在此場次的成績
+#[utoipa::path(
+    tag = "scores",
+    responses((status = 201, description = "新增成功，Location 標頭為場次成績的位置")),
+)]
+#[post("/exam-sessions/{sn}/attendance")]
+async fn create_attendance(
+    path: web::Path<i32>,
+    data: web::Json<AddAttendance>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Staff)?;
+    let exam_session_sn = path.into_inner();
+    // 先確認場次存在，否則外鍵錯誤會被當成學號不存在
+    let exists = sqlx::query_scalar::<_, i32>("SELECT SN FROM ExamSessions WHERE SN = ?")
+        .bind(exam_session_sn)
+        .fetch_optional(db_pool.get_ref())
+        .await
+        .map_err(|err| ApiError::database(t!(QuerySessionFailed), err))?;
+    if exists.is_none() {
+        return Err(ApiError::new(ErrorCode::ExamSessionNotFound, t!(ExamSessionNotFound)));
+    }
+    insert_score(&db_pool, &session, ENDPOINT, exam_session_sn, &data.student_id, &data.num, &data.notes).await?;
+    Ok(HttpResponse::Created()
+        .insert_header(("Location", format!("/api/v2/exam-sessions/{}/attendance", exam_session_sn)))
+        .finish())
+}
+
+/// 修改場次內的成績，必須帶入 If-Match
+#[utoipa::path(
+    tag = "scores",
+    params(("If-Match" = String, Header, description = "讀取時回應的 ETag")),
+    responses((status = 200, description = "更新的筆數，ETag 標頭為新的版本", body = String)),
+)]
+#[patch("/exam-sessions/{sn}/attendance")]
+async fn patch_attendance(
+    path: web::Path<i32>,
+    mut data: web::Json<UpdateScores>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req.clone(), session.clone(), Role::Staff)?;
+    let if_match = required_if_match(&req)?;
+    modify_scores(&db_pool, &session, ENDPOINT, path.into_inner(), &if_match, &mut data.students).await
+}
+
+/// 刪除場次內選取的成績，有帶入 If-Match 時先比對版本
+#[utoipa::path(
+    tag = "scores",
+    params(("If-Match" = Option<String>, Header, description = "讀取時回應的 ETag，未帶入時不比對版本")),
+    responses((status = 200, description = "刪除的筆數，ETag 標頭為新的版本", body = String)),
+)]
+#[delete("/exam-sessions/{sn}/attendance")]
+async fn delete_attendance(
+    path: web::Path<i32>,
+    data: web::Json<StudentSelection>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req.clone(), session.clone(), Role::Admin)?;
+    // 未帶入時以 * 比對，代表不限版本
+    let if_match = optional_if_match(&req).unwrap_or_else(|| "*".to_string());
+    remove_scores(&db_pool, &session, ENDPOINT, path.into_inner(), &if_match, &data.students).await
+}
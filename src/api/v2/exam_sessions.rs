@@ -0,0 +1,99 @@
+use actix_session::Session;
+use actix_web::{delete, get, patch, post, web, HttpRequest, HttpResponse};
+use sqlx::MySqlPool;
+use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::audit::AuditTarget;
+use crate::api::etag::{check_version, etag_of, optional_if_match, required_if_match};
+use crate::api::create::add_exam::{insert_exam, AddExam};
+use crate::api::delete::delete_exam_info::remove_exam_session;
+use crate::api::modify::modify_exam_info::{modify_exam, ModifyData};
+use crate::api::query::query_exam_info::{exam_info_response, ExamInfo};
+
+/// 取得場次資料，回應的 ETag 用於 PATCH 的 If-Match
+#[utoipa::path(
+    tag = "exam_sessions",
+    responses((status = 200, description = "場次資料，ETag 標頭為目前的版本", body = ExamInfo)),
+)]
+#[get("/exam-sessions/{sn}")]
+async fn get_exam_session(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session, Role::ReadOnly)?;
+    exam_info_response(db_pool.get_ref(), path.into_inner()).await
+}
+
+/// 新增場次，內容與 /api/add_exam 相同，成功時回傳 201 與新資料的位置
+#[utoipa::path(
+    tag = "exam_sessions",
+    responses((status = 201, description = "新增成功，Location 標頭為新資料的位置")),
+)]
+#[post("/exam-sessions")]
+async fn create_exam_session(
+    data: web::Json<AddExam>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Staff)?;
+    let exam_session_sn = insert_exam(db_pool.get_ref(), &session, "/api/v2/exam-sessions", &data).await?;
+    Ok(HttpResponse::Created()
+        .insert_header(("Location", format!("/api/v2/exam-sessions/{}", exam_session_sn)))
+        .finish())
+}
+
+/// 修改場次資料，未提供的欄位不更新，必須帶入 If-Match
+#[utoipa::path(
+    tag = "exam_sessions",
+    params(("If-Match" = String, Header, description = "讀取時回應的 ETag")),
+    responses((status = 200, description = "修改成功，ETag 標頭為新的版本", body = String)),
+)]
+#[patch("/exam-sessions/{sn}")]
+async fn patch_exam_session(
+    path: web::Path<i32>,
+    data: web::Json<ModifyData>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req.clone(), session.clone(), Role::Staff)?;
+    let if_match = required_if_match(&req)?;
+    modify_exam(&db_pool, &session, "/api/v2/exam-sessions", path.into_inner(), &if_match, data.into_inner()).await
+}
+
+/// 刪除場次與場次內所有應考紀錄，有帶入 If-Match 時先比對場次資料的版本
+#[utoipa::path(
+    tag = "exam_sessions",
+    params(("If-Match" = Option<String>, Header, description = "讀取時回應的 ETag，未帶入時不比對版本")),
+    responses((status = 200, description = "已刪除場次與場次內所有成績", body = String)),
+)]
+#[delete("/exam-sessions/{sn}")]
+async fn delete_exam_session(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req.clone(), session.clone(), Role::Admin)?;
+    let exam_session_sn = path.into_inner();
+
+    let mut tx = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
+    let current = AuditTarget::ExamSession(exam_session_sn)
+        .snapshot_for_update(&mut tx)
+        .await
+        .map_err(|err| ApiError::database(t!(QueryFailed), err))?;
+    let Some(current) = current else {
+        return Err(ApiError::new(ErrorCode::ExamSessionNotFound, t!(ExamSessionNotFound)));
+    };
+    if let Some(if_match) = optional_if_match(&req) {
+        check_version(&if_match, &etag_of(&current))?;
+    }
+    remove_exam_session(tx, &session, "/api/v2/exam-sessions", exam_session_sn).await?;
+
+    Ok(HttpResponse::Ok().body(t!(DeleteExamSuccess)))
+}
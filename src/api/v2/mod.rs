@@ -0,0 +1,4 @@
+pub mod students;
+pub mod exam_sessions;
+pub mod attendance;
+pub mod scholarships;
\ No newline at end of file
@@ -0,0 +1,213 @@
+use actix_session::Session;
+use actix_web::{delete, get, patch, post, web, HttpRequest, HttpResponse};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::{MySqlConnection, MySqlPool, Row};
+use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::messages::t;
+use crate::api::audit::{write_audit_log, AuditTarget};
+use crate::api::etag::{check_version, etag_of, optional_if_match, required_if_match};
+use crate::api::modify::update_scholarship::{save_scholarship, StudentData};
+use utoipa::ToSchema;
+
+/// 寫入稽核紀錄的端點名稱
+const ENDPOINT: &str = "/api/v2/scholarships/{student_id}";
+
+/// 新增或修改時傳入的領獎資料，欄位名稱與 /api/update_scholarship 相同
+#[derive(Deserialize, Debug, ToSchema)]
+struct ScholarshipData {
+    correct_numbers: i32, // 對應 CorrectAnswersCount
+    money: i32,           // 對應 ScholarshipAmount
+    note: Option<String>, // 對應 Notes
+    received_date: String, // 格式為 "YYYY-MM-DD"
+}
+
+impl ScholarshipData {
+    fn into_student(self, student_id: String) -> StudentData {
+        StudentData {
+            student_id,
+            correct_numbers: self.correct_numbers,
+            money: self.money,
+            note: self.note,
+            claimed: true,
+            received_date: Some(self.received_date),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+struct ScholarshipRecord {
+    student_id: String,
+    correct_numbers: i32,
+    money: i32,
+    note: Option<String>,
+    received_date: NaiveDate,
+}
+
+/// 取得學生的領獎紀錄，回應的 ETag 用於 PATCH 與 DELETE 的 If-Match
+#[utoipa::path(
+    tag = "scholarships",
+    responses((status = 200, description = "領獎紀錄，ETag 標頭為目前的版本", body = ScholarshipRecord)),
+)]
+#[get("/scholarships/{student_id}")]
+async fn get_scholarship(
+    path: web::Path<String>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session, Role::ReadOnly)?;
+    let student_id = path.into_inner().to_ascii_uppercase();
+    let mut conn = db_pool.acquire().await?;
+    let row = sqlx::query(
+        r#"
+        SELECT StudentID, CorrectAnswersCount, ScholarshipAmount, Notes, ReceivedDate
+        FROM ScholarshipRecord WHERE StudentID = ?
+        "#,
+    )
+    .bind(&student_id)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|err| ApiError::database(t!(QueryScholarshipFailed), err))?;
+    let Some(row) = row else {
+        return Err(ApiError::not_found(t!(ScholarshipNotFound)));
+    };
+    let record = ScholarshipRecord {
+        student_id: row.try_get("StudentID")?,
+        correct_numbers: row.try_get("CorrectAnswersCount")?,
+        money: row.try_get("ScholarshipAmount")?,
+        note: row.try_get("Notes")?,
+        received_date: row.try_get("ReceivedDate")?,
+    };
+    let etag = current_etag(&mut conn, &student_id, false).await?;
+
+    let mut response = HttpResponse::Ok();
+    if let Some(etag) = etag {
+        response.insert_header(("ETag", etag));
+    }
+    Ok(response.json(record))
+}
+
+/// 新增學生的領獎紀錄，已有紀錄時回傳 409，成功時回傳 201 與新資料的位置
+#[utoipa::path(
+    tag = "scholarships",
+    responses((status = 201, description = "新增成功，Location 標頭為新資料的位置")),
+)]
+#[post("/scholarships/{student_id}")]
+async fn create_scholarship(
+    path: web::Path<String>,
+    data: web::Json<ScholarshipData>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Staff)?;
+    let student_id = path.into_inner().to_ascii_uppercase();
+
+    let mut tx = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
+    if current_etag(&mut tx, &student_id, true).await?.is_some() {
+        return Err(ApiError::new(ErrorCode::AlreadyExists, t!(ScholarshipExists)));
+    }
+    save_scholarship(&mut tx, &session, ENDPOINT, &data.into_inner().into_student(student_id.clone())).await?;
+    let etag = current_etag(&mut tx, &student_id, false).await?;
+    tx.commit().await.map_err(|err| ApiError::database(t!(CommitFailed), err))?;
+
+    let mut response = HttpResponse::Created();
+    response.insert_header(("Location", format!("/api/v2/scholarships/{}", student_id)));
+    if let Some(etag) = etag {
+        response.insert_header(("ETag", etag));
+    }
+    Ok(response.finish())
+}
+
+/// 修改學生的領獎紀錄，必須帶入 If-Match
+#[utoipa::path(
+    tag = "scholarships",
+    params(("If-Match" = String, Header, description = "讀取時回應的 ETag")),
+    responses((status = 200, description = "修改成功，ETag 標頭為新的版本", body = String)),
+)]
+#[patch("/scholarships/{student_id}")]
+async fn patch_scholarship(
+    path: web::Path<String>,
+    data: web::Json<ScholarshipData>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req.clone(), session.clone(), Role::Staff)?;
+    let if_match = required_if_match(&req)?;
+    let student_id = path.into_inner().to_ascii_uppercase();
+
+    let mut tx = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
+    let Some(current) = current_etag(&mut tx, &student_id, true).await? else {
+        return Err(ApiError::not_found(t!(ScholarshipNotFound)));
+    };
+    check_version(&if_match, &current)?;
+    save_scholarship(&mut tx, &session, ENDPOINT, &data.into_inner().into_student(student_id.clone())).await?;
+    let etag = current_etag(&mut tx, &student_id, false).await?;
+    tx.commit().await.map_err(|err| ApiError::database(t!(CommitFailed), err))?;
+
+    let mut response = HttpResponse::Ok();
+    if let Some(etag) = etag {
+        response.insert_header(("ETag", etag));
+    }
+    Ok(response.body(t!(UpdateSuccess)))
+}
+
+/// 刪除學生的領獎紀錄，有帶入 If-Match 時先比對版本
+#[utoipa::path(
+    tag = "scholarships",
+    operation_id = "v2_delete_scholarship",
+    params(("If-Match" = Option<String>, Header, description = "讀取時回應的 ETag，未帶入時不比對版本")),
+    responses((status = 200, description = "刪除的筆數", body = String)),
+)]
+#[delete("/scholarships/{student_id}")]
+async fn delete_scholarship(
+    path: web::Path<String>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req.clone(), session.clone(), Role::Admin)?;
+    let student_id = path.into_inner().to_ascii_uppercase();
+    let target = AuditTarget::Scholarship(&student_id);
+
+    let mut tx = db_pool.begin().await.map_err(|err| ApiError::database(t!(StartTransactionFailed), err))?;
+    let before = target
+        .snapshot_for_update(&mut tx)
+        .await
+        .map_err(|err| ApiError::database(t!(QueryScholarshipFailed), err))?;
+    let Some(current) = before.as_deref() else {
+        return Err(ApiError::not_found(t!(ScholarshipNotFound)));
+    };
+    if let Some(if_match) = optional_if_match(&req) {
+        check_version(&if_match, &etag_of(current))?;
+    }
+    let result = async {
+        sqlx::query("DELETE FROM ScholarshipRecord WHERE StudentID = ?")
+            .bind(&student_id)
+            .execute(&mut *tx)
+            .await?;
+        write_audit_log(&mut tx, &session, ENDPOINT, &target, before.clone()).await?;
+        tx.commit().await
+    }
+    .await;
+    result.map_err(|err| ApiError::database(t!(DeleteFailed), err))?;
+
+    Ok(HttpResponse::Ok().body(t!(ScholarshipDeletedCount, 1)))
+}
+
+/// 目前領獎紀錄的 ETag，沒有紀錄時回傳 None
+/// lock 為 true 時鎖定該筆資料直到交易結束
+async fn current_etag(conn: &mut MySqlConnection, student_id: &str, lock: bool) -> Result<Option<String>, ApiError> {
+    let target = AuditTarget::Scholarship(student_id);
+    let snapshot = if lock {
+        target.snapshot_for_update(conn).await
+    } else {
+        target.snapshot(conn).await
+    };
+    let snapshot = snapshot.map_err(|err| ApiError::database(t!(QueryScholarshipFailed), err))?;
+    Ok(snapshot.as_deref().map(etag_of))
+}
@@ -0,0 +1,86 @@
+use actix_session::Session;
+use actix_web::{delete, get, patch, post, web, HttpRequest, HttpResponse};
+use sqlx::MySqlPool;
+use crate::api::lib::is_authorization;
+use crate::api::role::Role;
+use crate::api::error::ApiError;
+use crate::api::etag::{optional_if_match, required_if_match};
+use crate::api::create::single_add_student::{insert_student, AddStudent};
+use crate::api::delete::delete_student::remove_student;
+use crate::api::modify::modify_student_info::{modify_student, ModifyData};
+use crate::api::query::student_id_search::{student_info_response, QueryResult};
+
+/// 取得學生資料，回應的 ETag 用於 PATCH 的 If-Match
+#[utoipa::path(
+    tag = "students",
+    responses((status = 200, description = "學生資料與應考紀錄，ETag 標頭為目前的版本", body = QueryResult)),
+)]
+#[get("/students/{student_id}")]
+async fn get_student(
+    path: web::Path<String>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session, Role::ReadOnly)?;
+    student_info_response(db_pool.get_ref(), &path.into_inner().to_ascii_uppercase()).await
+}
+
+/// 新增學生，內容與 /api/single_add_student 相同，成功時回傳 201 與新資料的位置
+#[utoipa::path(
+    tag = "students",
+    responses((status = 201, description = "新增成功，Location 標頭為新資料的位置")),
+)]
+#[post("/students")]
+async fn create_student(
+    data: web::Json<AddStudent>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req, session.clone(), Role::Staff)?;
+    let student_id = insert_student(db_pool.get_ref(), &session, "/api/v2/students", &data).await?;
+    Ok(HttpResponse::Created()
+        .insert_header(("Location", format!("/api/v2/students/{}", student_id)))
+        .finish())
+}
+
+/// 修改學生資料，未提供的欄位不更新，必須帶入 If-Match
+#[utoipa::path(
+    tag = "students",
+    params(("If-Match" = String, Header, description = "讀取時回應的 ETag")),
+    responses((status = 200, description = "修改成功，ETag 標頭為新的版本", body = String)),
+)]
+#[patch("/students/{student_id}")]
+async fn patch_student(
+    path: web::Path<String>,
+    data: web::Json<ModifyData>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req.clone(), session.clone(), Role::Staff)?;
+    let if_match = required_if_match(&req)?;
+    let student_id = path.into_inner().to_ascii_uppercase();
+    modify_student(&db_pool, &session, "/api/v2/students", &student_id, &if_match, data.into_inner()).await
+}
+
+/// 刪除學生與其應考、獎學金紀錄，有帶入 If-Match 時先比對版本
+#[utoipa::path(
+    tag = "students",
+    operation_id = "v2_delete_student",
+    params(("If-Match" = Option<String>, Header, description = "讀取時回應的 ETag，未帶入時不比對版本")),
+    responses((status = 200, description = "已刪除學生與其應考、獎學金紀錄", body = String)),
+)]
+#[delete("/students/{student_id}")]
+async fn delete_student(
+    path: web::Path<String>,
+    req: HttpRequest,
+    session: Session,
+    db_pool: web::Data<MySqlPool>,
+) -> Result<HttpResponse, ApiError> {
+    is_authorization(req.clone(), session.clone(), Role::Admin)?;
+    let if_match = optional_if_match(&req);
+    let student_id = path.into_inner().to_ascii_uppercase();
+    remove_student(db_pool.get_ref(), &session, "/api/v2/students", &student_id, if_match.as_deref()).await
+}
@@ -0,0 +1,287 @@
+use cpe_backend::api::audit::AuditActor;
+use cpe_backend::api::create::create_user::insert_user;
+use cpe_backend::api::create::mutiple_add_exam_score::import_exam_scores;
+use cpe_backend::api::create::mutiple_add_scholarship::import_scholarships;
+use cpe_backend::api::create::mutiple_add_student_info::import_student_info;
+use cpe_backend::api::error::ApiError;
+use cpe_backend::api::export::{ExportFormat, ExportTable};
+use cpe_backend::api::import::{ImportReport, RowAction};
+use cpe_backend::api::modify::reset_user_password::reset_password;
+use cpe_backend::api::pass_status::{recompute_pass_status, RecomputeFilter};
+use cpe_backend::api::query::query_passed_by_year::{passed_by_year_rows, passed_by_year_table};
+use cpe_backend::api::query::query_scholarship_excel::{scholarship_table, ScholarshipExcelForm};
+use cpe_backend::api::role::Role;
+use cpe_backend::api::session_registry::SessionRegistry;
+use cpe_backend::api::upload::{SheetSelection, UploadedFile};
+use cpe_backend::migrate;
+use sqlx::MySqlPool;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+const USAGE: &str = "\
+cpe-admin：不經過網頁直接操作資料庫的管理工具，連線設定與伺服器相同（.env 的 DATABASE_URL）
+
+用法：
+  cpe-admin create-user <使用者名稱> <readonly|staff|admin>
+  cpe-admin reset-password <使用者名稱>
+  cpe-admin import <students|scores|scholarships> <檔案> [--sheet <工作表>] [--all-sheets] [--dry-run]
+  cpe-admin export passed-by-year <學年度> <輸出檔案>
+  cpe-admin export scholarships <輸出檔案> [--academic-year <學年度>] [--exam-academic-year <學年度>] [--status <all|claimed|unclaimed>]
+  cpe-admin recompute-status [--student <學號>]... [--entrance-year-from <學年度>] [--entrance-year-to <學年度>] [--dry-run]
+  cpe-admin migrate
+
+密碼由標準輸入讀取一行，不放在參數中以免留在 shell 歷史紀錄
+匯出的格式依輸出檔案的副檔名決定：.xlsx、.csv 或 .ndjson";
+
+#[actix_web::main]
+async fn main() {
+    dotenv::dotenv().ok();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || matches!(args[0].as_str(), "help" | "-h" | "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+    if let Err(err) = run(&args).await {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+async fn run(args: &[String]) -> Result<(), String> {
+    let database_url = std::env::var("DATABASE_URL").map_err(|_| "DATABASE_URL must be set.".to_string())?;
+    let db_pool = MySqlPool::connect(&database_url)
+        .await
+        .map_err(|err| format!("無法連線到資料庫: {}", err))?;
+
+    match args[0].as_str() {
+        "create-user" => {
+            let [username, role] = positional::<2>(&args[1..])?;
+            let role = Role::from_db(role).ok_or_else(|| format!("未知的角色: {}", role))?;
+            let password = read_password()?;
+            let actor = AuditActor::new(admin_username(), "cpe-admin create-user");
+            insert_user(&db_pool, &actor, username, &password, role)
+                .await
+                .map_err(describe)?;
+            println!("已新增使用者 {}（{}）", username.trim(), role.as_str());
+        }
+        "reset-password" => {
+            let [username] = positional::<1>(&args[1..])?;
+            let password = read_password()?;
+            let actor = AuditActor::new(admin_username(), "cpe-admin reset-password");
+            reset_password(&db_pool, &actor, username, &password)
+                .await
+                .map_err(describe)?;
+            println!("已重設 {} 的密碼", username);
+            revoke_sessions(username).await?;
+        }
+        "import" => import(&db_pool, &args[1..]).await?,
+        "export" => export(&db_pool, &args[1..]).await?,
+        "recompute-status" => recompute_status(&db_pool, &args[1..]).await?,
+        "migrate" => migrate::run(&db_pool).await.map_err(|err| format!("資料庫遷移失敗: {}", err))?,
+        other => return Err(format!("未知的指令: {}\n\n{}", other, USAGE)),
+    }
+    Ok(())
+}
+
+/// 匯入與網頁上傳使用相同的檢查，任一列被拒絕時整批不寫入
+async fn import(db_pool: &MySqlPool, args: &[String]) -> Result<(), String> {
+    let options = Options::parse(args, &["--sheet"], &["--all-sheets", "--dry-run"])?;
+    let [kind, path] = positional::<2>(&options.positional)?;
+    let sheets = SheetSelection { sheet: options.value("--sheet"), all_sheets: options.flag("--all-sheets") };
+    let upload = UploadedFile {
+        filename: Path::new(path).file_name().unwrap_or_default().to_string_lossy().into_owned(),
+        data: std::fs::read(path).map_err(|err| format!("無法讀取 {}: {}", path, err))?,
+    };
+    let sheets = upload.read_sheets(&sheets).map_err(describe)?;
+
+    let actor = AuditActor::new(admin_username(), format!("cpe-admin import {}", kind));
+    let mut tx = db_pool.begin().await.map_err(|err| format!("無法開始交易: {}", err))?;
+    let report = match kind.as_str() {
+        "students" => import_student_info(&mut tx, &actor, &sheets).await,
+        "scores" => import_exam_scores(&mut tx, &actor, &sheets).await,
+        "scholarships" => import_scholarships(&mut tx, &actor, &sheets).await,
+        other => return Err(format!("未知的匯入類型: {}（可用 students、scores、scholarships）", other)),
+    };
+    let report = report.map_err(describe)?;
+    print_report(&report);
+
+    if report.rejected > 0 {
+        let _ = tx.rollback().await;
+        return Err(format!("有 {} 列被拒絕，未寫入任何資料", report.rejected));
+    }
+    if options.flag("--dry-run") {
+        let _ = tx.rollback().await;
+        println!("--dry-run：未寫入任何資料");
+        return Ok(());
+    }
+    tx.commit().await.map_err(|err| format!("提交失敗: {}", err))?;
+    println!("匯入完成");
+    Ok(())
+}
+
+async fn export(db_pool: &MySqlPool, args: &[String]) -> Result<(), String> {
+    let options = Options::parse(args, &["--academic-year", "--exam-academic-year", "--status"], &[])?;
+    let Some(report) = options.positional.first().cloned() else {
+        return Err(USAGE.to_string());
+    };
+    let (table, output) = match report.as_str() {
+        "passed-by-year" => {
+            let [_, year, output] = positional::<3>(&options.positional)?;
+            let year = year.parse().map_err(|_| format!("學年度格式錯誤: {}", year))?;
+            let rows = passed_by_year_rows(db_pool, year).await.map_err(describe)?;
+            (passed_by_year_table(&rows), output.clone())
+        }
+        "scholarships" => {
+            let [_, output] = positional::<2>(&options.positional)?;
+            let form = ScholarshipExcelForm {
+                academic_year: options.number("--academic-year")?,
+                exam_academic_year: options.number("--exam-academic-year")?,
+                status: options.value("--status").unwrap_or_else(|| "all".to_string()),
+            };
+            (scholarship_table(db_pool, &form).await.map_err(describe)?, output.clone())
+        }
+        other => return Err(format!("未知的報表: {}（可用 passed-by-year、scholarships）", other)),
+    };
+    write_table(&table, &output)?;
+    println!("已匯出 {}", output);
+    Ok(())
+}
+
+/// 重新計算通過狀態並列出有變動的學生
+async fn recompute_status(db_pool: &MySqlPool, args: &[String]) -> Result<(), String> {
+    let options = Options::parse(args, &["--student", "--entrance-year-from", "--entrance-year-to"], &["--dry-run"])?;
+    positional::<0>(&options.positional)?;
+    let filter = RecomputeFilter {
+        student_ids: options.values("--student"),
+        entrance_year_from: options.number("--entrance-year-from")?,
+        entrance_year_to: options.number("--entrance-year-to")?,
+    };
+    let actor = AuditActor::new(admin_username(), "cpe-admin recompute-status");
+    let report = recompute_pass_status(db_pool, &actor, &filter, options.flag("--dry-run"))
+        .await
+        .map_err(|err| format!("重新計算通過狀態失敗: {}", err))?;
+    for change in &report.changed {
+        let passed_date = change.pass_record.as_ref().map(|record| record.exam_date.to_string());
+        println!(
+            "{} {}: {} -> {}（{}，通過日期 {}）",
+            change.student_id,
+            change.name,
+            if change.was_passed { "通過" } else { "未通過" },
+            if change.is_passed { "通過" } else { "未通過" },
+            change.passing_criteria.as_deref().unwrap_or("-"),
+            passed_date.as_deref().unwrap_or("-"),
+        );
+    }
+    println!("已重新計算 {} 位學生的通過狀態，{} 位有變動", report.checked, report.changed.len());
+    if report.dry_run {
+        println!("--dry-run：未寫入任何資料");
+    }
+    Ok(())
+}
+
+/// 依副檔名選擇格式寫入檔案
+fn write_table(table: &ExportTable, output: &str) -> Result<(), String> {
+    let extension = Path::new(output).extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+    let format = match extension.to_ascii_lowercase().as_str() {
+        "xlsx" => ExportFormat::Xlsx,
+        "csv" => ExportFormat::Csv,
+        "ndjson" | "json" => ExportFormat::Json,
+        _ => return Err(format!("無法依副檔名判斷匯出格式: {}（可用 .xlsx、.csv、.ndjson）", output)),
+    };
+    let data = table.render(format).map_err(|err| format!("產生匯出檔案失敗: {}", err))?;
+    std::fs::write(output, data).map_err(|err| format!("無法寫入 {}: {}", output, err))
+}
+
+/// 稽核紀錄中的操作者記為「cpe-admin (系統使用者)」，與網頁上的使用者區分
+fn admin_username() -> String {
+    format!("cpe-admin ({})", std::env::var("USER").unwrap_or_default())
+}
+
+/// 重設密碼後撤銷此使用者的 session，沒有設定 REDIS_URL 時只提醒，無法撤銷時以錯誤結束
+async fn revoke_sessions(username: &str) -> Result<(), String> {
+    let Ok(redis_url) = std::env::var("REDIS_URL") else {
+        println!("未設定 REDIS_URL，已登入的 session 會在逾時後失效");
+        return Ok(());
+    };
+    let result = async { SessionRegistry::connect(&redis_url).await?.revoke_all(username).await }.await;
+    let count = result.map_err(|err| format!("無法撤銷 {} 已登入的 session，該使用者仍維持登入: {}", username, err))?;
+    println!("已撤銷 {} 個 session", count);
+    Ok(())
+}
+
+fn read_password() -> Result<String, String> {
+    eprint!("密碼: ");
+    std::io::stderr().flush().ok();
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line).map_err(|err| err.to_string())?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn print_report(report: &ImportReport) {
+    for row in report.rows.iter().filter(|row| row.action == RowAction::Reject) {
+        println!("{} 第 {} 列 {}: {}", row.sheet, row.row, row.key, row.message.as_deref().unwrap_or_default());
+    }
+    println!(
+        "新增 {}、更新 {}、略過 {}、拒絕 {}",
+        report.inserted, report.updated, report.skipped, report.rejected
+    );
+}
+
+fn describe(err: ApiError) -> String {
+    let mut message = err.message;
+    for detail in err.details {
+        message.push_str(&format!("\n  {}", detail.message));
+    }
+    message
+}
+
+/// 取出固定數量的位置參數
+fn positional<const N: usize>(args: &[String]) -> Result<&[String; N], String> {
+    args.try_into().map_err(|_| format!("參數數量錯誤\n\n{}", USAGE))
+}
+
+/// 位置參數與 `--name value`、`--flag` 形式的選項
+struct Options {
+    positional: Vec<String>,
+    values: Vec<(String, String)>,
+    flags: Vec<String>,
+}
+
+impl Options {
+    fn parse(args: &[String], value_options: &[&str], flag_options: &[&str]) -> Result<Options, String> {
+        let mut options = Options { positional: Vec::new(), values: Vec::new(), flags: Vec::new() };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if value_options.contains(&arg.as_str()) {
+                let value = args.next().ok_or_else(|| format!("{} 需要一個值", arg))?;
+                options.values.push((arg.clone(), value.clone()));
+            } else if flag_options.contains(&arg.as_str()) {
+                options.flags.push(arg.clone());
+            } else if arg.starts_with("--") {
+                return Err(format!("未知的選項: {}\n\n{}", arg, USAGE));
+            } else {
+                options.positional.push(arg.clone());
+            }
+        }
+        Ok(options)
+    }
+
+    fn value(&self, name: &str) -> Option<String> {
+        self.values.iter().find(|(key, _)| key == name).map(|(_, value)| value.clone())
+    }
+
+    /// 可重複的選項，例如多個 `--student`
+    fn values(&self, name: &str) -> Vec<String> {
+        self.values.iter().filter(|(key, _)| key == name).map(|(_, value)| value.clone()).collect()
+    }
+
+    fn number<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        self.value(name)
+            .map(|value| value.parse().map_err(|_| format!("{} 必須是數字: {}", name, value)))
+            .transpose()
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|flag| flag == name)
+    }
+}
@@ -0,0 +1,3 @@
+//! 伺服器（main.rs）與管理工具（bin/cpe-admin.rs）共用的 API 與資料庫程式碼
+pub mod api;
+pub mod migrate;
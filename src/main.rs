@@ -1,26 +1,40 @@
 use actix_cors::Cors;
 use actix_session::{storage::RedisSessionStore, SessionMiddleware, config::PersistentSession};
-use actix_web::{web::Data, App, HttpServer, cookie::{Key, time::Duration}};
+use actix_web::{web::{self, Data}, App, HttpServer, cookie::{Key, time::Duration}, middleware::from_fn};
 use sqlx::mysql::MySqlPool;
-mod api;
+use cpe_backend::migrate;
 use rustls::{Certificate, PrivateKey, ServerConfig};
 use std::fs::File;
 use std::io::BufReader;
-// use api::create_user::create_user;
-use api::{
+use cpe_backend::api::{
     login::login,
+    logout::logout,
+    login_throttle::LoginThrottle,
+    import::ImportPreviewStore,
+    download::{download, DownloadStore},
+    session_registry::{session_guard, SessionRegistry},
+    i18n::localize,
+    upload::cleanup_temp_files,
+    pass_status::spawn_recompute_job,
     check_session::check_session,
+    openapi::openapi_json,
     query::{
         excel_search_pass::excel_search_pass,
-        student_id_search::student_id_search,
+        student_id_search::{student_id_search, get_student_info},
         get_exam_session_info::get_exam_session_info,
         search_absent_and_excused::search_absent_and_excused,
         excel_search_absent::excel_search_absent,
-        query_exam_info::query_exam_info,
-        query_score_info::query_score_info,
+        query_exam_info::{query_exam_info, get_exam_info},
+        query_score_info::{query_score_info, get_exam_scores},
         scholarship_query_json::query_scholarship_json,
         query_scholarship_excel::query_scholarship_excel,
         query_passed_by_year::query_passed_by_year,
+        query_pass_criteria::query_pass_criteria,
+        query_users::query_users,
+        query_sessions::query_sessions,
+        query_login_locks::query_login_locks,
+        query_audit_log::query_audit_log,
+        query_lookup::query_lookup,
     },
     create::{
         add_exam::add_exam,
@@ -32,22 +46,38 @@ use api::{
         single_add_exam_score::single_add_exam_score,
         get_scholarship_template::get_scholarship_template,
         mutiple_add_scholarship::mutiple_add_scholarship,
+        add_pass_criteria::add_pass_criteria,
+        create_user::create_user,
+        add_lookup::add_lookup,
     },
     modify::{
-        modify_student_info::modify_student_info,
-        modify_exam_info::modify_exam_info,
-        modify_exam_score::modify_exam_score,
-        update_exam_score::update_exam_score,
+        modify_student_info::{modify_student_info, modify_student_info_by_id},
+        modify_exam_info::{modify_exam_info, modify_exam_info_by_sn},
+        modify_exam_score::{modify_exam_score, modify_exam_score_by_sn},
+        update_exam_score::{update_exam_score, update_exam_score_by_sn},
         update_scholarship::update_scholarship,
+        activate_pass_criteria::activate_pass_criteria,
+        recompute_pass_status::recompute_pass_status,
+        modify_user_role::modify_user_role,
+        disable_user::disable_user,
+        reset_user_password::reset_user_password,
+        unlock_login::unlock_login,
+        modify_language::modify_language,
+        modify_lookup::modify_lookup,
     },
     delete::{
         delete_student_info::delete_student_info,
         delete_exam_info::delete_exam_info,
-        delete_exam_score::delete_exam_score,
+        delete_exam_score::{delete_exam_score, delete_exam_score_by_sn},
         delete_scholarship_records::delete_scholarship_records,
         delete_scholarship::delete_scholarship,
         delete_student::delete_student,
-    }
+        delete_pass_criteria::delete_pass_criteria,
+        delete_user::delete_user,
+        revoke_session::revoke_session,
+        delete_lookup::delete_lookup,
+    },
+    v2::{students, exam_sessions, attendance, scholarships},
 };
 
 /// 從指定路徑讀取憑證（.pem 格式）
@@ -76,18 +106,45 @@ fn load_private_key(path: &str) -> PrivateKey {
 async fn main() -> Result<(), std::io::Error> {
     dotenv::dotenv().ok();
     let datacase_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set.");
+    // `cpe_backend migrate`：只套用資料庫遷移，不啟動伺服器
+    if std::env::args().nth(1).as_deref() == Some("migrate") {
+        let db_pool = MySqlPool::connect(&datacase_url)
+            .await
+            .expect("Failed to connect to the database.");
+        migrate::run(&db_pool).await.expect("資料庫遷移失敗");
+        return Ok(());
+    }
     let redis_url = std::env::var("REDIS_URL").expect("REDIS_URL must be set");
     let ip = std::env::var("IP").expect("IP must be set.");
     let port = std::env::var("PORT").expect("PORT must be set.");
     let cert_path = std::env::var("CERT").expect("CERT_PATH must be set.");
     let key_path = std::env::var("KEY").expect("KEY_PATH must be set.");
+    // 清除上次執行殘留的上傳與匯出暫存檔
+    cleanup_temp_files();
     let redis_store = RedisSessionStore::new(&redis_url)
         .await
         .expect("Failed to connect to Redis");
+    let session_registry = SessionRegistry::connect(&redis_url)
+        .await
+        .expect("Failed to connect to Redis");
+    let login_throttle = LoginThrottle::connect(&redis_url)
+        .await
+        .expect("Failed to connect to Redis");
+    let import_previews = ImportPreviewStore::connect(&redis_url)
+        .await
+        .expect("Failed to connect to Redis");
+    // 匯出檔案的下載網址，逾時的檔案在背景定期刪除
+    let download_store = DownloadStore::default();
+    download_store.spawn_cleanup();
     let db_pool = MySqlPool::connect(&datacase_url)
         .await
         .expect("Failed to connect to the database.");
-    
+    if migrate::auto_migrate_enabled() {
+        migrate::run(&db_pool).await.expect("資料庫遷移失敗");
+    }
+    // 定期重新計算所有學生的通過狀態
+    spawn_recompute_job(db_pool.clone());
+
     // 讀取證書與私鑰檔案（請確保 cert.pem 與 key.pem 存在）
     let certs = load_certs(&cert_path);
     let key = load_private_key(&key_path);
@@ -102,12 +159,14 @@ async fn main() -> Result<(), std::io::Error> {
     println!("Server is running at https://{}:{}...", ip, port);
     HttpServer::new(move || {
         App::new()
+            .wrap(from_fn(session_guard)) // 必須在 SessionMiddleware 內層才能取得 session
+            .wrap(from_fn(localize)) // 依使用者設定或 Accept-Language 決定回應語言，包住 session_guard 讓它的錯誤訊息也依語言回應
             .wrap(
                 Cors::default()
                     .allowed_origin("https://140.128.101.24:8080") // 允許前端的域名
-                    .allowed_methods(vec!["GET", "POST", "OPTIONS"]) // 允許的方法
-                    .allowed_headers(vec!["Content-Type", "Authorization", "X-CSRF-Token"]) // 允許的請求頭
-                    .expose_headers(vec!["X-CSRF-Token"]) //沒有允許暴露的話前端是無法讀取的
+                    .allowed_methods(vec!["GET", "POST", "PATCH", "DELETE", "OPTIONS"]) // 允許的方法
+                    .allowed_headers(vec!["Content-Type", "Authorization", "X-CSRF-Token", "If-Match"]) // 允許的請求頭
+                    .expose_headers(vec!["X-CSRF-Token", "X-User-Role", "Retry-After", "X-Import-Rejected", "Content-Disposition", "Content-Language", "ETag"]) //沒有允許暴露的話前端是無法讀取的
                     .supports_credentials(), // 支持附帶 Cookie
             )
             .wrap(
@@ -122,8 +181,14 @@ async fn main() -> Result<(), std::io::Error> {
                     .build(),
             )
             .app_data(Data::new(db_pool.clone()))
+            .app_data(Data::new(session_registry.clone()))
+            .app_data(Data::new(login_throttle.clone()))
+            .app_data(Data::new(import_previews.clone()))
+            .app_data(Data::new(download_store.clone()))
             .service(login)
+            .service(logout)
             .service(check_session)
+            .service(download)
             .service(excel_search_pass)
             .service(student_id_search)
             .service(get_exam_session_info)
@@ -154,7 +219,56 @@ async fn main() -> Result<(), std::io::Error> {
             .service(update_scholarship)
             .service(delete_student)
             .service(query_passed_by_year)
-            // .service(create_user) //要創建新使用者在打開
+            .service(query_pass_criteria)
+            .service(add_pass_criteria)
+            .service(activate_pass_criteria)
+            .service(recompute_pass_status)
+            .service(delete_pass_criteria)
+            .service(query_users)
+            .service(create_user)
+            .service(modify_user_role)
+            .service(disable_user)
+            .service(reset_user_password)
+            .service(delete_user)
+            .service(query_sessions)
+            .service(revoke_session)
+            .service(query_login_locks)
+            .service(unlock_login)
+            .service(modify_language)
+            .service(query_audit_log)
+            .service(query_lookup)
+            .service(add_lookup)
+            .service(modify_lookup)
+            .service(delete_lookup)
+            .service(get_student_info)
+            .service(modify_student_info_by_id)
+            .service(get_exam_info)
+            .service(modify_exam_info_by_sn)
+            .service(get_exam_scores)
+            .service(update_exam_score_by_sn)
+            .service(modify_exam_score_by_sn)
+            .service(delete_exam_score_by_sn)
+            .service(openapi_json)
+            // 以 REST 動詞操作資源的 v2 路由，與上面的舊路由並存
+            .service(
+                web::scope("/api/v2")
+                    .service(students::get_student)
+                    .service(students::create_student)
+                    .service(students::patch_student)
+                    .service(students::delete_student)
+                    .service(exam_sessions::get_exam_session)
+                    .service(exam_sessions::create_exam_session)
+                    .service(exam_sessions::patch_exam_session)
+                    .service(exam_sessions::delete_exam_session)
+                    .service(attendance::get_attendance)
+                    .service(attendance::create_attendance)
+                    .service(attendance::patch_attendance)
+                    .service(attendance::delete_attendance)
+                    .service(scholarships::get_scholarship)
+                    .service(scholarships::create_scholarship)
+                    .service(scholarships::patch_scholarship)
+                    .service(scholarships::delete_scholarship),
+            )
     })
     .bind_rustls(format!("{}:{}", ip, port), config)?
     .run()
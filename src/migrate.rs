@@ -0,0 +1,40 @@
+use sqlx::migrate::{Migrate, MigrateError, Migrator};
+use sqlx::MySqlPool;
+use std::collections::HashSet;
+
+/// migrations/ 中的 SQL 在編譯時嵌入執行檔，部署時不需要另外複製
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// 啟動時是否自動套用遷移，設定 `AUTO_MIGRATE=false` 可關閉，改用 `cpe_backend migrate` 手動執行
+pub fn auto_migrate_enabled() -> bool {
+    match std::env::var("AUTO_MIGRATE") {
+        Ok(value) => !matches!(value.trim().to_ascii_lowercase().as_str(), "false" | "0" | "no" | "off"),
+        Err(_) => true,
+    }
+}
+
+/// 套用尚未執行的資料庫遷移，已套用的版本記錄在 _sqlx_migrations 資料表
+pub async fn run(db_pool: &MySqlPool) -> Result<(), MigrateError> {
+    let mut conn = db_pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    let applied: HashSet<i64> = conn
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|migration| migration.version)
+        .collect();
+    drop(conn);
+
+    MIGRATOR.run(db_pool).await?;
+    let pending: Vec<_> = MIGRATOR
+        .iter()
+        .filter(|migration| !applied.contains(&migration.version))
+        .collect();
+    if pending.is_empty() {
+        println!("資料庫結構已是最新版本");
+    }
+    for migration in pending {
+        println!("已套用資料庫遷移 {:04} {}", migration.version, migration.description);
+    }
+    Ok(())
+}